@@ -0,0 +1,48 @@
+//! Blinks the on-board LED from one async task while another reports the
+//! uptime over serial.
+//!
+//! Build with `cargo run --example async_blink --features executor`.
+#![no_std]
+#![no_main]
+
+use arduino_uno::hal::port::{mode, portb};
+use arduino_uno::prelude::*;
+use arduino_uno_micros::time::U32Ext;
+use arduino_uno_micros::{executor, micros_init, millis};
+use panic_halt as _;
+
+async fn blink(mut led: portb::PB5<mode::Output>) {
+    loop {
+        led.toggle().void_unwrap();
+        executor::delay(500.ms()).await;
+    }
+}
+
+async fn report(mut serial: arduino_uno::Serial<mode::Floating>) {
+    loop {
+        ufmt::uwriteln!(&mut serial, "Up for {} ms\r", millis()).void_unwrap();
+        executor::delay(1.s()).await;
+    }
+}
+
+#[arduino_uno::entry]
+fn main() -> ! {
+    let dp = arduino_uno::Peripherals::take().unwrap();
+
+    let mut pins = arduino_uno::Pins::new(dp.PORTB, dp.PORTC, dp.PORTD);
+
+    let serial = arduino_uno::Serial::new(
+        dp.USART0,
+        pins.d0,
+        pins.d1.into_output(&mut pins.ddr),
+        57600.into_baudrate(),
+    );
+    let led = pins.d13.into_output(&mut pins.ddr);
+
+    micros_init(&dp.TC0);
+
+    // Enable interrupts globally
+    unsafe { avr_device::interrupt::enable() };
+
+    executor::run(&mut [&mut blink(led), &mut report(serial)])
+}
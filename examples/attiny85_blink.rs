@@ -0,0 +1,33 @@
+//! Blinks the LED of an ATtiny85 based Trinket once a second without
+//! blocking, using the millisecond counter.
+//!
+//! Build with
+//! `cargo build --release --example attiny85_blink --no-default-features --features attiny85 --target avr-attiny85.json`.
+#![no_std]
+#![no_main]
+
+use arduino_uno_micros::{micros_init, millis};
+use panic_halt as _;
+use trinket::prelude::*;
+
+#[trinket::entry]
+fn main() -> ! {
+    let dp = trinket::Peripherals::take().unwrap();
+
+    let mut pins = trinket::Pins::new(dp.PORTB);
+    let mut led = pins.d1.into_output(&mut pins.ddr);
+
+    micros_init(&dp.TC0);
+
+    // Enable interrupts globally
+    unsafe { avr_device::interrupt::enable() };
+
+    let mut last = millis();
+    loop {
+        let now = millis();
+        if now.wrapping_sub(last) >= 500 {
+            led.toggle().void_unwrap();
+            last = last.wrapping_add(500);
+        }
+    }
+}
@@ -0,0 +1,304 @@
+//! An interactive shell over serial for exploring the time base.
+//!
+//! Connect a terminal at 57600 baud and type `help`.  Panics are reported
+//! over the same port, and the LED on D13 blinks a heartbeat as long as the
+//! main loop and the time base are running.
+#![no_std]
+#![no_main]
+
+use arduino_uno_micros::cli::{Args, Error, Input, LineEditor};
+use arduino_uno_micros::heartbeat::{Heartbeat, Pattern};
+use arduino_uno_micros::isr_metrics::{self, Metrics};
+use arduino_uno_micros::power::{self, Keep};
+use arduino_uno_micros::profile::{self, Span};
+use arduino_uno_micros::selftest;
+use arduino_uno_micros::serial_tx::SerialTx;
+use arduino_uno_micros::session::SessionTimer;
+use arduino_uno_micros::telemetry::{self, Format};
+use arduino_uno_micros::time::{deadline_reached, Duration, Instant};
+use arduino_uno_micros::trace::TraceBuffer;
+use arduino_uno_micros::uptime::UptimeLog;
+use arduino_uno_micros::watchdog::{Timeout, Watchdog};
+use arduino_uno_micros::{info, micros64, micros_init, missed_ticks, now, reset, serial_rx, warn};
+use ufmt::uwriteln;
+
+const HELP: &str = "commands:\r
+  uptime      time since boot and since the last reset, in days,\r
+              hours, minutes and seconds and in raw microseconds\r
+  reset       restart the session timer from zero\r
+  lifetime    total time run across resets\r
+  rate <ms>   report every <ms>, 0 to stop\r
+  format <f>  report as text, the uptime, or as csv or binary\r
+              telemetry: loop count (0) and slowest loop in us (1)\r
+  stats       main loop timing since the last call\r
+  isr         timer ISR latency since the last call\r
+  profile     time taken by each command since the last call\r
+  trace       recent commands and slow loops, oldest first\r
+  gaps <on|off>\r
+              start or stop timing the gaps between received bytes\r
+  gaps        the gaps between received bytes since the last call\r
+  panic       test the panic handler\r
+  stall       stop the timer to test the watchdog\r
+  help        this text\r
+";
+
+// The trace records, with the line length or the loop time in ms as the
+// argument.
+const TRACE_COMMAND: u8 = 1;
+const TRACE_SLOW_LOOP: u8 = 2;
+const SLOW_LOOP: Duration = Duration::from_millis(10);
+
+static TRACE: TraceBuffer<32> = TraceBuffer::new();
+
+// Where the lifetime uptime is kept in EEPROM, and how often it is saved.
+const LIFETIME_BASE: u16 = 0;
+const LIFETIME_SLOTS: u16 = 16;
+const LIFETIME_INTERVAL: Duration = Duration::from_secs(600);
+
+struct Stats {
+    loops: u32,
+    slowest: Duration,
+    since: Instant,
+}
+
+impl Stats {
+    fn new() -> Self {
+        Stats {
+            loops: 0,
+            slowest: Duration::from_micros(0),
+            since: now(),
+        }
+    }
+
+    fn record(&mut self, elapsed: Duration) {
+        self.loops += 1;
+        if elapsed > self.slowest {
+            self.slowest = elapsed;
+        }
+    }
+}
+
+#[arduino_uno::entry]
+fn main() -> ! {
+    let cause = reset::take_cause();
+    let dp = arduino_uno::Peripherals::take().unwrap();
+    let mut pins = arduino_uno::Pins::new(dp.PORTB, dp.PORTC, dp.PORTD);
+    let mut watchdog = Watchdog::start(dp.WDT, Timeout::S2);
+
+    serial_rx::start(&dp.USART0, 57600);
+    let mut serial = SerialTx::new(dp.USART0, 57600);
+
+    let tc0 = dp.TC0;
+    micros_init(&tc0);
+    // Only the time base and the serial port are needed.
+    power::disable_unused(Keep::default());
+
+    let eeprom = dp.EEPROM;
+    let mut lifetime = UptimeLog::restore(&eeprom, LIFETIME_BASE, LIFETIME_SLOTS);
+
+    // Enable interrupts globally
+    unsafe { avr_device::interrupt::enable() };
+
+    let mut heartbeat = Heartbeat::new(pins.d13.into_output(&mut pins.ddr), Pattern::HEARTBEAT);
+    let mut editor = LineEditor::<32>::new();
+    let mut rate: Option<Duration> = None;
+    // `None` for text reports.
+    let mut format: Option<Format> = None;
+    let mut next_report = now();
+    let mut report = Stats::new();
+    let mut stats = Stats::new();
+    let mut last_loop = now();
+    let mut session = SessionTimer::new();
+
+    reset::report(&mut serial, cause).ok();
+    lifetime.report(&mut serial).ok();
+    selftest::selftest().report(&mut serial).ok();
+    info!(&mut serial, "ready").ok();
+    serial.write_bytes(b"> ");
+    loop {
+        watchdog.feed();
+        heartbeat.poll();
+        let loop_start = now();
+        let elapsed = loop_start - last_loop;
+        last_loop = loop_start;
+        stats.record(elapsed);
+        if elapsed > SLOW_LOOP {
+            TRACE.record(
+                TRACE_SLOW_LOOP,
+                elapsed.as_millis().min(u16::MAX as u32) as u16,
+            );
+        }
+        report.record(elapsed);
+        lifetime.poll(&eeprom, LIFETIME_INTERVAL);
+
+        if let Some(rate) = rate {
+            if deadline_reached(next_report.as_micros()) {
+                next_report += rate;
+                match format {
+                    None => print_uptime(&mut serial, "up", micros64()),
+                    Some(format) => {
+                        let loops = report.loops as i32;
+                        let slowest = report.slowest.as_micros() as i32;
+                        telemetry::send_value(&mut serial, format, 0, loop_start, loops).ok();
+                        telemetry::send_value(&mut serial, format, 1, loop_start, slowest).ok();
+                    }
+                }
+                report = Stats::new();
+            }
+        }
+
+        let byte = match serial_rx::read() {
+            Some((_, byte)) => byte,
+            None => continue,
+        };
+        let line = match editor.feed(byte) {
+            Input::None => continue,
+            Input::Echo(byte) => {
+                serial.write_byte(byte);
+                continue;
+            }
+            Input::Erase => {
+                serial.write_bytes(b"\x08 \x08");
+                continue;
+            }
+            Input::Line(line) => line,
+        };
+        serial.write_bytes(b"\r\n");
+
+        TRACE.record(TRACE_COMMAND, line.len() as u16);
+        let _span = Span::new("command");
+        let mut args = Args::new(line);
+        match args.next_str() {
+            None => {}
+            Some("uptime") => {
+                print_uptime(&mut serial, "up", micros64());
+                print_uptime(&mut serial, "session", session.micros());
+            }
+            Some("reset") => {
+                // The counters since boot keep running for everything else.
+                session.reset();
+                stats = Stats::new();
+                report = Stats::new();
+                info!(&mut serial, "session reset").ok();
+            }
+            Some("rate") => match args.next_u32() {
+                Ok(0) => rate = None,
+                Ok(ms) => match Duration::checked_from_millis(ms) {
+                    Some(period) => {
+                        rate = Some(period);
+                        next_report = now();
+                    }
+                    None => serial.write_bytes(b"rate too long\r\n"),
+                },
+                Err(Error::Missing) => serial.write_bytes(b"usage: rate <ms>\r\n"),
+                Err(Error::Invalid) => serial.write_bytes(b"not a number\r\n"),
+            },
+            Some("format") => match args.next_str() {
+                Some("text") => format = None,
+                Some(name) if Format::from_name(name).is_some() => {
+                    format = Format::from_name(name);
+                }
+                _ => serial.write_bytes(b"usage: format <text|csv|binary>\r\n"),
+            },
+            Some("lifetime") => {
+                uwriteln!(&mut serial, "{} s\r", lifetime.total_secs()).ok();
+            }
+            Some("stats") => {
+                let window = now() - stats.since;
+                uwriteln!(
+                    &mut serial,
+                    "{} loops in {}, slowest {}\r",
+                    stats.loops,
+                    window,
+                    stats.slowest
+                )
+                .ok();
+                uwriteln!(
+                    &mut serial,
+                    "rx: {} dropped, {} errors\r",
+                    serial_rx::dropped(),
+                    serial_rx::errors()
+                )
+                .ok();
+                stats = Stats::new();
+            }
+            Some("stall") => {
+                // Feeding the watchdog doesn't help once the timer stops.
+                avr_device::interrupt::disable();
+                loop {
+                    watchdog.feed();
+                }
+            }
+            Some("gaps") => match args.next_str() {
+                Some("on") => serial_rx::record_gaps(true),
+                Some("off") => serial_rx::record_gaps(false),
+                None => {
+                    serial_rx::gaps().report(&mut serial).ok();
+                    serial_rx::reset_gaps();
+                }
+                Some(_) => serial.write_bytes(b"usage: gaps [on|off]\r\n"),
+            },
+            Some("panic") => panic!("requested from the shell"),
+            Some("isr") => {
+                let metrics = isr_metrics::snapshot();
+                uwriteln!(
+                    &mut serial,
+                    "{} runs, worst latency {} us, {} missed\r",
+                    metrics.runs,
+                    metrics.worst_latency_micros(),
+                    missed_ticks()
+                )
+                .ok();
+                for (bucket, &runs) in metrics.histogram.iter().enumerate() {
+                    uwriteln!(
+                        &mut serial,
+                        "  >= {} counts: {}\r",
+                        Metrics::bucket_start(bucket),
+                        runs
+                    )
+                    .ok();
+                }
+                isr_metrics::reset();
+            }
+            Some("profile") => {
+                profile::report(&mut serial).ok();
+                profile::reset();
+            }
+            Some("trace") => {
+                TRACE.dump(&mut serial).ok();
+            }
+            Some("help") => serial.write_bytes(HELP.as_bytes()),
+            Some(command) => {
+                warn!(&mut serial, "unknown command {}, try help", command).ok();
+            }
+        }
+        serial.write_bytes(b"> ");
+    }
+}
+
+fn print_uptime(serial: &mut SerialTx, label: &str, micros: u64) {
+    // 64-bit microseconds don't wrap in practice, so the breakdown holds
+    // however long the board runs.
+    let secs = micros / 1_000_000;
+    let ms = (micros / 1000 % 1000) as u16;
+    let hours = (secs / 3600 % 24) as u8;
+    let minutes = (secs / 60 % 60) as u8;
+    let seconds = (secs % 60) as u8;
+    uwriteln!(
+        serial,
+        "{} {}d {}{}:{}{}:{}{}.{}{}{} ({} us)\r",
+        label,
+        (secs / 86_400) as u32,
+        hours / 10,
+        hours % 10,
+        minutes / 10,
+        minutes % 10,
+        seconds / 10,
+        seconds % 10,
+        ms / 100,
+        ms / 10 % 10,
+        ms % 10,
+        micros
+    )
+    .ok();
+}
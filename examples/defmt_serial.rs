@@ -0,0 +1,39 @@
+//! Logs the loop timing with `defmt` over serial.
+//!
+//! Build with `RUSTFLAGS="-C link-arg=-Tdefmt.x"` and decode the output on
+//! the host by piping the serial port at 57600 baud into
+//! `defmt-print -e target/avr-atmega328p/debug/examples/defmt_serial.elf`.
+#![no_std]
+#![no_main]
+
+use arduino_uno_micros::delay::delay_micros;
+use arduino_uno_micros::serial_tx::SerialTx;
+use arduino_uno_micros::time::Duration;
+use arduino_uno_micros::{defmt_serial, micros_init, now};
+use panic_halt as _;
+
+#[arduino_uno::entry]
+fn main() -> ! {
+    let dp = arduino_uno::Peripherals::take().unwrap();
+
+    defmt_serial::init(SerialTx::new(dp.USART0, 57600));
+
+    micros_init(&dp.TC0);
+
+    // Enable interrupts globally
+    unsafe { avr_device::interrupt::enable() };
+
+    defmt::info!("started");
+    let mut slowest = Duration::from_micros(0);
+    loop {
+        let start = now();
+        delay_micros(100_000);
+        let elapsed = now() - start;
+        if elapsed > slowest {
+            slowest = elapsed;
+            defmt::warn!("slowest delay so far: {=u32} us", elapsed.as_micros());
+        } else {
+            defmt::info!("delay took {=u32} us", elapsed.as_micros());
+        }
+    }
+}
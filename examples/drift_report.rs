@@ -0,0 +1,50 @@
+//! Reports the drift of the time base against a 32.768 kHz watch crystal on
+//! Timer2 every ten seconds.
+//!
+//! The watch crystal shares its pins with the main crystal, so this needs an
+//! ATmega328P running from its internal oscillator (`clock-8mhz`).
+#![no_std]
+#![no_main]
+
+use arduino_uno::prelude::*;
+use arduino_uno_micros::drift::DriftEstimator;
+use arduino_uno_micros::{micros_init, rtc};
+use panic_halt as _;
+
+#[arduino_uno::entry]
+fn main() -> ! {
+    let dp = arduino_uno::Peripherals::take().unwrap();
+
+    let mut pins = arduino_uno::Pins::new(dp.PORTB, dp.PORTC, dp.PORTD);
+
+    let mut serial = arduino_uno::Serial::new(
+        dp.USART0,
+        pins.d0,
+        pins.d1.into_output(&mut pins.ddr),
+        57600.into_baudrate(),
+    );
+
+    micros_init(&dp.TC0);
+    rtc::rtc_init(&dp.TC2);
+
+    // Enable interrupts globally
+    unsafe { avr_device::interrupt::enable() };
+
+    let mut estimator = DriftEstimator::new();
+    let mut last = rtc::seconds();
+    loop {
+        // Sample right after each RTC second begins.
+        let seconds = rtc::seconds();
+        if seconds == last {
+            continue;
+        }
+        last = seconds;
+        estimator.sample_rtc();
+
+        if seconds % 10 == 0 {
+            if let Some(ppb) = estimator.ppb() {
+                ufmt::uwriteln!(&mut serial, "{} ppb after {} s\r", ppb, seconds).void_unwrap();
+            }
+        }
+    }
+}
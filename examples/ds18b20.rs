@@ -0,0 +1,72 @@
+//! Prints the temperature from a DS18B20 on pin 2 every second.
+//!
+//! The sensor's data line needs a 4.7 kOhm pull-up to 5 V.
+#![no_std]
+#![no_main]
+
+use arduino_uno::prelude::*;
+use arduino_uno_micros::delay::delay_micros;
+use arduino_uno_micros::micros_init;
+use arduino_uno_micros::one_wire::{crc8, OneWire};
+use panic_halt as _;
+
+const CONVERT_T: u8 = 0x44;
+const READ_SCRATCHPAD: u8 = 0xbe;
+
+#[arduino_uno::entry]
+fn main() -> ! {
+    let dp = arduino_uno::Peripherals::take().unwrap();
+
+    let mut pins = arduino_uno::Pins::new(dp.PORTB, dp.PORTC, dp.PORTD);
+
+    let mut serial = arduino_uno::Serial::new(
+        dp.USART0,
+        pins.d0,
+        pins.d1.into_output(&mut pins.ddr),
+        57600.into_baudrate(),
+    );
+
+    let mut bus = OneWire::new(pins.d2.into_tri_state(&mut pins.ddr));
+
+    micros_init(&dp.TC0);
+
+    // Enable interrupts globally
+    unsafe { avr_device::interrupt::enable() };
+
+    loop {
+        if !bus.select(None) {
+            ufmt::uwriteln!(&mut serial, "No sensor found\r").void_unwrap();
+            delay_micros(1_000_000);
+            continue;
+        }
+        bus.write_byte(CONVERT_T);
+        // A 12 bit conversion takes up to 750 ms.
+        delay_micros(750_000);
+
+        bus.select(None);
+        bus.write_byte(READ_SCRATCHPAD);
+        let mut scratchpad = [0; 9];
+        bus.read(&mut scratchpad);
+        if crc8(&scratchpad[..8]) != scratchpad[8] {
+            ufmt::uwriteln!(&mut serial, "CRC error\r").void_unwrap();
+            continue;
+        }
+
+        // The temperature is in sixteenths of a degree.
+        let raw = i16::from_le_bytes([scratchpad[0], scratchpad[1]]);
+        let centi = raw as i32 * 100 / 16;
+        let sign = if centi < 0 { "-" } else { "" };
+        let centi = centi.abs();
+        ufmt::uwriteln!(
+            &mut serial,
+            "{}{}.{}{} C\r",
+            sign,
+            centi / 100,
+            centi / 10 % 10,
+            centi % 10
+        )
+        .void_unwrap();
+
+        delay_micros(250_000);
+    }
+}
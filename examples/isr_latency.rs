@@ -0,0 +1,44 @@
+//! Prints the latency histogram of the timer ISR every second.
+//!
+//! Timer0 runs with a prescaler of 1, so each count is a CPU cycle, and
+//! toggles D6 at every compare match for a scope to compare against.  The
+//! blocking serial writes leave the interrupts alone, so the histogram shows
+//! the latency of the time base itself; add another library's interrupts to
+//! see how they delay it.
+#![no_std]
+#![no_main]
+
+use arduino_uno::prelude::*;
+use arduino_uno_micros::time::{deadline_reached, Duration};
+use arduino_uno_micros::{latency, micros_init_with, now, TimerConfig};
+use panic_halt as _;
+
+#[arduino_uno::entry]
+fn main() -> ! {
+    let dp = arduino_uno::Peripherals::take().unwrap();
+
+    let mut pins = arduino_uno::Pins::new(dp.PORTB, dp.PORTC, dp.PORTD);
+
+    let mut serial = arduino_uno::Serial::new(
+        dp.USART0,
+        pins.d0,
+        pins.d1.into_output(&mut pins.ddr),
+        57600.into_baudrate(),
+    );
+
+    // 256 cycles, 16 us, per interrupt.
+    micros_init_with(&dp.TC0, TimerConfig::<1, 256>::new());
+    latency::start(&dp.TC0);
+
+    // Enable interrupts globally
+    unsafe { avr_device::interrupt::enable() };
+
+    let mut next_report = now() + Duration::from_secs(1);
+    loop {
+        if deadline_reached(next_report.as_micros()) {
+            next_report += Duration::from_secs(1);
+            latency::report(&mut serial).void_unwrap();
+            ufmt::uwriteln!(&mut serial, "\r").void_unwrap();
+        }
+    }
+}
@@ -0,0 +1,53 @@
+//! Plots how late a 20 ms periodic loop wakes up, for the Arduino IDE's
+//! Serial Plotter.
+//!
+//! Open the plotter at 57600 baud to see a trace of the wake-up latency and
+//! one of the measured period.
+#![no_std]
+#![no_main]
+
+use arduino_uno::prelude::*;
+use arduino_uno_micros::time::{deadline_reached, Duration};
+use arduino_uno_micros::{micros_init, now, telemetry};
+use panic_halt as _;
+
+const PERIOD: Duration = Duration::from_millis(20);
+
+#[arduino_uno::entry]
+fn main() -> ! {
+    let dp = arduino_uno::Peripherals::take().unwrap();
+
+    let mut pins = arduino_uno::Pins::new(dp.PORTB, dp.PORTC, dp.PORTD);
+
+    let mut serial = arduino_uno::Serial::new(
+        dp.USART0,
+        pins.d0,
+        pins.d1.into_output(&mut pins.ddr),
+        57600.into_baudrate(),
+    );
+
+    micros_init(&dp.TC0);
+
+    // Enable interrupts globally
+    unsafe { avr_device::interrupt::enable() };
+
+    let mut deadline = now() + PERIOD;
+    let mut last_wake = now();
+    loop {
+        while !deadline_reached(deadline.as_micros()) {}
+        let wake = now();
+        let late = wake - deadline;
+        let period = wake - last_wake;
+        last_wake = wake;
+        deadline += PERIOD;
+
+        telemetry::plot(
+            &mut serial,
+            &[
+                ("late_us", late.as_micros() as i32),
+                ("period_us", period.as_micros() as i32),
+            ],
+        )
+        .void_unwrap();
+    }
+}
@@ -0,0 +1,38 @@
+//! Prints the time at which each character is received on USART1 of an
+//! Arduino Leonardo or Micro.
+//!
+//! Build with
+//! `cargo build --example leonardo_serial --no-default-features --features atmega32u4 --target avr-atmega32u4.json`.
+#![no_std]
+#![no_main]
+
+use arduino_leonardo::prelude::*;
+use arduino_uno_micros::{micros, micros_init};
+use panic_halt as _;
+
+#[arduino_leonardo::entry]
+fn main() -> ! {
+    let dp = arduino_leonardo::Peripherals::take().unwrap();
+
+    let mut pins = arduino_leonardo::Pins::new(dp.PORTB, dp.PORTC, dp.PORTD, dp.PORTE, dp.PORTF);
+
+    let mut serial = arduino_leonardo::Serial::new(
+        dp.USART1,
+        pins.d0,
+        pins.d1.into_output(&mut pins.ddr),
+        57600.into_baudrate(),
+    );
+
+    micros_init(&dp.TC0);
+
+    // Enable interrupts globally
+    unsafe { avr_device::interrupt::enable() };
+
+    // Wait for a character and print current time once it is received
+    loop {
+        let b = nb::block!(serial.read()).void_unwrap();
+
+        let time = micros();
+        ufmt::uwriteln!(&mut serial, "Got {} after {} us!\r", b, time).void_unwrap();
+    }
+}
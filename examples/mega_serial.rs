@@ -0,0 +1,41 @@
+//! Prints the time at which each character is received over serial on an
+//! Arduino Mega 2560.
+//!
+//! Build with
+//! `cargo build --example mega_serial --no-default-features --features atmega2560 --target avr-atmega2560.json`.
+#![no_std]
+#![no_main]
+
+use arduino_mega2560::prelude::*;
+use arduino_uno_micros::{micros, micros_init};
+use panic_halt as _;
+
+#[arduino_mega2560::entry]
+fn main() -> ! {
+    let dp = arduino_mega2560::Peripherals::take().unwrap();
+
+    let mut pins = arduino_mega2560::Pins::new(
+        dp.PORTA, dp.PORTB, dp.PORTC, dp.PORTD, dp.PORTE, dp.PORTF, dp.PORTG, dp.PORTH, dp.PORTJ,
+        dp.PORTK, dp.PORTL,
+    );
+
+    let mut serial = arduino_mega2560::Serial::new(
+        dp.USART0,
+        pins.d0,
+        pins.d1.into_output(&mut pins.ddr),
+        57600.into_baudrate(),
+    );
+
+    micros_init(&dp.TC0);
+
+    // Enable interrupts globally
+    unsafe { avr_device::interrupt::enable() };
+
+    // Wait for a character and print current time once it is received
+    loop {
+        let b = nb::block!(serial.read()).void_unwrap();
+
+        let time = micros();
+        ufmt::uwriteln!(&mut serial, "Got {} after {} us!\r", b, time).void_unwrap();
+    }
+}
@@ -0,0 +1,57 @@
+//! Compares the cost of reading [`micros`] lock-free against reading it in
+//! a critical section, and prints both every second.
+//!
+//! [`micros`]: arduino_uno_micros::micros
+#![no_std]
+#![no_main]
+
+use arduino_uno::prelude::*;
+use arduino_uno_micros::{micros, micros_in, micros_init, now};
+use core::hint::black_box;
+use panic_halt as _;
+
+const CALLS: u32 = 10_000;
+
+#[arduino_uno::entry]
+fn main() -> ! {
+    let dp = arduino_uno::Peripherals::take().unwrap();
+
+    let mut pins = arduino_uno::Pins::new(dp.PORTB, dp.PORTC, dp.PORTD);
+
+    let mut serial = arduino_uno::Serial::new(
+        dp.USART0,
+        pins.d0,
+        pins.d1.into_output(&mut pins.ddr),
+        57600.into_baudrate(),
+    );
+
+    micros_init(&dp.TC0);
+
+    // Enable interrupts globally
+    unsafe { avr_device::interrupt::enable() };
+
+    loop {
+        let start = now();
+        for _ in 0..CALLS {
+            black_box(micros());
+        }
+        let lock_free = now() - start;
+
+        let start = now();
+        for _ in 0..CALLS {
+            black_box(avr_device::interrupt::free(micros_in));
+        }
+        let critical_section = now() - start;
+
+        // Both include the same loop overhead.  Microseconds per 10,000
+        // calls divided by 10 are nanoseconds per call.
+        ufmt::uwriteln!(
+            &mut serial,
+            "lock-free: {} ns, critical section: {} ns per call\r",
+            lock_free.as_micros() / 10,
+            critical_section.as_micros() / 10
+        )
+        .void_unwrap();
+        arduino_uno_micros::delay::delay_micros(1_000_000);
+    }
+}
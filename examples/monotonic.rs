@@ -0,0 +1,81 @@
+//! Blinks the on-board LED at instants scheduled on the RTIC monotonic,
+//! driving the `Monotonic` trait by hand the way RTIC's timer queue does.
+//!
+//! Build with `cargo run --example monotonic --features rtic`.
+#![no_std]
+#![no_main]
+#![feature(abi_avr_interrupt)]
+
+use core::cell;
+
+use arduino_uno::prelude::*;
+use arduino_uno_micros::monotonic::{Duration, MicrosMonotonic};
+use avr_device::interrupt::Mutex;
+use panic_halt as _;
+use rtic_monotonic::Monotonic;
+
+static MONO: Mutex<cell::RefCell<Option<MicrosMonotonic>>> = Mutex::new(cell::RefCell::new(None));
+static FIRED: Mutex<cell::Cell<bool>> = Mutex::new(cell::Cell::new(false));
+
+// The interrupt an RTIC application would bind the monotonic to.
+#[avr_device::interrupt(atmega328p)]
+fn TIMER0_COMPB() {
+    avr_device::interrupt::free(|cs| {
+        if let Some(mono) = MONO.borrow(cs).borrow_mut().as_mut() {
+            mono.clear_compare_flag();
+            mono.on_interrupt();
+        }
+        FIRED.borrow(cs).set(true);
+    })
+}
+
+#[arduino_uno::entry]
+fn main() -> ! {
+    let dp = arduino_uno::Peripherals::take().unwrap();
+
+    let mut pins = arduino_uno::Pins::new(dp.PORTB, dp.PORTC, dp.PORTD);
+
+    let mut serial = arduino_uno::Serial::new(
+        dp.USART0,
+        pins.d0,
+        pins.d1.into_output(&mut pins.ddr),
+        57600.into_baudrate(),
+    );
+    let mut led = pins.d13.into_output(&mut pins.ddr);
+
+    let mut mono = MicrosMonotonic::new(&dp.TC0);
+    let mut next = mono.now() + Duration::millis(500);
+    mono.set_compare(next);
+    avr_device::interrupt::free(|cs| MONO.borrow(cs).replace(Some(mono)));
+
+    // Enable interrupts globally
+    unsafe { avr_device::interrupt::enable() };
+
+    loop {
+        if !avr_device::interrupt::free(|cs| FIRED.borrow(cs).replace(false)) {
+            continue;
+        }
+
+        // Like RTIC, check the instant again, since the compare may match
+        // slightly early, and set the next compare either way.
+        let late = avr_device::interrupt::free(|cs| {
+            let mut mono = MONO.borrow(cs).borrow_mut();
+            let mono = mono.as_mut().unwrap();
+            let now = mono.now();
+            let late = if now >= next {
+                let late = now - next;
+                next += Duration::millis(500);
+                Some(late)
+            } else {
+                None
+            };
+            mono.set_compare(next);
+            late
+        });
+
+        if let Some(late) = late {
+            led.toggle().void_unwrap();
+            ufmt::uwriteln!(&mut serial, "Blinked {} us late\r", late.ticks() as u32).void_unwrap();
+        }
+    }
+}
@@ -0,0 +1,38 @@
+//! Blinks the LED of an Arduino Nano Every (PE2) once a second, timed by
+//! the TCB0 time base.
+//!
+//! Build with
+//! `cargo build --release --example nano_every_blink --no-default-features --features atmega4809 --target avr-atmega4809.json`.
+#![no_std]
+#![no_main]
+
+use arduino_uno_micros::{micros_init, millis};
+use avr_device::atmega4809::Peripherals;
+use panic_halt as _;
+
+const LED: u8 = 1 << 2;
+
+#[avr_device::entry]
+fn main() -> ! {
+    let dp = Peripherals::take().unwrap();
+
+    // Disable the main clock prescaler so the CPU runs at the full 16 MHz.
+    // MCLKCTRLB is protected and must be written right after unlocking it.
+    dp.CPU.ccp.write(|w| w.ccp().ioreg());
+    dp.CLKCTRL.mclkctrlb.write(|w| w.pen().clear_bit());
+
+    dp.PORTE.dirset.write(|w| unsafe { w.bits(LED) });
+
+    micros_init(&dp.TCB0);
+
+    // Enable interrupts globally
+    unsafe { avr_device::interrupt::enable() };
+
+    let mut last = millis();
+    loop {
+        if millis().wrapping_sub(last) >= 500 {
+            dp.PORTE.outtgl.write(|w| unsafe { w.bits(LED) });
+            last = last.wrapping_add(500);
+        }
+    }
+}
@@ -0,0 +1,50 @@
+//! Prints the time of every change on pins 8 to 13.
+#![no_std]
+#![no_main]
+
+use arduino_uno::prelude::*;
+use arduino_uno_micros::micros_init;
+use arduino_uno_micros::pcint_log::{self, Port};
+use panic_halt as _;
+
+#[arduino_uno::entry]
+fn main() -> ! {
+    let dp = arduino_uno::Peripherals::take().unwrap();
+
+    let mut pins = arduino_uno::Pins::new(dp.PORTB, dp.PORTC, dp.PORTD);
+
+    let mut serial = arduino_uno::Serial::new(
+        dp.USART0,
+        pins.d0,
+        pins.d1.into_output(&mut pins.ddr),
+        57600.into_baudrate(),
+    );
+
+    micros_init(&dp.TC0);
+    pcint_log::start(&dp.EXINT, Port::B, 0b0011_1111);
+
+    // Enable interrupts globally
+    unsafe { avr_device::interrupt::enable() };
+
+    let mut dropped = 0;
+    loop {
+        if let Some((at, event)) = pcint_log::read() {
+            let edge = if event.rising { "rising" } else { "falling" };
+            ufmt::uwriteln!(
+                &mut serial,
+                "pin {} {} at {} us\r",
+                event.pin + 8,
+                edge,
+                at.as_micros()
+            )
+            .void_unwrap();
+        }
+
+        let now_dropped = pcint_log::dropped();
+        if now_dropped != dropped {
+            ufmt::uwriteln!(&mut serial, "{} events dropped\r", now_dropped - dropped)
+                .void_unwrap();
+            dropped = now_dropped;
+        }
+    }
+}
@@ -0,0 +1,37 @@
+//! Prints the crystal error measured against a 1 PPS signal on pin 2.
+#![no_std]
+#![no_main]
+
+use arduino_uno::prelude::*;
+use arduino_uno_micros::delay::delay_micros;
+use arduino_uno_micros::{micros_init, pps};
+use panic_halt as _;
+
+#[arduino_uno::entry]
+fn main() -> ! {
+    let dp = arduino_uno::Peripherals::take().unwrap();
+
+    let mut pins = arduino_uno::Pins::new(dp.PORTB, dp.PORTC, dp.PORTD);
+
+    let mut serial = arduino_uno::Serial::new(
+        dp.USART0,
+        pins.d0,
+        pins.d1.into_output(&mut pins.ddr),
+        57600.into_baudrate(),
+    );
+
+    micros_init(&dp.TC0);
+    pps::start(&dp.EXINT);
+
+    // Enable interrupts globally
+    unsafe { avr_device::interrupt::enable() };
+
+    loop {
+        delay_micros(1_000_000);
+        match pps::ppm() {
+            Some(ppm) => ufmt::uwriteln!(&mut serial, "{} ppm over {} s\r", ppm, pps::pulses()),
+            None => ufmt::uwriteln!(&mut serial, "Waiting for pulses\r"),
+        }
+        .void_unwrap();
+    }
+}
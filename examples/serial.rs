@@ -0,0 +1,44 @@
+//! Prints the time at which each character is received over serial.
+#![no_std]
+#![no_main]
+
+use arduino_uno::prelude::*;
+use arduino_uno_micros::time::U32Ext;
+use arduino_uno_micros::timeout::{with_timeout, Error};
+use arduino_uno_micros::{micros, micros_init};
+use panic_halt as _;
+
+#[arduino_uno::entry]
+fn main() -> ! {
+    let dp = arduino_uno::Peripherals::take().unwrap();
+
+    let mut pins = arduino_uno::Pins::new(dp.PORTB, dp.PORTC, dp.PORTD);
+
+    let mut serial = arduino_uno::Serial::new(
+        dp.USART0,
+        pins.d0,
+        pins.d1.into_output(&mut pins.ddr),
+        57600.into_baudrate(),
+    );
+
+    micros_init(&dp.TC0);
+
+    // Enable interrupts globally
+    unsafe { avr_device::interrupt::enable() };
+
+    // Wait for a character and print current time once it is received,
+    // or every second while nothing arrives
+    loop {
+        let result = with_timeout(1.s(), || serial.read());
+
+        let time = micros();
+        match result {
+            Ok(b) => ufmt::uwriteln!(&mut serial, "Got {} after {} us!\r", b, time),
+            Err(Error::TimedOut) => {
+                ufmt::uwriteln!(&mut serial, "Still waiting after {} us\r", time)
+            }
+            Err(Error::Other(e)) => match e {},
+        }
+        .void_unwrap();
+    }
+}
@@ -0,0 +1,42 @@
+//! Prints the time at which each character arrived over serial.
+//!
+//! Unlike the `serial` example, the time is taken by the receive ISR, so it
+//! is accurate even though the main loop only checks for bytes now and
+//! then, and printing doesn't hold up the loop.
+#![no_std]
+#![no_main]
+
+use arduino_uno_micros::delay::delay_micros;
+use arduino_uno_micros::serial_tx::SerialTx;
+use arduino_uno_micros::{micros, micros_init, serial_rx};
+use panic_halt as _;
+
+#[arduino_uno::entry]
+fn main() -> ! {
+    let dp = arduino_uno::Peripherals::take().unwrap();
+
+    serial_rx::start(&dp.USART0, 57600);
+    let mut serial = SerialTx::new(dp.USART0, 57600);
+
+    micros_init(&dp.TC0);
+
+    // Enable interrupts globally
+    unsafe { avr_device::interrupt::enable() };
+
+    loop {
+        // Pretend to be busy with something else.
+        delay_micros(50_000);
+
+        while let Some((at, b)) = serial_rx::read() {
+            let late = micros().wrapping_sub(at.as_micros());
+            ufmt::uwriteln!(
+                &mut serial,
+                "Got {} at {} us, read {} us later\r",
+                b,
+                at.as_micros(),
+                late
+            )
+            .ok();
+        }
+    }
+}
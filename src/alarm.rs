@@ -0,0 +1,120 @@
+//! One-shot software alarms.
+//!
+//! Up to [`MAX_ALARMS`] callbacks can be registered to run at a given
+//! [`Instant`].  Due alarms are fired by [`poll`], which should be called
+//! regularly from the main loop.  With the `isr-alarms` feature they are
+//! fired from the timer ISR instead, in which case the callbacks run with
+//! interrupts disabled and must be kept short.
+//!
+//! The `tickless` feature goes further: rather than waking every timer
+//! period to check for due alarms, it programs a compare match for the
+//! earliest one.  The time base runs on a free-running Timer1 then, which
+//! only interrupts on overflow, every 32.768 ms at 16 MHz, so with no alarm
+//! pending the CPU is woken about 30 times a second instead of 1000.
+
+use core::cell;
+
+use avr_device::interrupt::{CriticalSection, Mutex};
+
+use crate::time::{time_after, Duration, Instant};
+
+/// The number of alarms that can be pending at once.
+pub const MAX_ALARMS: usize = 4;
+
+/// Errors returned when registering an alarm.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// All [`MAX_ALARMS`] slots are in use.
+    Full,
+}
+
+/// Identifies a pending alarm so that it can be cancelled.
+///
+/// Ids are reused once an alarm has fired, so an id must not be cancelled
+/// after its alarm is known to have run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AlarmId(u8);
+
+#[derive(Clone, Copy)]
+struct Alarm {
+    at: Instant,
+    callback: fn(),
+}
+
+static ALARMS: Mutex<[cell::Cell<Option<Alarm>>; MAX_ALARMS]> = Mutex::new([
+    cell::Cell::new(None),
+    cell::Cell::new(None),
+    cell::Cell::new(None),
+    cell::Cell::new(None),
+]);
+
+/// Registers `callback` to run once at `at`.
+pub fn alarm_at(at: Instant, callback: fn()) -> Result<AlarmId, Error> {
+    avr_device::interrupt::free(|cs| {
+        let alarms = ALARMS.borrow(cs);
+        let index = alarms
+            .iter()
+            .position(|slot| slot.get().is_none())
+            .ok_or(Error::Full)?;
+        alarms[index].set(Some(Alarm { at, callback }));
+        #[cfg(feature = "tickless")]
+        rearm(cs);
+        Ok(AlarmId(index as u8))
+    })
+}
+
+/// Registers `callback` to run once after `duration` has elapsed.
+pub fn alarm_in(duration: Duration, callback: fn()) -> Result<AlarmId, Error> {
+    alarm_at(crate::now() + duration, callback)
+}
+
+/// Cancels a pending alarm.  Does nothing if it has already fired.
+pub fn cancel(id: AlarmId) {
+    avr_device::interrupt::free(|cs| {
+        ALARMS.borrow(cs)[id.0 as usize].set(None);
+        #[cfg(feature = "tickless")]
+        rearm(cs);
+    })
+}
+
+/// Removes and returns the callback of the first alarm that is due.
+fn take_due(cs: &CriticalSection, now: Instant) -> Option<fn()> {
+    ALARMS.borrow(cs).iter().find_map(|slot| match slot.get() {
+        Some(alarm) if !time_after(alarm.at.as_micros(), now.as_micros()) => {
+            slot.set(None);
+            Some(alarm.callback)
+        }
+        _ => None,
+    })
+}
+
+/// Fires all alarms that are due.
+///
+/// Callbacks run outside of a critical section, so they may register new
+/// alarms.
+pub fn poll() {
+    let now = crate::now();
+    while let Some(callback) = avr_device::interrupt::free(|cs| take_due(cs, now)) {
+        callback();
+    }
+    #[cfg(feature = "tickless")]
+    avr_device::interrupt::free(rearm);
+}
+
+/// Schedules the timer's compare match interrupt for the earliest pending
+/// alarm, or cancels it if there is none.
+#[cfg(feature = "tickless")]
+fn rearm(cs: &CriticalSection) {
+    let now = crate::now_in(cs).as_micros();
+    let earliest = ALARMS
+        .borrow(cs)
+        .iter()
+        .filter_map(|slot| slot.get())
+        .map(|alarm| alarm.at.as_micros().wrapping_sub(now) as i32)
+        .min();
+    match earliest {
+        // An alarm already due fires as soon as possible.
+        Some(delta) => crate::timer::arm_compare(delta.max(0) as u32),
+        None => crate::timer::disarm_compare(),
+    }
+}
@@ -0,0 +1,157 @@
+//! Building blocks for a command line over serial.
+//!
+//! [`LineEditor`] collects typed characters into a line, handling backspace
+//! and telling the caller what to echo, and [`Args`] splits a finished line
+//! into a command and its arguments.  The commands themselves are up to the
+//! application; see the `cli` example.
+
+use core::str::SplitAsciiWhitespace;
+
+/// What a byte fed to a [`LineEditor`] did.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Input<'a> {
+    /// Nothing to echo, e.g. for a character that didn't fit.
+    None,
+    /// The character was added to the line and should be echoed.
+    Echo(u8),
+    /// The last character was removed and should be erased, e.g. by
+    /// echoing `"\x08 \x08"`.
+    Erase,
+    /// Enter was pressed; the line is complete.
+    Line(&'a str),
+}
+
+/// A line buffer of up to `N` characters.
+pub struct LineEditor<const N: usize> {
+    buffer: [u8; N],
+    len: usize,
+    complete: bool,
+}
+
+impl<const N: usize> LineEditor<N> {
+    /// Creates an empty line.
+    pub const fn new() -> Self {
+        LineEditor {
+            buffer: [0; N],
+            len: 0,
+            complete: false,
+        }
+    }
+
+    /// Processes a received byte.
+    ///
+    /// Either CR or LF ends a line, and an LF right after a CR is ignored,
+    /// so any terminal's line endings work.  Non-printable characters other
+    /// than backspace and delete are ignored.
+    pub fn feed(&mut self, byte: u8) -> Input<'_> {
+        if self.complete {
+            self.complete = false;
+            self.len = 0;
+            if byte == b'\n' {
+                return Input::None;
+            }
+        }
+        match byte {
+            b'\r' | b'\n' => {
+                self.complete = true;
+                // Only printable ASCII is ever stored.
+                Input::Line(core::str::from_utf8(&self.buffer[..self.len]).unwrap_or(""))
+            }
+            0x08 | 0x7f if self.len > 0 => {
+                self.len -= 1;
+                Input::Erase
+            }
+            b' '..=b'~' if self.len < N => {
+                self.buffer[self.len] = byte;
+                self.len += 1;
+                Input::Echo(byte)
+            }
+            _ => Input::None,
+        }
+    }
+}
+
+impl<const N: usize> Default for LineEditor<N> {
+    fn default() -> Self {
+        LineEditor::new()
+    }
+}
+
+/// Errors from parsing an argument.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// There are no more words.
+    Missing,
+    /// The word isn't a valid value.
+    Invalid,
+}
+
+/// The words of a command line.
+pub struct Args<'a> {
+    words: SplitAsciiWhitespace<'a>,
+}
+
+impl<'a> Args<'a> {
+    /// Splits `line` at whitespace.
+    pub fn new(line: &'a str) -> Self {
+        Args {
+            words: line.split_ascii_whitespace(),
+        }
+    }
+
+    /// Takes the next word, or `None` if there are no more.
+    pub fn next_str(&mut self) -> Option<&'a str> {
+        self.words.next()
+    }
+
+    /// Takes the next word as a decimal number.
+    pub fn next_u32(&mut self) -> Result<u32, Error> {
+        let word = self.next_str().ok_or(Error::Missing)?;
+        word.parse().map_err(|_| Error::Invalid)
+    }
+
+    /// Returns `true` if all words have been taken.
+    pub fn is_empty(&self) -> bool {
+        self.words.clone().next().is_none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edits_a_line() {
+        let mut editor = LineEditor::<8>::new();
+        assert_eq!(editor.feed(b'a'), Input::Echo(b'a'));
+        assert_eq!(editor.feed(b'x'), Input::Echo(b'x'));
+        assert_eq!(editor.feed(0x7f), Input::Erase);
+        assert_eq!(editor.feed(0x01), Input::None);
+        assert_eq!(editor.feed(b'b'), Input::Echo(b'b'));
+        assert_eq!(editor.feed(b'\r'), Input::Line("ab"));
+        // The LF of a CR LF pair is swallowed, and the next line starts empty.
+        assert_eq!(editor.feed(b'\n'), Input::None);
+        assert_eq!(editor.feed(b'\n'), Input::Line(""));
+    }
+
+    #[test]
+    fn drops_characters_that_dont_fit() {
+        let mut editor = LineEditor::<2>::new();
+        editor.feed(b'a');
+        editor.feed(b'b');
+        assert_eq!(editor.feed(b'c'), Input::None);
+        assert_eq!(editor.feed(0x08), Input::Erase);
+        assert_eq!(editor.feed(b'\n'), Input::Line("a"));
+    }
+
+    #[test]
+    fn splits_arguments() {
+        let mut args = Args::new("  set  42 x ");
+        assert_eq!(args.next_str(), Some("set"));
+        assert_eq!(args.next_u32(), Ok(42));
+        assert!(!args.is_empty());
+        assert_eq!(args.next_u32(), Err(Error::Invalid));
+        assert!(args.is_empty());
+        assert_eq!(args.next_u32(), Err(Error::Missing));
+    }
+}
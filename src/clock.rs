@@ -0,0 +1,22 @@
+//! `embedded-time` clock backed by the time base.
+
+use embedded_time::{clock, fraction::Fraction, Clock, Instant};
+
+use crate::CLOCK_HZ;
+
+/// An `embedded-time` clock counting CPU cycles.
+///
+/// The value advances in steps of one timer count, so its resolution follows
+/// the configured prescaler.  The 64-bit count does not wrap in practice.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MicrosClock;
+
+impl Clock for MicrosClock {
+    type T = u64;
+
+    const SCALING_FACTOR: Fraction = Fraction::new(1, CLOCK_HZ);
+
+    fn try_now(&self) -> Result<Instant<Self>, clock::Error> {
+        Ok(Instant::new(crate::cycles64()))
+    }
+}
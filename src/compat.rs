@@ -0,0 +1,35 @@
+//! Arduino-compatible timing functions for porting sketches.
+//!
+//! These mirror the signatures and semantics of the Arduino core.  With the
+//! `arduino-core` feature, [`micros`] advances in 4 us steps exactly like
+//! the original, since it is interpolated from the same Timer0 setup.
+
+#![allow(non_snake_case)]
+
+pub use crate::{micros, millis};
+
+/// Pauses for `ms` milliseconds.
+///
+/// Like the Arduino core, this counts against [`micros`], so time spent in
+/// interrupts is not lost, but it idles the CPU between timer interrupts
+/// like [`delay_micros`](crate::delay::delay_micros) rather than spinning.
+pub fn delay(mut ms: u32) {
+    // Waits of up to 1000 s, so the microseconds fit a `u32`.
+    const CHUNK_MS: u32 = 1_000_000;
+    let mut start = micros();
+    while ms > 0 {
+        let chunk = ms.min(CHUNK_MS);
+        crate::delay::wait_from(start, chunk * 1000);
+        start = start.wrapping_add(chunk * 1000);
+        ms -= chunk;
+    }
+}
+
+/// Pauses for `us` microseconds.
+///
+/// The Arduino core uses a cycle-counted loop here; this waits on the
+/// counter instead, so very short delays are rounded up to the timer
+/// resolution.
+pub fn delayMicroseconds(us: u16) {
+    crate::delay::delay_micros(us as u32);
+}
@@ -0,0 +1,222 @@
+//! Compile-time validated timer configuration.
+//!
+//! A [`TimerConfig`] names a prescaler and the number of timer counts per
+//! interrupt.  Both are const generics, so a combination the selected timer
+//! can't produce fails to compile instead of panicking in
+//! [`micros_init_with`](crate::micros_init_with).
+//!
+//! Periods that aren't a whole number of microseconds at the selected clock
+//! frequency are allowed.  As in the Arduino core, the leftover fraction is
+//! accumulated by the ISR and carried into the counter once it adds up to a
+//! whole microsecond, so the counters don't drift from the crystal.
+//!
+//! Possible values (at 16 MHz), of which 64 and 250 are the default:
+//!
+//! | Prescaler | Counts | Interrupt interval |
+//! |----------:|-------:|-------------------:|
+//! |         8 |      2 |               1 us |
+//! |        64 |    250 |               1 ms |
+//! |       256 |    125 |               2 ms |
+//! |       256 |    250 |               4 ms |
+//! |      1024 |    125 |               8 ms |
+//! |      1024 |    250 |              16 ms |
+//!
+//! Shorter intervals give [`millis`](crate::millis) and the tick-driven
+//! features a finer resolution at the cost of more time spent in the ISR.
+//! The regular ISR takes over a hundred cycles, so periods below about
+//! 10 us leave little time for the program.  The `fast-isr` feature swaps
+//! in a 31 cycle ISR on the ATmega328P that makes periods of a few
+//! microseconds practical.  Its shortest supported period is 64 cycles,
+//! 4 us from a prescaler of 8 and 8 counts at 16 MHz, where the ISR takes
+//! half of the CPU; with shorter ones a period can end before the ISR is
+//! done.  No ISR can keep up with the 16 cycles of the 1 us row.
+//! [`micros`](crate::micros) is interpolated from the hardware timer either
+//! way.
+//!
+//! Rather than consulting the table, [`timer_config!`](crate::timer_config)
+//! picks the values for a requested interval with [`config_for_resolution`].
+
+use crate::{timer, CLOCK_HZ, CLOCK_MHZ};
+
+/// A timer configuration of `COUNTS` counts per interrupt, each lasting
+/// `PRESCALER` CPU cycles.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TimerConfig<const PRESCALER: u32, const COUNTS: u32>;
+
+impl<const PRESCALER: u32, const COUNTS: u32> TimerConfig<PRESCALER, COUNTS> {
+    // Compile-time assertion: indexing a one-element array with `true as
+    // usize` fails const evaluation, which is reported as an error wherever
+    // the configuration is used.
+    const VALID: () =
+        [()][!(timer::clock_select(PRESCALER).is_some() && timer::valid_counts(COUNTS)) as usize];
+
+    /// The number of whole microseconds between timer interrupts.
+    pub const MICROS_PER_TICK: u32 = PRESCALER * COUNTS / CLOCK_MHZ;
+
+    /// Creates the configuration.
+    pub const fn new() -> Self {
+        TimerConfig
+    }
+
+    pub(crate) const fn settings() -> Settings {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::VALID;
+        Settings {
+            prescaler: PRESCALER,
+            counts: COUNTS,
+            clock_select: match timer::clock_select(PRESCALER) {
+                Some(cs) => cs,
+                None => 0,
+            },
+            micros_increment: 0,
+            micros_fract_increment: 0,
+            millis_increment: 0,
+            millis_fract_increment: 0,
+        }
+        .corrected(0)
+    }
+}
+
+/// A prescaler and period computed by [`config_for_resolution`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Resolution {
+    /// The timer clock prescaler.
+    pub prescaler: u32,
+    /// The number of timer counts between interrupts.
+    pub counts: u32,
+}
+
+// Every prescaler offered by one of the backends, in ascending order.
+const PRESCALERS: [u32; 8] = [1, 2, 8, 32, 64, 128, 256, 1024];
+
+/// Computes the prescaler and timer counts giving an interrupt every
+/// `us_per_tick` microseconds on the selected timer.
+///
+/// The smallest usable prescaler is chosen, as it gives
+/// [`micros`](crate::micros) the finest resolution.  Evaluating it in a
+/// const context fails to compile if the timer can't produce the interval
+/// exactly.
+pub const fn config_for_resolution(us_per_tick: u32) -> Resolution {
+    let cycles = us_per_tick * CLOCK_MHZ;
+    let mut i = 0;
+    while i < PRESCALERS.len() {
+        let prescaler = PRESCALERS[i];
+        if timer::clock_select(prescaler).is_some()
+            && cycles % prescaler == 0
+            && timer::valid_counts(cycles / prescaler)
+        {
+            return Resolution {
+                prescaler,
+                counts: cycles / prescaler,
+            };
+        }
+        i += 1;
+    }
+    // No prescaler fits.  `i` is now past the end of the array, so indexing
+    // with it fails const evaluation.
+    let none = [Resolution {
+        prescaler: 0,
+        counts: 0,
+    }; PRESCALERS.len()];
+    none[i]
+}
+
+/// Expands to the [`TimerConfig`] interrupting every given number of
+/// microseconds, as computed by [`config_for_resolution`].
+///
+/// For example, `micros_init_with(&dp.TC0, timer_config!(2000))` sets up a
+/// 2 ms interval.
+#[macro_export]
+macro_rules! timer_config {
+    ($us_per_tick:expr) => {
+        $crate::TimerConfig::<
+            { $crate::config::config_for_resolution($us_per_tick).prescaler },
+            { $crate::config::config_for_resolution($us_per_tick).counts },
+        >::new()
+    };
+}
+
+/// The configuration used by [`micros_init`](crate::micros_init): a 1 ms
+/// interval, with [`micros`](crate::micros) interpolated from the timer
+/// between interrupts to a resolution of 4 us at 16 MHz.
+///
+/// The 8-bit timers need a prescaler of 64 to reach 1 ms.  At 20 MHz 1 ms
+/// would take more than 256 counts, so the interval is 0.8 ms instead.
+///
+/// Everything timed from the counters is then only resolved to 4 us, and
+/// the hooks in the timer ISR run once per millisecond.  The modules
+/// needing finer timing work around it: the `tone` module and the
+/// embassy driver schedule their edges on the timer's second compare unit,
+/// and [`one_wire`](crate::one_wire) counts cycles for the parts of a slot
+/// shorter than 15 us.  For a finer resolution overall, put the time base
+/// on Timer1 with the `timer1` feature, for 0.5 us, or pass a smaller
+/// prescaler to [`micros_init_with`](crate::micros_init_with), e.g.
+/// `TimerConfig::<8, 250>` for 0.5 us and a 125 us interval.
+#[cfg(not(any(feature = "arduino-core", feature = "atmega4809", feature = "timer1")))]
+pub type DefaultConfig = TimerConfig<64, { DEFAULT_COUNTS }>;
+
+#[cfg(not(any(feature = "arduino-core", feature = "atmega4809", feature = "timer1")))]
+const DEFAULT_COUNTS: u32 = if CLOCK_MHZ == 20 {
+    250
+} else {
+    CLOCK_MHZ * 1000 / 64
+};
+
+/// The configuration used by [`micros_init`](crate::micros_init): a 1 ms
+/// interval.  Timer1 counts to 65535, so its prescaler can stay at 8 for a
+/// resolution of 0.5 us at 16 MHz.
+#[cfg(all(feature = "timer1", not(feature = "timer1-overflow")))]
+pub type DefaultConfig = TimerConfig<8, { CLOCK_MHZ * 125 }>;
+
+/// The configuration used by [`micros_init`](crate::micros_init), and the
+/// only one possible, with `timer1-overflow`: a full 16-bit period at a
+/// prescaler of 8.
+#[cfg(feature = "timer1-overflow")]
+pub type DefaultConfig = TimerConfig<8, 65536>;
+
+/// The configuration used by [`micros_init`](crate::micros_init): a 1 ms
+/// interval.  A TCB can only divide the clock by 1 or 2, and counts to
+/// 65535, so this resolves 1/8 us at 16 MHz.
+#[cfg(feature = "atmega4809")]
+pub type DefaultConfig = TimerConfig<2, { CLOCK_MHZ * 500 }>;
+
+/// The configuration used by [`micros_init`](crate::micros_init).  The Arduino
+/// core runs Timer0 freely in Fast PWM mode, overflowing every 1024 us at
+/// 16 MHz.
+#[cfg(feature = "arduino-core")]
+pub type DefaultConfig = TimerConfig<64, 256>;
+
+/// The settings derived from a validated [`TimerConfig`], read by the
+/// backend and the ISR at runtime.
+#[derive(Clone, Copy)]
+pub(crate) struct Settings {
+    pub(crate) prescaler: u32,
+    pub(crate) counts: u32,
+    pub(crate) clock_select: u8,
+    pub(crate) micros_increment: u32,
+    // The part of a microsecond left over each period, in millionths of a
+    // CPU cycle so that a ppm correction can be folded in.  A microsecond is
+    // `CLOCK_HZ` of these.
+    pub(crate) micros_fract_increment: u32,
+    // The millisecond counter is advanced by whole milliseconds, with the
+    // remaining microseconds carried over in a separate accumulator.
+    pub(crate) millis_increment: u32,
+    pub(crate) millis_fract_increment: u16,
+}
+
+impl Settings {
+    /// Returns the settings with the increments recomputed for a crystal
+    /// running `ppm` parts per million fast.
+    pub(crate) const fn corrected(self, ppm: i16) -> Settings {
+        let cycles = (self.prescaler * self.counts) as u64;
+        let scaled = cycles * (1_000_000 - ppm as i32) as u64;
+        let micros_increment = (scaled / CLOCK_HZ as u64) as u32;
+        Settings {
+            micros_increment,
+            micros_fract_increment: (scaled % CLOCK_HZ as u64) as u32,
+            millis_increment: micros_increment / 1000,
+            millis_fract_increment: (micros_increment % 1000) as u16,
+            ..self
+        }
+    }
+}
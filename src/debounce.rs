@@ -0,0 +1,221 @@
+//! Debouncing of mechanical switches.
+//!
+//! Both types are driven by timestamps rather than delays: they are updated
+//! with the raw level of the input and the current time, e.g. on every pass
+//! through the main loop, and never block.
+
+use crate::time::{Duration, Instant};
+
+/// Filters the bounces of a single input.
+///
+/// A change of the raw level is only accepted once it has been stable for
+/// the configured time.
+#[derive(Clone, Copy, Debug)]
+pub struct Debouncer {
+    stable_time: Duration,
+    level: bool,
+    candidate: bool,
+    since: Instant,
+}
+
+impl Debouncer {
+    /// Creates a debouncer that requires a level to be stable for
+    /// `stable_micros` and starts at `level`.
+    pub const fn new(stable_micros: u32, level: bool) -> Self {
+        Debouncer {
+            stable_time: Duration::from_micros(stable_micros),
+            level,
+            candidate: level,
+            since: Instant::from_micros(0),
+        }
+    }
+
+    /// Feeds the raw level at `now`.  Returns the new debounced level if it
+    /// just changed.
+    pub fn update(&mut self, raw: bool, now: Instant) -> Option<bool> {
+        if raw != self.candidate {
+            self.candidate = raw;
+            self.since = now;
+        }
+        if self.candidate != self.level && now - self.since >= self.stable_time {
+            self.level = self.candidate;
+            return Some(self.level);
+        }
+        None
+    }
+
+    /// Returns the debounced level.
+    pub fn level(&self) -> bool {
+        self.level
+    }
+}
+
+/// Something that happened to a [`Button`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    /// The button went down.
+    Pressed,
+    /// The button was let go, after being held for the given time.
+    Released(Duration),
+    /// The button has been held for the long press time.  It is reported
+    /// once per press, while the button is still down.
+    LongPress,
+    /// The button was pressed again within the double click time of the
+    /// previous release.  It follows the second [`Event::Pressed`].
+    DoubleClick,
+}
+
+/// A push button with press, release, long press and double click events.
+#[derive(Clone, Copy, Debug)]
+pub struct Button {
+    debouncer: Debouncer,
+    active_low: bool,
+    long_press: Duration,
+    double_click: Duration,
+    pressed_at: Option<Instant>,
+    long_reported: bool,
+    released_at: Option<Instant>,
+    pending: Option<Event>,
+}
+
+impl Button {
+    /// Creates a button that is pressed when its input is low, as with a
+    /// switch to ground and a pull-up, with a 5 ms debounce time, 1 s long
+    /// presses and 300 ms double clicks.
+    pub const fn new() -> Self {
+        Button {
+            debouncer: Debouncer::new(5_000, true),
+            active_low: true,
+            long_press: Duration::from_millis(1000),
+            double_click: Duration::from_millis(300),
+            pressed_at: None,
+            long_reported: false,
+            released_at: None,
+            pending: None,
+        }
+    }
+
+    /// Makes the button pressed when its input is high instead.
+    pub const fn active_high(self) -> Self {
+        Button {
+            debouncer: Debouncer::new(self.debouncer.stable_time.as_micros(), false),
+            active_low: false,
+            ..self
+        }
+    }
+
+    /// Sets the debounce time.
+    pub const fn with_stable_time(self, stable_micros: u32) -> Self {
+        Button {
+            debouncer: Debouncer::new(stable_micros, self.active_low),
+            ..self
+        }
+    }
+
+    /// Sets how long the button must be held for a long press.
+    pub const fn with_long_press(self, long_press: Duration) -> Self {
+        Button { long_press, ..self }
+    }
+
+    /// Sets how soon after a release a press counts as a double click.
+    pub const fn with_double_click(self, double_click: Duration) -> Self {
+        Button {
+            double_click,
+            ..self
+        }
+    }
+
+    /// Feeds the raw level of the input at `now` and returns the next
+    /// event, if any.
+    ///
+    /// A single update can cause two events, a press and a double click, in
+    /// which case the second is returned by the next update.
+    pub fn update(&mut self, raw: bool, now: Instant) -> Option<Event> {
+        if let Some(event) = self.pending.take() {
+            return Some(event);
+        }
+
+        match self.debouncer.update(raw, now) {
+            Some(level) if level != self.active_low => {
+                self.pressed_at = Some(now);
+                self.long_reported = false;
+                if let Some(released) = self.released_at.take() {
+                    if now - released <= self.double_click {
+                        self.pending = Some(Event::DoubleClick);
+                    }
+                }
+                Some(Event::Pressed)
+            }
+            Some(_) => {
+                let held = self
+                    .pressed_at
+                    .take()
+                    .map_or(Duration::from_micros(0), |pressed| now - pressed);
+                // A long press doesn't start a double click.
+                if !self.long_reported {
+                    self.released_at = Some(now);
+                }
+                Some(Event::Released(held))
+            }
+            None => match self.pressed_at {
+                Some(pressed) if !self.long_reported && now - pressed >= self.long_press => {
+                    self.long_reported = true;
+                    Some(Event::LongPress)
+                }
+                _ => None,
+            },
+        }
+    }
+
+    /// Returns whether the button is currently pressed.
+    pub fn is_pressed(&self) -> bool {
+        self.debouncer.level() != self.active_low
+    }
+}
+
+impl Default for Button {
+    fn default() -> Self {
+        Button::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_a_stable_level() {
+        let mut debouncer = Debouncer::new(1000, false);
+        assert_eq!(debouncer.update(true, Instant::from_micros(0)), None);
+        assert_eq!(debouncer.update(true, Instant::from_micros(999)), None);
+        assert_eq!(
+            debouncer.update(true, Instant::from_micros(1000)),
+            Some(true)
+        );
+        assert_eq!(debouncer.update(true, Instant::from_micros(2000)), None);
+        assert!(debouncer.level());
+    }
+
+    #[test]
+    fn ignores_bounces() {
+        let mut debouncer = Debouncer::new(1000, false);
+        debouncer.update(true, Instant::from_micros(0));
+        debouncer.update(false, Instant::from_micros(500));
+        debouncer.update(true, Instant::from_micros(800));
+        assert_eq!(debouncer.update(true, Instant::from_micros(1500)), None);
+        assert_eq!(
+            debouncer.update(true, Instant::from_micros(1800)),
+            Some(true)
+        );
+    }
+
+    #[test]
+    fn handles_wrapping_time() {
+        let mut debouncer = Debouncer::new(1000, false);
+        debouncer.update(true, Instant::from_micros(u32::MAX - 200));
+        assert_eq!(
+            debouncer.update(true, Instant::from_micros(799)),
+            Some(true)
+        );
+    }
+}
@@ -0,0 +1,125 @@
+//! Power-down sleep for seconds at a time without losing the timeline.
+//!
+//! The deeper sleep modes stop the CPU clock and with it the time base
+//! timer, which also makes them far more frugal than IDLE.  The functions
+//! here suspend the time base, hand timekeeping to a clock that keeps
+//! running, and advance the counters by the time it measured on wake, so
+//! [`micros64`](crate::micros64) and everything scheduled on it carry on as
+//! if the CPU had been awake.
+//!
+//! [`power_down`] wakes from the watchdog, whose 128 kHz oscillator is only
+//! accurate to about 10%, or to the error measured by [`calibrate`].  With
+//! the `rtc` feature, [`power_save`] keeps time on the Timer2 watch crystal
+//! instead.  Other interrupts still run while asleep, but the CPU goes back
+//! to sleep until the chosen time is up.
+//!
+//! The watchdog must not be running in reset mode, see
+//! [`Watchdog::stop`](crate::watchdog::Watchdog::stop).  This module
+//! defines the watchdog's interrupt handler, so it is only compiled with the
+//! `deep-sleep` feature, leaving the vector free otherwise.
+
+#[cfg(feature = "atmega4809")]
+compile_error!("the `deep-sleep` feature is not supported on the ATmega4809");
+
+use core::cell;
+
+use avr_device::interrupt::Mutex;
+
+use crate::power::{self, Mode};
+use crate::suspend;
+use crate::time::Duration;
+use crate::watchdog::{self, Timeout, Wdt};
+
+/// The CPU cycles the crystal oscillator takes to start up after a wake
+/// from power-down, with the Arduino Uno's fuse settings: 16K cycles.
+///
+/// The CPU doesn't run meanwhile, so this is added to the time slept.
+pub const WAKE_UP_CYCLES: u32 = 16 * 1024;
+
+// Set by the watchdog ISR.
+static WOKE: Mutex<cell::Cell<bool>> = Mutex::new(cell::Cell::new(false));
+
+// The measured length of a nominal 128 ms watchdog timeout, in
+// microseconds.
+static WATCHDOG_128MS: Mutex<cell::Cell<u32>> = Mutex::new(cell::Cell::new(128_000));
+
+/// Measures how long the watchdog oscillator actually takes for a nominal
+/// 128 ms against the time base, and uses that for [`power_down`] from now
+/// on.  Returns the measurement.
+///
+/// The oscillator drifts with voltage and temperature, so calibrating
+/// shortly before sleeping gives the best results.  The time base must be
+/// running with interrupts enabled.
+pub fn calibrate(wdt: &Wdt) -> Duration {
+    let start = crate::now();
+    wait_for_watchdog(wdt, Timeout::Ms125, Mode::Idle);
+    let measured = crate::now() - start;
+    avr_device::interrupt::free(|cs| WATCHDOG_128MS.borrow(cs).set(measured.as_micros()));
+    measured
+}
+
+/// Sleeps in power-down mode for `timeout`, as measured by the watchdog,
+/// and returns the time the counters were advanced by.
+///
+/// Interrupts must be enabled.
+pub fn power_down(wdt: &Wdt, timeout: Timeout) -> Duration {
+    let suspended = suspend::suspend();
+    wait_for_watchdog(wdt, timeout, Mode::PowerDown);
+    let nominal = timeout.duration().as_micros() as u64;
+    let measured = avr_device::interrupt::free(|cs| WATCHDOG_128MS.borrow(cs).get()) as u64;
+    let slept = nominal * measured / 128_000;
+    suspended.resume_after_cycles(slept * crate::CLOCK_MHZ as u64 + WAKE_UP_CYCLES as u64)
+}
+
+/// Sleeps in power-save mode for `secs` seconds of the Timer2 watch
+/// crystal, and returns the time the counters were advanced by.
+///
+/// The time slept is read from the RTC after waking, so it includes the
+/// oscillator start-up and is as accurate as the crystal, to 1/256 s.  The
+/// RTC must have been started with [`rtc_init`](crate::rtc::rtc_init) and
+/// interrupts must be enabled.
+#[cfg(feature = "rtc")]
+pub fn power_save(secs: u32) -> Duration {
+    use crate::rtc::{self, COUNTS_PER_SEC};
+
+    let suspended = suspend::suspend();
+    let start = rtc_counts(rtc::now());
+    let target = secs as u64 * COUNTS_PER_SEC as u64;
+    loop {
+        // Going back to sleep within a crystal cycle of the last wake
+        // would miss the next one.
+        rtc::sync();
+        let mut elapsed = 0;
+        power::sleep_unless(Mode::PowerSave, |_| {
+            elapsed = rtc_counts(rtc::now()) - start;
+            elapsed >= target
+        });
+        if elapsed >= target {
+            let cycles = elapsed * crate::CLOCK_HZ as u64 / COUNTS_PER_SEC as u64;
+            return suspended.resume_after_cycles(cycles);
+        }
+    }
+}
+
+#[cfg(feature = "rtc")]
+fn rtc_counts((seconds, counts): (u32, u8)) -> u64 {
+    seconds as u64 * crate::rtc::COUNTS_PER_SEC as u64 + counts as u64
+}
+
+/// Runs the watchdog in interrupt mode for `timeout`, sleeping in `mode`
+/// until it fires.
+fn wait_for_watchdog(wdt: &Wdt, timeout: Timeout, mode: Mode) {
+    avr_device::interrupt::free(|cs| WOKE.borrow(cs).set(false));
+    // Restarting the watchdog also resets its count.
+    watchdog::start_interrupt(wdt, timeout);
+    while !avr_device::interrupt::free(|cs| WOKE.borrow(cs).get()) {
+        power::sleep_unless(mode, |cs| WOKE.borrow(cs).get());
+    }
+    watchdog::stop_interrupt(wdt);
+}
+
+isr! {
+    fn WDT() {
+        avr_device::interrupt::free(|cs| WOKE.borrow(cs).set(true))
+    }
+}
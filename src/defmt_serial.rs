@@ -0,0 +1,87 @@
+//! A `defmt` logger that sends its frames over the buffered serial port.
+//!
+//! With `defmt` the format strings stay on the host: the firmware only sends
+//! an index into a table in the ELF file followed by the arguments in binary,
+//! which saves both flash and serial bandwidth compared to `ufmt`.  Each
+//! record is timestamped with [`micros`](crate::micros), which the host
+//! prints as microseconds.
+//!
+//! Pass the [`SerialTx`] to [`init`], then log with the `defmt` macros.  The
+//! program must be linked with `-C link-arg=-Tdefmt.x`, and the output
+//! decoded on the host with `defmt-print -e <elf>` reading the serial port.
+//! Records logged before [`init`] are discarded.
+
+use core::cell::{Cell, RefCell};
+
+use avr_device::interrupt::{CriticalSection, Mutex};
+
+use crate::serial_tx::SerialTx;
+
+static SERIAL: Mutex<RefCell<Option<SerialTx>>> = Mutex::new(RefCell::new(None));
+static ENCODER: Mutex<RefCell<defmt::Encoder>> = Mutex::new(RefCell::new(defmt::Encoder::new()));
+static TAKEN: Mutex<Cell<bool>> = Mutex::new(Cell::new(false));
+static RESTORE: Mutex<Cell<bool>> = Mutex::new(Cell::new(false));
+
+defmt::timestamp!("{=u32:us}", crate::micros());
+
+/// Starts sending `defmt` records to `serial`.
+pub fn init(serial: SerialTx) {
+    avr_device::interrupt::free(|cs| {
+        SERIAL.borrow(cs).replace(Some(serial));
+    })
+}
+
+/// Stops logging and returns the serial port, e.g. to reconfigure it.
+pub fn release() -> Option<SerialTx> {
+    avr_device::interrupt::free(|cs| SERIAL.borrow(cs).take())
+}
+
+#[defmt::global_logger]
+struct Logger;
+
+unsafe impl defmt::Logger for Logger {
+    fn acquire() {
+        let enabled = crate::masked::interrupts_enabled();
+        avr_device::interrupt::disable();
+        // Interrupts stay disabled until `release`.
+        let cs = unsafe { CriticalSection::new() };
+        let taken = TAKEN.borrow(&cs);
+        if taken.get() {
+            panic!("defmt logger taken reentrantly");
+        }
+        taken.set(true);
+        RESTORE.borrow(&cs).set(enabled);
+        with_encoder(&cs, |encoder, write| encoder.start_frame(write));
+    }
+
+    unsafe fn flush() {
+        let cs = CriticalSection::new();
+        if let Some(serial) = SERIAL.borrow(&cs).borrow_mut().as_mut() {
+            serial.flush();
+        }
+    }
+
+    unsafe fn release() {
+        let cs = CriticalSection::new();
+        with_encoder(&cs, |encoder, write| encoder.end_frame(write));
+        TAKEN.borrow(&cs).set(false);
+        if RESTORE.borrow(&cs).get() {
+            avr_device::interrupt::enable();
+        }
+    }
+
+    unsafe fn write(bytes: &[u8]) {
+        let cs = CriticalSection::new();
+        with_encoder(&cs, |encoder, write| encoder.write(bytes, write));
+    }
+}
+
+fn with_encoder(cs: &CriticalSection, f: impl FnOnce(&mut defmt::Encoder, &mut dyn FnMut(&[u8]))) {
+    let mut serial = SERIAL.borrow(cs).borrow_mut();
+    let mut encoder = ENCODER.borrow(cs).borrow_mut();
+    f(&mut encoder, &mut |bytes| {
+        if let Some(serial) = serial.as_mut() {
+            serial.write_bytes(bytes);
+        }
+    });
+}
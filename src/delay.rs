@@ -0,0 +1,217 @@
+//! Blocking delays backed by the interrupt-driven counter.
+//!
+//! Unlike cycle-counted busy loops, these stay accurate when other
+//! interrupts steal cycles while waiting.  While more than a timer period
+//! is left they idle the CPU between interrupts, which keeps the time base
+//! running, and only spin for the rest, so they are as precise as spinning
+//! for a fraction of the power.  The `sleep_*` variants idle all the way,
+//! at the cost of waking up to one timer period late.
+
+#[cfg(target_arch = "avr")]
+use core::arch::asm;
+
+use embedded_hal::blocking::delay::{DelayMs, DelayUs};
+
+use crate::masked::interrupts_enabled;
+use crate::time::{time_after, Duration, Instant};
+use crate::{micros, power, CLOCK_MHZ};
+
+/// Waits until `us` microseconds have elapsed.
+///
+/// With interrupts disabled this spins rather than idling, which would
+/// never wake up.  The counters then stop advancing after a timer period,
+/// so only delays up to a period finish.
+pub fn delay_micros(us: u32) {
+    wait_from(micros(), us);
+}
+
+/// Waits until `deadline` has passed.
+///
+/// Advancing the deadline by a fixed period on every iteration gives a loop
+/// that runs at a steady rate without accumulating drift.  Like
+/// [`delay_micros`], it spins with interrupts disabled.
+pub fn delay_until(deadline: Instant) {
+    let period = crate::period_micros();
+    loop {
+        let now = micros();
+        if !time_after(deadline.as_micros(), now) {
+            return;
+        }
+        if deadline.as_micros().wrapping_sub(now) > period && interrupts_enabled() {
+            power::idle();
+        }
+    }
+}
+
+/// Waits until `us` microseconds after `start`, idling while more than a
+/// timer period is left and interrupts are enabled.  The timer interrupt
+/// wakes the CPU within one period, so it never sleeps past the end.
+pub(crate) fn wait_from(start: u32, us: u32) {
+    let period = crate::period_micros();
+    loop {
+        let elapsed = micros().wrapping_sub(start);
+        if elapsed >= us {
+            return;
+        }
+        if us - elapsed > period && interrupts_enabled() {
+            power::idle();
+        }
+    }
+}
+
+/// Sleeps until `us` microseconds have elapsed.
+pub fn sleep_micros(us: u32) {
+    let start = micros();
+    while micros().wrapping_sub(start) < us {
+        power::idle();
+    }
+}
+
+/// Sleeps until `deadline` has passed.
+pub fn sleep_until(deadline: Instant) {
+    while time_after(deadline.as_micros(), micros()) {
+        power::idle();
+    }
+}
+
+/// Busy-waits for `cycles` CPU cycles, rounded down to a multiple of 6.
+///
+/// Unlike the other delays this doesn't depend on the time base, so it
+/// works with interrupts disabled, but any interrupt that runs meanwhile
+/// stretches it.  It is inlined so that a constant count is divided at
+/// compile time; a count only known at runtime costs a division on top.
+/// Counts below 6 return immediately.
+#[inline(always)]
+pub fn delay_cycles(cycles: u32) {
+    let iterations = cycles / 6;
+    if iterations == 0 {
+        return;
+    }
+    let [b0, b1, b2, b3] = iterations.to_le_bytes();
+    // Each iteration takes 6 cycles, the last one 5.  SBCI only clears Z,
+    // so it is set at the end of the chain if all four bytes are zero.
+    #[cfg(target_arch = "avr")]
+    unsafe {
+        asm!(
+            "1:",
+            "subi {b0}, 1",
+            "sbci {b1}, 0",
+            "sbci {b2}, 0",
+            "sbci {b3}, 0",
+            "brne 1b",
+            b0 = inout(reg_upper) b0 => _,
+            b1 = inout(reg_upper) b1 => _,
+            b2 = inout(reg_upper) b2 => _,
+            b3 = inout(reg_upper) b3 => _,
+            options(nomem, nostack),
+        );
+    }
+}
+
+/// The result of [`check_clock`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ClockCheck {
+    /// How long the busy delay should have taken at the configured clock.
+    pub expected: Duration,
+    /// How long the time base measured it as.
+    pub measured: Duration,
+}
+
+impl ClockCheck {
+    /// Returns how far the measurement is off, in parts per million, with a
+    /// positive value for a delay that took longer than expected.
+    ///
+    /// The timer ISR runs during the delay and stretches it by its share of
+    /// the CPU, typically a few hundred ppm with a 1 ms period.  A timer
+    /// running at another prescaler or period than configured shows up as a
+    /// far larger error, e.g. -750,000 ppm for a prescaler of 256 instead
+    /// of 64.
+    pub fn error_ppm(&self) -> i32 {
+        let expected = self.expected.as_micros() as i64;
+        let measured = self.measured.as_micros() as i64;
+        ((measured - expected) * 1_000_000 / expected) as i32
+    }
+}
+
+/// Times a 100 ms [`delay_cycles`] against the time base, to catch a timer
+/// configuration that doesn't match the hardware, e.g. after other code
+/// reprogrammed the prescaler.
+///
+/// The delay and the timer both run from the CPU clock, so a `clock-*`
+/// feature that doesn't match the crystal slows or speeds up both alike and
+/// isn't caught; that takes an external reference, see
+/// [`drift`](crate::drift).  The time base must be running with interrupts
+/// enabled.
+pub fn check_clock() -> ClockCheck {
+    const MICROS: u32 = 100_000;
+    let start = micros();
+    delay_cycles(MICROS * CLOCK_MHZ);
+    let measured = micros().wrapping_sub(start);
+    ClockCheck {
+        expected: Duration::from_micros(MICROS),
+        measured: Duration::from_micros(measured),
+    }
+}
+
+/// An `embedded-hal` delay provider that waits on [`micros`].
+///
+/// The time base must have been initialized with
+/// [`micros_init`](crate::micros_init) and interrupts enabled, otherwise the
+/// delays never finish.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MicrosDelay {
+    _private: (),
+}
+
+impl MicrosDelay {
+    /// Creates a new delay provider.
+    pub fn new() -> Self {
+        MicrosDelay { _private: () }
+    }
+}
+
+impl DelayUs<u32> for MicrosDelay {
+    fn delay_us(&mut self, us: u32) {
+        delay_micros(us);
+    }
+}
+
+impl DelayUs<u16> for MicrosDelay {
+    fn delay_us(&mut self, us: u16) {
+        self.delay_us(us as u32);
+    }
+}
+
+impl DelayUs<u8> for MicrosDelay {
+    fn delay_us(&mut self, us: u8) {
+        self.delay_us(us as u32);
+    }
+}
+
+impl DelayMs<u32> for MicrosDelay {
+    fn delay_ms(&mut self, ms: u32) {
+        // Wait a second at a time to avoid overflowing the microsecond
+        // count, advancing the start by exactly the time waited so no time
+        // is lost between iterations.
+        let mut start = micros();
+        let mut left = ms;
+        while left > 0 {
+            let chunk = left.min(1000);
+            wait_from(start, chunk * 1000);
+            start = start.wrapping_add(chunk * 1000);
+            left -= chunk;
+        }
+    }
+}
+
+impl DelayMs<u16> for MicrosDelay {
+    fn delay_ms(&mut self, ms: u16) {
+        self.delay_ms(ms as u32);
+    }
+}
+
+impl DelayMs<u8> for MicrosDelay {
+    fn delay_ms(&mut self, ms: u8) {
+        self.delay_ms(ms as u32);
+    }
+}
@@ -0,0 +1,148 @@
+//! DHT11 and DHT22 temperature and humidity sensors.
+//!
+//! The sensor answers a start signal with a 40 bit frame on its single data
+//! line.  Each bit is a 50 us low phase followed by a high phase of about
+//! 27 us for a zero or 70 us for a one, which is told apart by timing the
+//! high phases with [`micros`](crate::micros).
+//!
+//! The data pin must be open drain with a pull-up, so that setting it high
+//! releases the line, as with avr-hal's `OpenDrain` or `TriState` modes.
+
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+use crate::delay::delay_micros;
+
+// High phases longer than this are ones.
+const THRESHOLD_MICROS: u32 = 48;
+
+// Every phase of the response is well under this.
+const PHASE_TIMEOUT_MICROS: u32 = 120;
+
+/// The sensor model, which determines the start signal and data format.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Model {
+    /// DHT11: whole degrees and percent, one reading per second.
+    Dht11,
+    /// DHT22 or AM2302: tenths, one reading every two seconds.
+    Dht22,
+}
+
+/// Errors that can occur during a reading.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The sensor didn't answer the start signal.
+    NoResponse,
+    /// The sensor stopped in the middle of the frame.
+    Timeout,
+    /// The frame was received but its checksum doesn't match.
+    Checksum,
+}
+
+/// A reading from the sensor.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Reading {
+    /// The temperature in tenths of degrees Celsius.
+    pub temperature: i16,
+    /// The relative humidity in tenths of a percent.
+    pub humidity: u16,
+}
+
+/// A DHT sensor on an open drain data pin.
+pub struct Dht<P> {
+    pin: P,
+    model: Model,
+}
+
+impl<P: InputPin + OutputPin> Dht<P> {
+    /// Creates a driver, releasing the data line.
+    pub fn new(mut pin: P, model: Model) -> Self {
+        pin.set_high().ok();
+        Dht { pin, model }
+    }
+
+    /// Reads the temperature and humidity.
+    ///
+    /// This takes about 5 ms for a DHT22 and 23 ms for a DHT11, most of it
+    /// the start signal.  Readings taken more often than the sensor allows
+    /// repeat the previous values.
+    pub fn read(&mut self) -> Result<Reading, Error> {
+        let start_micros = match self.model {
+            Model::Dht11 => 18_000,
+            Model::Dht22 => 1_000,
+        };
+        self.pin.set_low().ok();
+        delay_micros(start_micros);
+        self.pin.set_high().ok();
+
+        // The sensor pulls the line low after 20-40 us, then sends 80 us
+        // low and 80 us high before the first bit.
+        self.wait_while(true).map_err(|_| Error::NoResponse)?;
+        self.wait_while(false).map_err(|_| Error::NoResponse)?;
+        self.wait_while(true).map_err(|_| Error::NoResponse)?;
+
+        let mut data = [0u8; 5];
+        for bit in 0..40 {
+            self.wait_while(false)?;
+            let high = self.wait_while(true)?;
+            if high > THRESHOLD_MICROS {
+                data[bit / 8] |= 0x80 >> (bit % 8);
+            }
+        }
+
+        let sum = data[..4]
+            .iter()
+            .fold(0u8, |sum, &byte| sum.wrapping_add(byte));
+        if sum != data[4] {
+            return Err(Error::Checksum);
+        }
+        Ok(self.decode(&data))
+    }
+
+    /// Releases the pin.
+    pub fn release(self) -> P {
+        self.pin
+    }
+
+    fn decode(&self, data: &[u8; 5]) -> Reading {
+        match self.model {
+            Model::Dht11 => {
+                // Newer DHT11s report tenths of a degree, and the sign of
+                // the temperature in the top bit.
+                let temperature = data[2] as i16 * 10 + (data[3] & 0x0f) as i16;
+                Reading {
+                    temperature: if data[3] & 0x80 != 0 {
+                        -temperature
+                    } else {
+                        temperature
+                    },
+                    humidity: data[0] as u16 * 10 + data[1] as u16,
+                }
+            }
+            Model::Dht22 => {
+                let temperature = (((data[2] & 0x7f) as i16) << 8) | data[3] as i16;
+                Reading {
+                    temperature: if data[2] & 0x80 != 0 {
+                        -temperature
+                    } else {
+                        temperature
+                    },
+                    humidity: ((data[0] as u16) << 8) | data[1] as u16,
+                }
+            }
+        }
+    }
+
+    /// Waits while the line is at `level` and returns how long that took.
+    fn wait_while(&self, level: bool) -> Result<u32, Error> {
+        let start = crate::micros();
+        loop {
+            let elapsed = crate::micros().wrapping_sub(start);
+            if self.pin.is_high().ok() != Some(level) {
+                return Ok(elapsed);
+            }
+            if elapsed > PHASE_TIMEOUT_MICROS {
+                return Err(Error::Timeout);
+            }
+        }
+    }
+}
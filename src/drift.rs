@@ -0,0 +1,83 @@
+//! Long-term drift of the time base against a reference.
+//!
+//! A [`DriftEstimator`] compares the time base with a more accurate clock,
+//! such as the Timer2 watch crystal RTC or a GPS receiver, over a window that
+//! grows with every sample.  The longer it runs, the less the resolution of
+//! either clock matters, which is what characterizing a board's crystal
+//! takes.
+
+use crate::micros64;
+
+/// Estimates the drift of the time base from pairs of timestamps.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DriftEstimator {
+    first: Option<(u64, u64)>,
+    last: Option<(u64, u64)>,
+}
+
+impl DriftEstimator {
+    /// Creates an estimator with no samples.
+    pub const fn new() -> Self {
+        DriftEstimator {
+            first: None,
+            last: None,
+        }
+    }
+
+    /// Records the reference time in microseconds along with the
+    /// [`micros64`] timestamp taken at the same moment.
+    pub fn sample(&mut self, reference_micros: u64, local_micros: u64) {
+        let sample = (reference_micros, local_micros);
+        if self.first.is_none() {
+            self.first = Some(sample);
+        }
+        self.last = Some(sample);
+    }
+
+    /// Records the time of the watch crystal RTC.
+    ///
+    /// The RTC only resolves 1/256 of a second, so this should be called
+    /// just after its seconds change for the best precision over short
+    /// windows.
+    #[cfg(feature = "rtc")]
+    pub fn sample_rtc(&mut self) {
+        let (reference, local) = avr_device::interrupt::free(|_| {
+            let (seconds, fraction) = crate::rtc::now();
+            let reference = seconds as u64 * 1_000_000
+                + fraction as u64 * 1_000_000 / crate::rtc::COUNTS_PER_SEC as u64;
+            (reference, micros64())
+        });
+        self.sample(reference, local);
+    }
+
+    /// Returns the reference time covered by the samples, in microseconds.
+    pub fn window(&self) -> u64 {
+        match (self.first, self.last) {
+            (Some(first), Some(last)) => last.0 - first.0,
+            _ => 0,
+        }
+    }
+
+    /// Returns the drift in parts per billion, positive if the time base
+    /// runs fast, or `None` until two samples are apart.
+    pub fn ppb(&self) -> Option<i32> {
+        let (first, last) = (self.first?, self.last?);
+        let reference = (last.0 - first.0) as i64;
+        if reference == 0 {
+            return None;
+        }
+        let local = (last.1 - first.1) as i64;
+        Some(((local - reference) * 1_000_000_000 / reference) as i32)
+    }
+
+    /// Returns the drift in parts per million, positive if the time base
+    /// runs fast.
+    pub fn ppm(&self) -> Option<i32> {
+        self.ppb().map(|ppb| ppb / 1000)
+    }
+
+    /// Discards all samples.
+    pub fn reset(&mut self) {
+        *self = DriftEstimator::new();
+    }
+}
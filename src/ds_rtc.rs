@@ -0,0 +1,225 @@
+//! Synchronizing the wall clock to a DS1307 or DS3231 battery-backed RTC.
+//!
+//! The RTC only counts whole seconds, so the [`WallClock`] is set at the
+//! moment its seconds register changes.  From then on the time base
+//! interpolates between the RTC's seconds, and [`Resync`] periodically
+//! realigns the two to cancel out the drift of the board's crystal.
+
+use embedded_hal::blocking::i2c::{Write, WriteRead};
+
+use crate::time::Duration;
+use crate::timeout::Timeout;
+use crate::wall_clock::{DateTime, WallClock};
+
+/// The I2C address shared by both chips.
+pub const ADDRESS: u8 = 0x68;
+
+/// The supported RTC chips.  Their timekeeping registers are laid out the
+/// same way, apart from a few control bits.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Chip {
+    Ds1307,
+    Ds3231,
+}
+
+// Clock halt bit in the DS1307 seconds register.
+const CLOCK_HALT: u8 = 1 << 7;
+// 12-hour mode and PM bits in the hours register.
+const HOURS_12: u8 = 1 << 6;
+const HOURS_PM: u8 = 1 << 5;
+// Century bit in the DS3231 month register.
+const CENTURY: u8 = 1 << 7;
+// The DS3231 status register and its oscillator stop flag.
+const STATUS: u8 = 0x0f;
+const OSCILLATOR_STOPPED: u8 = 1 << 7;
+
+// How long `sync` waits for the seconds to change: a second and some
+// margin for the I2C transfers.
+const SYNC_TIMEOUT: Duration = Duration::from_millis(1100);
+
+/// An error from [`DsRtc::sync`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error<E> {
+    /// The I2C transfer failed.
+    I2c(E),
+    /// The oscillator had stopped, so the time the RTC holds isn't valid.
+    /// The flag has been cleared, which on the DS1307 restarts the
+    /// oscillator, and the time should be set with [`DsRtc::write`].
+    Stopped,
+    /// The seconds didn't change within 1.1 s.
+    TimedOut,
+}
+
+impl<E> From<E> for Error<E> {
+    fn from(error: E) -> Self {
+        Error::I2c(error)
+    }
+}
+
+/// A DS1307 or DS3231 on an I2C bus.
+pub struct DsRtc<I2C> {
+    i2c: I2C,
+    chip: Chip,
+}
+
+impl<I2C, E> DsRtc<I2C>
+where
+    I2C: Write<Error = E> + WriteRead<Error = E>,
+{
+    /// Creates a driver for the given chip.
+    pub fn new(i2c: I2C, chip: Chip) -> Self {
+        DsRtc { i2c, chip }
+    }
+
+    /// Releases the I2C bus.
+    pub fn release(self) -> I2C {
+        self.i2c
+    }
+
+    /// Reads the date and time.  Years are counted from 2000.
+    pub fn read(&mut self) -> Result<DateTime, E> {
+        let mut regs = [0; 7];
+        self.i2c.write_read(ADDRESS, &[0x00], &mut regs)?;
+
+        let hours = regs[2];
+        let hour = if hours & HOURS_12 != 0 {
+            bcd_decode(hours & 0x1f) % 12 + if hours & HOURS_PM != 0 { 12 } else { 0 }
+        } else {
+            bcd_decode(hours & 0x3f)
+        };
+        let mut year = 2000 + bcd_decode(regs[6]) as u16;
+        if self.chip == Chip::Ds3231 && regs[5] & CENTURY != 0 {
+            year += 100;
+        }
+
+        Ok(DateTime {
+            year,
+            month: bcd_decode(regs[5] & 0x1f),
+            day: bcd_decode(regs[4] & 0x3f),
+            hour,
+            minute: bcd_decode(regs[1] & 0x7f),
+            second: bcd_decode(regs[0] & 0x7f),
+        })
+    }
+
+    /// Sets the date and time, in 24-hour mode.  On the DS1307 this also
+    /// starts the oscillator if it was halted.
+    pub fn write(&mut self, date_time: &DateTime) -> Result<(), E> {
+        let mut month = bcd_encode(date_time.month);
+        if self.chip == Chip::Ds3231 && date_time.year >= 2100 {
+            month |= CENTURY;
+        }
+        self.i2c.write(
+            ADDRESS,
+            &[
+                0x00,
+                bcd_encode(date_time.second) & !CLOCK_HALT,
+                bcd_encode(date_time.minute),
+                bcd_encode(date_time.hour),
+                date_time.weekday() + 1,
+                bcd_encode(date_time.day),
+                month,
+                bcd_encode((date_time.year % 100) as u8),
+            ],
+        )
+    }
+
+    /// Sets `clock` from the RTC, waiting for the start of the next second
+    /// so that the clock's fraction of a second is aligned with the RTC.
+    ///
+    /// Blocks for up to a second, and gives up after 1.1 s.  Fails with
+    /// [`Error::Stopped`] if the oscillator had stopped, e.g. because the
+    /// backup battery ran out, since the seconds then don't advance.
+    pub fn sync(&mut self, clock: &mut WallClock) -> Result<(), Error<E>> {
+        if self.clear_stopped()? {
+            return Err(Error::Stopped);
+        }
+        let timeout = Timeout::new(SYNC_TIMEOUT);
+        let first = self.read()?.second;
+        while !timeout.expired() {
+            let now = self.read()?;
+            if now.second != first {
+                clock.set(&now);
+                return Ok(());
+            }
+        }
+        Err(Error::TimedOut)
+    }
+
+    /// Returns `true` if the oscillator had stopped, clearing the DS1307's
+    /// clock halt bit or the DS3231's oscillator stop flag.
+    pub fn clear_stopped(&mut self) -> Result<bool, E> {
+        let mut value = [0];
+        match self.chip {
+            Chip::Ds1307 => {
+                self.i2c.write_read(ADDRESS, &[0x00], &mut value)?;
+                if value[0] & CLOCK_HALT == 0 {
+                    return Ok(false);
+                }
+                self.i2c.write(ADDRESS, &[0x00, value[0] & !CLOCK_HALT])?;
+            }
+            Chip::Ds3231 => {
+                self.i2c.write_read(ADDRESS, &[STATUS], &mut value)?;
+                if value[0] & OSCILLATOR_STOPPED == 0 {
+                    return Ok(false);
+                }
+                self.i2c
+                    .write(ADDRESS, &[STATUS, value[0] & !OSCILLATOR_STOPPED])?;
+            }
+        }
+        Ok(true)
+    }
+}
+
+/// Periodically resynchronizes a wall clock with an RTC without blocking.
+///
+/// Once the interval has passed, each call to [`poll`](Resync::poll) reads
+/// the RTC until its seconds change and then sets the clock, so it should be
+/// called often for the clock to be aligned closely.
+#[derive(Clone, Copy, Debug)]
+pub struct Resync {
+    interval: Timeout,
+    last_second: Option<u8>,
+}
+
+impl Resync {
+    /// Creates a resync that first runs after `interval`.
+    pub fn new(interval: Duration) -> Self {
+        Resync {
+            interval: Timeout::new(interval),
+            last_second: None,
+        }
+    }
+
+    /// Checks the RTC if a resync is due, returning `true` once the clock
+    /// has been set.
+    pub fn poll<I2C, E>(&mut self, rtc: &mut DsRtc<I2C>, clock: &mut WallClock) -> Result<bool, E>
+    where
+        I2C: Write<Error = E> + WriteRead<Error = E>,
+    {
+        if !self.interval.expired() {
+            return Ok(false);
+        }
+        let now = rtc.read()?;
+        match self.last_second {
+            Some(second) if second != now.second => {
+                clock.set(&now);
+                self.interval.restart();
+                self.last_second = None;
+                Ok(true)
+            }
+            _ => {
+                self.last_second = Some(now.second);
+                Ok(false)
+            }
+        }
+    }
+}
+
+fn bcd_decode(value: u8) -> u8 {
+    (value >> 4) * 10 + (value & 0x0f)
+}
+
+fn bcd_encode(value: u8) -> u8 {
+    (value / 10) << 4 | value % 10
+}
@@ -0,0 +1,28 @@
+//! Byte access to the on-chip EEPROM.
+
+/// The EEPROM peripheral.
+pub type Eeprom = crate::pac::EEPROM;
+
+/// Reads the byte at `address`.
+pub(crate) fn read(eeprom: &Eeprom, address: u16) -> u8 {
+    while eeprom.eecr.read().eepe().bit_is_set() {}
+    eeprom.eear.write(|w| unsafe { w.bits(address) });
+    eeprom.eecr.write(|w| w.eere().set_bit());
+    eeprom.eedr.read().bits()
+}
+
+/// Writes `value` to `address`, skipping the write if it is already stored
+/// to save wear.
+pub(crate) fn write(eeprom: &Eeprom, address: u16, value: u8) {
+    if read(eeprom, address) == value {
+        return;
+    }
+    eeprom.eear.write(|w| unsafe { w.bits(address) });
+    eeprom.eedr.write(|w| unsafe { w.bits(value) });
+    // EEPE must be set within four cycles of EEMPE, so the sequence can't be
+    // interrupted.
+    avr_device::interrupt::free(|_| {
+        eeprom.eecr.write(|w| w.eempe().set_bit());
+        eeprom.eecr.write(|w| w.eempe().set_bit().eepe().set_bit());
+    });
+}
@@ -0,0 +1,118 @@
+//! `embassy-time` driver backed by the time base.
+//!
+//! Time is reported in microseconds from [`micros64`].  A single alarm is
+//! provided, which is enough for the embassy executor.  It is checked on
+//! every timer interrupt, and once it is due within a timer period it is
+//! scheduled on the time base timer's second compare unit, so it fires
+//! within a timer count of its timestamp plus the interrupt latency.  The
+//! ATtiny85, ATmega4809 and `arduino-core` backends have no such unit to
+//! spare, so there alarms fire with the resolution of one timer period.
+
+use core::cell;
+
+use avr_device::interrupt::{CriticalSection, Mutex};
+use embassy_time::driver::{AlarmHandle, Driver};
+
+use crate::{micros64, micros64_in};
+
+struct Callback {
+    func: fn(*mut ()),
+    ctx: *mut (),
+}
+
+// The context pointer is only ever handed back to the callback registered
+// with it, from inside a critical section.
+unsafe impl Send for Callback {}
+
+struct AlarmState {
+    allocated: cell::Cell<bool>,
+    timestamp: cell::Cell<u64>,
+    callback: cell::Cell<Option<Callback>>,
+}
+
+struct MicrosDriver {
+    alarm: Mutex<AlarmState>,
+}
+
+embassy_time::time_driver_impl!(static DRIVER: MicrosDriver = MicrosDriver {
+    alarm: Mutex::new(AlarmState {
+        allocated: cell::Cell::new(false),
+        timestamp: cell::Cell::new(u64::MAX),
+        callback: cell::Cell::new(None),
+    }),
+});
+
+impl Driver for MicrosDriver {
+    fn now(&self) -> u64 {
+        micros64()
+    }
+
+    unsafe fn allocate_alarm(&self) -> Option<AlarmHandle> {
+        avr_device::interrupt::free(|cs| {
+            let alarm = self.alarm.borrow(cs);
+            if alarm.allocated.replace(true) {
+                None
+            } else {
+                Some(AlarmHandle::new(0))
+            }
+        })
+    }
+
+    fn set_alarm_callback(&self, _alarm: AlarmHandle, callback: fn(*mut ()), ctx: *mut ()) {
+        avr_device::interrupt::free(|cs| {
+            let callback = Callback {
+                func: callback,
+                ctx,
+            };
+            self.alarm.borrow(cs).callback.set(Some(callback));
+        })
+    }
+
+    fn set_alarm(&self, _alarm: AlarmHandle, timestamp: u64) -> bool {
+        avr_device::interrupt::free(|cs| {
+            let alarm = self.alarm.borrow(cs);
+            let now = micros64_in(cs);
+            if timestamp <= now {
+                alarm.timestamp.set(u64::MAX);
+                false
+            } else {
+                alarm.timestamp.set(timestamp);
+                #[cfg(not(any(
+                    feature = "attiny85",
+                    feature = "atmega4809",
+                    feature = "arduino-core"
+                )))]
+                crate::schedule_compare_b(cs, timestamp, now);
+                true
+            }
+        })
+    }
+}
+
+/// Fires the alarm if it is due, or schedules it on compare unit B if it is
+/// due within a period.  Called from the timer ISR after the counters have
+/// been advanced.
+pub(crate) fn on_tick(cs: &CriticalSection) {
+    let alarm = DRIVER.alarm.borrow(cs);
+    let timestamp = alarm.timestamp.get();
+    let now = micros64_in(cs);
+    if timestamp > now {
+        #[cfg(not(any(feature = "attiny85", feature = "atmega4809", feature = "arduino-core")))]
+        crate::schedule_compare_b(cs, timestamp, now);
+        return;
+    }
+
+    alarm.timestamp.set(u64::MAX);
+    if let Some(callback) = alarm.callback.take() {
+        (callback.func)(callback.ctx);
+        alarm.callback.set(Some(callback));
+    }
+}
+
+/// Fires the alarm if it is due, or schedules the next match otherwise.
+/// Called from the compare unit B ISR.
+#[cfg(not(any(feature = "attiny85", feature = "atmega4809", feature = "arduino-core")))]
+pub(crate) fn on_compare(cs: &CriticalSection) {
+    crate::timer::disarm_compare_b();
+    on_tick(cs);
+}
@@ -0,0 +1,146 @@
+//! Quadrature rotary encoders with speed measurement.
+//!
+//! [`on_change`] should be called with the levels of both encoder pins on
+//! every change of either, typically from their pin change interrupt, e.g.
+//! PCINT2 for pins 2 and 3 of the Uno.  It decodes the Gray code sequence
+//! into detents and queues each with its time in an [`EventQueue`].
+//! [`read`] drains the queue and derives the rotation speed from the time
+//! between detents, so a knob can move further the faster it is turned.
+
+use core::cell;
+
+use avr_device::interrupt::Mutex;
+
+use crate::event_queue::EventQueue;
+use crate::time::{Duration, Instant};
+
+/// The number of detents that can be queued between two calls to [`read`]
+/// before new ones are dropped.
+pub const QUEUE_LEN: usize = 16;
+
+// Steps further apart than this mean the knob was at rest in between.
+const REST_TIMEOUT: Duration = Duration::from_millis(200);
+
+// The step for each transition, indexed by the previous and the current
+// levels of A and B as `0bABAB`.  Invalid transitions, skipping a state,
+// count as no step.
+const TRANSITIONS: [i8; 16] = [0, -1, 1, 0, 1, 0, 0, -1, -1, 0, 0, 1, 0, 1, -1, 0];
+
+/// The movement since the previous [`read`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Motion {
+    /// The number of detents turned, positive when A changes before B.
+    pub delta: i16,
+    /// The current speed in detents per second, zero at rest.
+    pub velocity: i32,
+    /// The change in speed over the last detent, in detents per second
+    /// squared.
+    pub acceleration: i32,
+}
+
+#[derive(Clone, Copy)]
+struct Decoder {
+    levels: u8,
+    steps_per_detent: u8,
+    partial: i8,
+}
+
+#[derive(Clone, Copy)]
+struct Speed {
+    last_detent: Option<Instant>,
+    velocity: i32,
+    acceleration: i32,
+}
+
+static DECODER: Mutex<cell::Cell<Decoder>> = Mutex::new(cell::Cell::new(Decoder {
+    levels: 0,
+    steps_per_detent: 4,
+    partial: 0,
+}));
+
+static SPEED: Mutex<cell::Cell<Speed>> = Mutex::new(cell::Cell::new(Speed {
+    last_detent: None,
+    velocity: 0,
+    acceleration: 0,
+}));
+
+// The direction of each detent, 1 or -1.
+static DETENTS: EventQueue<i8, QUEUE_LEN> = EventQueue::new();
+
+/// Resets the encoder state given the current pin levels.  Most encoders
+/// go through all four steps of the sequence between two detents, some
+/// only one or two.
+pub fn init(a: bool, b: bool, steps_per_detent: u8) {
+    avr_device::interrupt::free(|cs| {
+        DECODER.borrow(cs).set(Decoder {
+            levels: levels(a, b),
+            steps_per_detent: steps_per_detent.max(1),
+            partial: 0,
+        });
+        SPEED.borrow(cs).set(Speed {
+            last_detent: None,
+            velocity: 0,
+            acceleration: 0,
+        });
+    });
+    DETENTS.clear();
+}
+
+/// Processes a change of the encoder pins, which are now at `a` and `b`.
+pub fn on_change(a: bool, b: bool) {
+    avr_device::interrupt::free(|cs| {
+        let now = crate::now_in(cs);
+        let cell = DECODER.borrow(cs);
+        let mut decoder = cell.get();
+        let current = levels(a, b);
+        decoder.partial += TRANSITIONS[((decoder.levels << 2) | current) as usize];
+        decoder.levels = current;
+
+        if decoder.partial.unsigned_abs() >= decoder.steps_per_detent {
+            DETENTS.push_in(cs, now, decoder.partial.signum());
+            decoder.partial = 0;
+        }
+        cell.set(decoder);
+    })
+}
+
+/// Takes the detents turned since the previous call, along with the
+/// current speed.
+pub fn read() -> Motion {
+    let mut delta = 0i16;
+    while let Some((at, direction)) = DETENTS.pop() {
+        delta = delta.saturating_add(direction as i16);
+        avr_device::interrupt::free(|cs| {
+            let cell = SPEED.borrow(cs);
+            let mut speed = cell.get();
+            let previous = speed.velocity;
+            speed.velocity = 0;
+            speed.acceleration = 0;
+            if let Some(last) = speed.last_detent {
+                let interval = (at - last).as_micros();
+                if interval > 0 && interval <= REST_TIMEOUT.as_micros() {
+                    speed.velocity = direction as i32 * (1_000_000 / interval) as i32;
+                    speed.acceleration =
+                        ((speed.velocity - previous) as i64 * 1_000_000 / interval as i64) as i32;
+                }
+            }
+            speed.last_detent = Some(at);
+            cell.set(speed);
+        });
+    }
+
+    let speed = avr_device::interrupt::free(|cs| SPEED.borrow(cs).get());
+    let moving = match speed.last_detent {
+        Some(last) => crate::now() - last <= REST_TIMEOUT,
+        None => false,
+    };
+    Motion {
+        delta,
+        velocity: if moving { speed.velocity } else { 0 },
+        acceleration: if moving { speed.acceleration } else { 0 },
+    }
+}
+
+fn levels(a: bool, b: bool) -> u8 {
+    ((a as u8) << 1) | b as u8
+}
@@ -0,0 +1,79 @@
+//! An entropy pool fed by the timer count at asynchronous events.
+//!
+//! Bytes arriving over serial and pin changes happen at times the CPU
+//! doesn't control, so the low bits of the timer count when their ISRs run
+//! vary unpredictably.  With this feature the `serial-rx`, `pcint-log` and
+//! `ext-int` ISRs mix the count into a pool, and [`mix`] lets applications
+//! feed it from their own interrupts too.  [`random_u32`] then draws a seed
+//! from it, e.g. for a PRNG.
+//!
+//! Each event contributes only a bit or two of real entropy, and events
+//! the firmware itself triggers, such as a pin it toggles, contribute
+//! none, so wait until [`samples`] reports a few dozen before drawing a
+//! seed that matters.  This is not a source for cryptographic keys.
+
+use core::cell;
+
+use avr_device::interrupt::{CriticalSection, Mutex};
+
+#[derive(Clone, Copy)]
+struct Pool {
+    state: u32,
+    samples: u16,
+}
+
+static POOL: Mutex<cell::Cell<Pool>> = Mutex::new(cell::Cell::new(Pool {
+    state: 0,
+    samples: 0,
+}));
+
+/// Mixes the current timer count into the pool.  Call it from an ISR whose
+/// timing isn't under the firmware's control.
+pub fn mix() {
+    avr_device::interrupt::free(mix_in)
+}
+
+/// Like [`mix`], reusing a critical section the caller already holds.
+pub fn mix_in(cs: &CriticalSection) {
+    let cell = POOL.borrow(cs);
+    let pool = cell.get();
+    let counts = crate::timer::counts() as u32;
+    cell.set(Pool {
+        // A rotate and add spread the changing low bits over the word
+        // cheaply enough for an ISR; `random_u32` does the real mixing.
+        state: pool.state.rotate_left(7).wrapping_add(counts),
+        samples: pool.samples.saturating_add(1),
+    })
+}
+
+/// Returns the number of events mixed into the pool so far, saturating at
+/// `u16::MAX`.
+pub fn samples() -> u16 {
+    avr_device::interrupt::free(|cs| POOL.borrow(cs).get().samples)
+}
+
+/// Draws 32 bits from the pool.
+///
+/// The pool is stirred with the time as well, so consecutive calls return
+/// different values, but they carry no more entropy between them than the
+/// events mixed in since boot.
+pub fn random_u32() -> u32 {
+    avr_device::interrupt::free(|cs| {
+        let cell = POOL.borrow(cs);
+        let mut pool = cell.get();
+        let value = finalize(pool.state ^ crate::micros_in(cs));
+        pool.state = pool.state.rotate_left(13) ^ value;
+        cell.set(pool);
+        value
+    })
+}
+
+// The MurmurHash3 finalizer, so every bit of the pool affects every bit
+// of the result.
+fn finalize(mut x: u32) -> u32 {
+    x ^= x >> 16;
+    x = x.wrapping_mul(0x85eb_ca6b);
+    x ^= x >> 13;
+    x = x.wrapping_mul(0xc2b2_ae35);
+    x ^ (x >> 16)
+}
@@ -0,0 +1,111 @@
+//! A queue of timestamped events from ISRs.
+
+use core::cell;
+
+use avr_device::interrupt::{CriticalSection, Mutex};
+
+use crate::time::Instant;
+
+/// A fixed capacity queue of `(Instant, T)` events, usually filled by an ISR
+/// and drained by the main loop.
+///
+/// It is meant to be placed in a `static` and accessed by shared reference
+/// from both sides.  Every operation runs in a short critical section, so
+/// producers and consumers may be in any context.  Events pushed while the
+/// queue holds `N` events are dropped and counted.
+pub struct EventQueue<T, const N: usize> {
+    slots: Mutex<cell::Cell<[Option<(Instant, T)>; N]>>,
+    head: Mutex<cell::Cell<usize>>,
+    len: Mutex<cell::Cell<usize>>,
+    dropped: Mutex<cell::Cell<u16>>,
+}
+
+impl<T: Copy, const N: usize> EventQueue<T, N> {
+    /// Creates an empty queue.
+    pub const fn new() -> Self {
+        EventQueue {
+            slots: Mutex::new(cell::Cell::new([None; N])),
+            head: Mutex::new(cell::Cell::new(0)),
+            len: Mutex::new(cell::Cell::new(0)),
+            dropped: Mutex::new(cell::Cell::new(0)),
+        }
+    }
+
+    /// Appends an event that happened at `at`.  Returns `false` if the queue
+    /// was full and the event was dropped.
+    pub fn push(&self, at: Instant, event: T) -> bool {
+        avr_device::interrupt::free(|cs| self.push_in(cs, at, event))
+    }
+
+    /// Like [`push`](EventQueue::push), but within a critical section the
+    /// caller already holds, e.g. in an ISR.
+    pub fn push_in(&self, cs: &CriticalSection, at: Instant, event: T) -> bool {
+        let len = self.len.borrow(cs);
+        if len.get() == N {
+            let dropped = self.dropped.borrow(cs);
+            dropped.set(dropped.get().wrapping_add(1));
+            return false;
+        }
+        let tail = (self.head.borrow(cs).get() + len.get()) % N;
+        self.slots(cs)[tail].set(Some((at, event)));
+        len.set(len.get() + 1);
+        true
+    }
+
+    /// Takes the oldest event.
+    pub fn pop(&self) -> Option<(Instant, T)> {
+        avr_device::interrupt::free(|cs| self.pop_in(cs))
+    }
+
+    /// Like [`pop`](EventQueue::pop), but within a critical section the
+    /// caller already holds.
+    pub fn pop_in(&self, cs: &CriticalSection) -> Option<(Instant, T)> {
+        let len = self.len.borrow(cs);
+        if len.get() == 0 {
+            return None;
+        }
+        let head = self.head.borrow(cs);
+        let event = self.slots(cs)[head.get()].take();
+        head.set((head.get() + 1) % N);
+        len.set(len.get() - 1);
+        event
+    }
+
+    /// Returns the number of queued events.
+    pub fn len(&self) -> usize {
+        avr_device::interrupt::free(|cs| self.len.borrow(cs).get())
+    }
+
+    /// Returns `true` if no events are queued.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of events dropped because the queue was full.
+    pub fn dropped(&self) -> u16 {
+        avr_device::interrupt::free(|cs| self.dropped.borrow(cs).get())
+    }
+
+    /// Discards all queued events and resets the dropped count.
+    pub fn clear(&self) {
+        avr_device::interrupt::free(|cs| {
+            for slot in self.slots(cs) {
+                slot.set(None);
+            }
+            self.head.borrow(cs).set(0);
+            self.len.borrow(cs).set(0);
+            self.dropped.borrow(cs).set(0);
+        })
+    }
+
+    fn slots<'cs>(&'cs self, cs: &'cs CriticalSection) -> &'cs [cell::Cell<Option<(Instant, T)>>] {
+        let slots: &cell::Cell<[Option<(Instant, T)>]> = self.slots.borrow(cs);
+        slots.as_slice_of_cells()
+    }
+}
+
+impl<T: Copy, const N: usize> Default for EventQueue<T, N> {
+    fn default() -> Self {
+        EventQueue::new()
+    }
+}
@@ -0,0 +1,164 @@
+//! A minimal cooperative executor for `async` tasks.
+//!
+//! [`run`] polls a fixed set of tasks forever, only polling a task again once
+//! it has been woken.  The [`delay`] future is woken by the timer ISR once
+//! its deadline has passed, so tasks can wait without blocking each other,
+//! and while no task is woken the CPU idles.
+//! See `examples/async_blink.rs`.
+//!
+//! At most [`MAX_TASKS`] tasks are supported.
+
+use core::cell;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+use avr_device::interrupt::{CriticalSection, Mutex};
+
+use crate::time::{time_after, Duration, Instant};
+
+/// The number of tasks that can be passed to [`run`].
+pub const MAX_TASKS: usize = 8;
+
+// Bit mask of the tasks that need to be polled again.
+static WOKEN: Mutex<cell::Cell<u8>> = Mutex::new(cell::Cell::new(0));
+
+// The task currently being polled, so that delays know whom to wake.
+static CURRENT: Mutex<cell::Cell<Option<u8>>> = Mutex::new(cell::Cell::new(None));
+
+// The earliest pending delay of each task.
+static DEADLINES: Mutex<[cell::Cell<Option<Instant>>; MAX_TASKS]> = Mutex::new([
+    cell::Cell::new(None),
+    cell::Cell::new(None),
+    cell::Cell::new(None),
+    cell::Cell::new(None),
+    cell::Cell::new(None),
+    cell::Cell::new(None),
+    cell::Cell::new(None),
+    cell::Cell::new(None),
+]);
+
+fn wake_task(index: u8) {
+    avr_device::interrupt::free(|cs| {
+        let woken = WOKEN.borrow(cs);
+        woken.set(woken.get() | 1 << index);
+    })
+}
+
+// The waker data is the index of the task rather than a pointer.
+static VTABLE: RawWakerVTable =
+    RawWakerVTable::new(waker_clone, waker_wake, waker_wake, waker_drop);
+
+unsafe fn waker_clone(data: *const ()) -> RawWaker {
+    RawWaker::new(data, &VTABLE)
+}
+
+unsafe fn waker_wake(data: *const ()) {
+    wake_task(data as usize as u8);
+}
+
+unsafe fn waker_drop(_data: *const ()) {}
+
+fn task_waker(index: u8) -> Waker {
+    let raw = RawWaker::new(index as usize as *const (), &VTABLE);
+    unsafe { Waker::from_raw(raw) }
+}
+
+/// Runs `tasks` forever.
+///
+/// Tasks that complete are not polled again.  Panics if more than
+/// [`MAX_TASKS`] tasks are given.
+pub fn run(tasks: &mut [&mut dyn Future<Output = ()>]) -> ! {
+    assert!(tasks.len() <= MAX_TASKS);
+
+    let all = (1u16 << tasks.len()) - 1;
+    let mut done = 0u8;
+    avr_device::interrupt::free(|cs| WOKEN.borrow(cs).set(all as u8));
+
+    loop {
+        let woken = avr_device::interrupt::free(|cs| WOKEN.borrow(cs).replace(0)) & !done;
+
+        for (index, task) in tasks.iter_mut().enumerate() {
+            let index = index as u8;
+            if woken & 1 << index == 0 {
+                continue;
+            }
+
+            avr_device::interrupt::free(|cs| CURRENT.borrow(cs).set(Some(index)));
+            let waker = task_waker(index);
+            let mut cx = Context::from_waker(&waker);
+
+            // The tasks are borrowed for the rest of the program as this
+            // function never returns, so they can't be moved once pinned.
+            let task = unsafe { Pin::new_unchecked(&mut **task) };
+            if task.poll(&mut cx).is_ready() {
+                done |= 1 << index;
+            }
+        }
+
+        avr_device::interrupt::free(|cs| CURRENT.borrow(cs).set(None));
+
+        // Nothing to do until an interrupt wakes a task, which always
+        // happens within a timer period for tasks waiting on a delay.
+        crate::power::sleep_unless(crate::power::Mode::Idle, |cs| {
+            WOKEN.borrow(cs).get() & !done != 0
+        });
+    }
+}
+
+/// Wakes the tasks whose delays have expired.  Called from the timer ISR.
+pub(crate) fn on_tick(cs: &CriticalSection) {
+    let now = crate::now_in(cs);
+    let woken = WOKEN.borrow(cs);
+    for (index, deadline) in DEADLINES.borrow(cs).iter().enumerate() {
+        match deadline.get() {
+            Some(at) if !time_after(at.as_micros(), now.as_micros()) => {
+                deadline.set(None);
+                woken.set(woken.get() | 1 << index);
+            }
+            _ => (),
+        }
+    }
+}
+
+/// A future that completes once its deadline has passed.
+#[derive(Clone, Copy, Debug)]
+pub struct Delay {
+    deadline: Instant,
+}
+
+/// Returns a future that completes after `duration` has elapsed.
+pub fn delay(duration: Duration) -> Delay {
+    delay_until(crate::now() + duration)
+}
+
+/// Returns a future that completes once `deadline` has passed.
+pub fn delay_until(deadline: Instant) -> Delay {
+    Delay { deadline }
+}
+
+impl Future for Delay {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let now = crate::now();
+        if !time_after(self.deadline.as_micros(), now.as_micros()) {
+            return Poll::Ready(());
+        }
+
+        avr_device::interrupt::free(|cs| match CURRENT.borrow(cs).get() {
+            Some(index) => {
+                let slot = &DEADLINES.borrow(cs)[index as usize];
+                let earliest = match slot.get() {
+                    Some(at) if time_after(self.deadline.as_micros(), at.as_micros()) => at,
+                    _ => self.deadline,
+                };
+                slot.set(Some(earliest));
+            }
+            // Polled by a different executor, which can't be woken from the
+            // ISR, so ask to be polled again straight away.
+            None => cx.waker().wake_by_ref(),
+        });
+        Poll::Pending
+    }
+}
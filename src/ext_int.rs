@@ -0,0 +1,112 @@
+//! Timestamped external interrupts on INT0 and INT1.
+//!
+//! A handler is attached to each interrupt and called from its ISR with the
+//! time of the edge, which is read before anything else so the latency is
+//! as short and constant as possible.  Handlers run with interrupts
+//! disabled; to read the time base from other ISRs, [`micros_in`] and
+//! [`now_in`] reuse the critical section the handler already holds.
+//!
+//! [`micros_in`]: crate::micros_in
+//! [`now_in`]: crate::now_in
+
+#[cfg(any(feature = "attiny85", feature = "atmega4809"))]
+compile_error!("the `ext-int` feature is not supported on this device");
+
+#[cfg(feature = "pps")]
+compile_error!("the `ext-int` and `pps` features are mutually exclusive");
+
+use core::cell;
+
+use avr_device::interrupt::Mutex;
+
+use crate::time::Instant;
+
+/// The external interrupt peripheral.
+pub type ExtInt = crate::pac::EXINT;
+
+/// An external interrupt line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Interrupt {
+    /// Pin 2 on the Uno.
+    Int0,
+    /// Pin 3 on the Uno.
+    Int1,
+}
+
+impl Interrupt {
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// The condition triggering the interrupt.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Sense {
+    /// Repeatedly while the pin is low.
+    Low,
+    /// On both edges.
+    Change,
+    Falling,
+    Rising,
+}
+
+static HANDLERS: Mutex<cell::Cell<[Option<fn(Instant)>; 2]>> =
+    Mutex::new(cell::Cell::new([None; 2]));
+
+/// Calls `handler` with the time of each `sense` event on `interrupt`,
+/// replacing any handler attached before.
+pub fn attach(exint: &ExtInt, interrupt: Interrupt, sense: Sense, handler: fn(Instant)) {
+    let index = interrupt.index();
+    let isc = (sense as u8) << (2 * index);
+    avr_device::interrupt::free(|cs| {
+        let cell = HANDLERS.borrow(cs);
+        let mut handlers = cell.get();
+        handlers[index] = Some(handler);
+        cell.set(handlers);
+
+        exint
+            .eicra
+            .modify(|r, w| unsafe { w.bits((r.bits() & !(0b11 << (2 * index))) | isc) });
+        exint.eifr.write(|w| unsafe { w.bits(1 << index) });
+        exint
+            .eimsk
+            .modify(|r, w| unsafe { w.bits(r.bits() | (1 << index)) });
+    })
+}
+
+/// Disables `interrupt` and removes its handler.
+pub fn detach(exint: &ExtInt, interrupt: Interrupt) {
+    let index = interrupt.index();
+    avr_device::interrupt::free(|cs| {
+        exint
+            .eimsk
+            .modify(|r, w| unsafe { w.bits(r.bits() & !(1 << index)) });
+        let cell = HANDLERS.borrow(cs);
+        let mut handlers = cell.get();
+        handlers[index] = None;
+        cell.set(handlers);
+    })
+}
+
+fn dispatch(interrupt: Interrupt) {
+    avr_device::interrupt::free(|cs| {
+        let now = crate::now_in(cs);
+        #[cfg(feature = "entropy")]
+        crate::entropy::mix_in(cs);
+        if let Some(handler) = HANDLERS.borrow(cs).get()[interrupt.index()] {
+            handler(now);
+        }
+    })
+}
+
+isr! {
+    fn INT0() {
+        dispatch(Interrupt::Int0)
+    }
+}
+
+isr! {
+    fn INT1() {
+        dispatch(Interrupt::Int1)
+    }
+}
@@ -0,0 +1,120 @@
+//! Frequency measurement by counting edges on Timer1's T1 pin.
+//!
+//! Timer1 is clocked from the rising edges of T1 (pin 5 on the Uno) and
+//! counts them in hardware, with an overflow interrupt extending the count
+//! to 32 bits.  The time base serves as the gate: the frequency is the
+//! number of edges counted divided by the time actually elapsed, so a late
+//! reading doesn't skew the result.
+//!
+//! The input is sampled by the CPU clock, so it works up to about 40% of
+//! the clock frequency, i.e. around 6 MHz at 16 MHz.
+
+#[cfg(any(feature = "attiny85", feature = "atmega4809"))]
+compile_error!("the `freq-counter` feature requires a device with Timer1");
+
+#[cfg(feature = "timer1")]
+compile_error!("the `freq-counter` and `timer1` features are mutually exclusive");
+
+#[cfg(feature = "input-capture")]
+compile_error!("the `freq-counter` and `input-capture` features are mutually exclusive");
+
+use core::cell;
+
+use avr_device::interrupt::Mutex;
+
+use crate::micros64;
+use crate::time::Duration;
+
+/// The timer peripheral counting the edges.
+pub type CounterTimer = crate::pac::TC1;
+
+// Clock select for an external clock on T1, rising edge.
+const EXTERNAL_RISING: u8 = 0b111;
+const TOIE1: u8 = 1 << 0;
+const TOV1: u8 = 1 << 0;
+
+static OVERFLOWS: Mutex<cell::Cell<u16>> = Mutex::new(cell::Cell::new(0));
+
+/// Counts edges on T1 over a gate time.
+pub struct FrequencyCounter {
+    tc1: CounterTimer,
+    gate: u64,
+    // The time and edge count at the start of the current gate.
+    start: (u64, u32),
+    last: Option<u32>,
+}
+
+impl FrequencyCounter {
+    /// Starts counting, producing a reading every `gate`.  Longer gates give
+    /// a finer resolution: 1 Hz with a one second gate.
+    pub fn new(tc1: CounterTimer, gate: Duration) -> Self {
+        avr_device::interrupt::free(|cs| {
+            OVERFLOWS.borrow(cs).set(0);
+            tc1.tccr1a.write(|w| unsafe { w.bits(0) });
+            tc1.tcnt1.write(|w| unsafe { w.bits(0) });
+            tc1.tifr1.write(|w| unsafe { w.bits(TOV1) });
+            tc1.timsk1.write(|w| unsafe { w.bits(TOIE1) });
+            tc1.tccr1b.write(|w| unsafe { w.bits(EXTERNAL_RISING) });
+        });
+        FrequencyCounter {
+            tc1,
+            gate: gate.as_micros() as u64,
+            start: sample(),
+            last: None,
+        }
+    }
+
+    /// Returns the frequency measured over the last complete gate, or `None`
+    /// until the first gate has passed.
+    ///
+    /// Readings are only updated while this is being called, so it should be
+    /// called at least once per gate time.
+    pub fn frequency_hz(&mut self) -> Option<u32> {
+        let (now, count) = sample();
+        let elapsed = now - self.start.0;
+        if elapsed >= self.gate {
+            let edges = count.wrapping_sub(self.start.1) as u64;
+            self.last = Some((edges * 1_000_000 / elapsed) as u32);
+            self.start = (now, count);
+        }
+        self.last
+    }
+
+    /// Returns the total number of edges counted, wrapping around after
+    /// 2^32.
+    pub fn count(&self) -> u32 {
+        sample().1
+    }
+
+    /// Stops counting and releases the timer.
+    pub fn release(self) -> CounterTimer {
+        self.tc1.tccr1b.write(|w| unsafe { w.bits(0) });
+        self.tc1.timsk1.write(|w| unsafe { w.bits(0) });
+        self.tc1
+    }
+}
+
+/// Returns the current time and extended edge count, taken together.
+fn sample() -> (u64, u32) {
+    let tc1 = unsafe { &*CounterTimer::ptr() };
+    avr_device::interrupt::free(|cs| {
+        let mut overflows = OVERFLOWS.borrow(cs).get();
+        let mut counts = tc1.tcnt1.read().bits();
+        if tc1.tifr1.read().bits() & TOV1 != 0 {
+            // The overflow hasn't been counted by the ISR yet.  Sample again
+            // in case it happened just after the first read.
+            counts = tc1.tcnt1.read().bits();
+            overflows = overflows.wrapping_add(1);
+        }
+        (micros64(), (overflows as u32) << 16 | counts as u32)
+    })
+}
+
+isr! {
+    fn TIMER1_OVF() {
+        avr_device::interrupt::free(|cs| {
+            let cell = OVERFLOWS.borrow(cs);
+            cell.set(cell.get().wrapping_add(1));
+        })
+    }
+}
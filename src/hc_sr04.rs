@@ -0,0 +1,92 @@
+//! HC-SR04 ultrasonic distance sensor.
+//!
+//! A 10 us pulse on the trigger pin makes the sensor send a burst of
+//! ultrasound, after which its echo pin stays high for as long as the sound
+//! took to travel to the obstacle and back.
+
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+use crate::delay::delay_micros;
+use crate::pulse::pulse_in;
+use crate::time::Duration;
+
+// Beyond about 5 m the echo is too weak to be detected anyway.
+const DEFAULT_TIMEOUT: Duration = Duration::from_millis(30);
+
+/// An HC-SR04 on a trigger and an echo pin.
+pub struct HcSr04<Trigger, Echo> {
+    trigger: Trigger,
+    echo: Echo,
+    timeout: Duration,
+    temperature: Option<i8>,
+}
+
+impl<Trigger: OutputPin, Echo: InputPin> HcSr04<Trigger, Echo> {
+    /// Creates a driver.  The trigger pin is driven low.
+    pub fn new(mut trigger: Trigger, echo: Echo) -> Self {
+        trigger.set_low().ok();
+        HcSr04 {
+            trigger,
+            echo,
+            timeout: DEFAULT_TIMEOUT,
+            temperature: None,
+        }
+    }
+
+    /// Sets how long to wait for the echo, which limits the range.  The
+    /// default of 30 ms covers the sensor's full range.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Sets the air temperature in degrees Celsius used to compute the
+    /// speed of sound, or `None` to assume 20 degrees.
+    pub fn set_temperature(&mut self, celsius: Option<i8>) {
+        self.temperature = celsius;
+    }
+
+    /// Triggers a measurement and returns the distance in millimeters, or
+    /// `None` if no echo was received in time.
+    ///
+    /// The sensor needs about 60 ms between measurements to let previous
+    /// echoes die down.
+    pub fn measure(&mut self) -> Option<u16> {
+        self.trigger.set_high().ok()?;
+        delay_micros(10);
+        self.trigger.set_low().ok()?;
+
+        let echo = pulse_in(&self.echo, true, self.timeout)?;
+        Some(self.echo_to_millimeters(echo))
+    }
+
+    /// Like [`measure`](HcSr04::measure), but times the echo with the input
+    /// capture unit, so the echo pin must be wired to ICP1 and `capture` set
+    /// up for both edges.  This is unaffected by interrupts arriving during
+    /// the echo.
+    #[cfg(feature = "input-capture")]
+    pub fn measure_capture(
+        &mut self,
+        capture: &mut crate::input_capture::InputCapture,
+    ) -> Option<u16> {
+        self.trigger.set_high().ok()?;
+        delay_micros(10);
+        self.trigger.set_low().ok()?;
+
+        let echo = crate::pulse::pulse_in_capture(capture, true, self.timeout)?;
+        Some(self.echo_to_millimeters(echo))
+    }
+
+    /// Converts the length of an echo pulse into millimeters.
+    pub fn echo_to_millimeters(&self, echo: Duration) -> u16 {
+        // The speed of sound is 331.3 m/s plus 0.606 m/s per degree.
+        let celsius = self.temperature.unwrap_or(20) as i32;
+        let speed_um_per_ms = (331_300 + 606 * celsius) as u32;
+        // Halved for the round trip.
+        (echo.as_micros() as u64 * speed_um_per_ms as u64 / 2_000_000) as u16
+    }
+
+    /// Releases the pins.
+    pub fn release(self) -> (Trigger, Echo) {
+        (self.trigger, self.echo)
+    }
+}
@@ -0,0 +1,86 @@
+//! A blinking liveness indicator, e.g. on the on-board LED on D13.
+//!
+//! The LED is switched by [`Heartbeat::poll`] from the main loop, next to
+//! [`Scheduler::poll`](crate::scheduler::Scheduler::poll), with the blink
+//! timing taken from the time base.  A hung main loop leaves it stuck on or
+//! off, and so does a stalled time base even while the loop keeps running.
+
+use embedded_hal::digital::v2::OutputPin;
+
+use crate::time::{time_after, Duration, Instant};
+
+/// A blink pattern: the LED is on for the first step, off for the second,
+/// and so on, each step lasting the given number of milliseconds.
+///
+/// A pattern needs an even, nonzero number of steps of at least 1 ms each.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Pattern(pub &'static [u16]);
+
+impl Pattern {
+    /// A slow, even blink, once a second.
+    pub const SLOW: Pattern = Pattern(&[500, 500]);
+    /// A double pulse once a second, like a heartbeat.
+    pub const HEARTBEAT: Pattern = Pattern(&[100, 150, 100, 650]);
+    /// A fast blink, e.g. to signal an error.
+    pub const FAST: Pattern = Pattern(&[100, 100]);
+}
+
+/// Blinks an LED in a [`Pattern`].
+pub struct Heartbeat<P> {
+    pin: P,
+    pattern: Pattern,
+    step: usize,
+    next: Instant,
+}
+
+impl<P: OutputPin> Heartbeat<P> {
+    /// Starts blinking `pin` in `pattern`.
+    pub fn new(pin: P, pattern: Pattern) -> Self {
+        let mut heartbeat = Heartbeat {
+            pin,
+            pattern,
+            step: 0,
+            next: crate::now(),
+        };
+        heartbeat.set_pattern(pattern);
+        heartbeat
+    }
+
+    /// Switches to `pattern`, starting with its first step now.
+    pub fn set_pattern(&mut self, pattern: Pattern) {
+        self.pattern = pattern;
+        self.step = 0;
+        self.next = crate::now() + self.step_duration();
+        self.pin.set_high().ok();
+    }
+
+    /// Moves on to the next step if it is due.
+    ///
+    /// Steps are timed from when they were due, so the pattern doesn't
+    /// drift if this is called late.  After a long delay the missed steps
+    /// are skipped rather than flashed in a burst.
+    pub fn poll(&mut self) {
+        let now = crate::now();
+        if time_after(self.next.as_micros(), now.as_micros()) {
+            return;
+        }
+        while !time_after(self.next.as_micros(), now.as_micros()) {
+            self.step = (self.step + 1) % self.pattern.0.len();
+            self.next += self.step_duration();
+        }
+        if self.step % 2 == 0 {
+            self.pin.set_high().ok();
+        } else {
+            self.pin.set_low().ok();
+        }
+    }
+
+    /// Stops blinking and returns the pin.
+    pub fn release(self) -> P {
+        self.pin
+    }
+
+    fn step_duration(&self) -> Duration {
+        Duration::from_millis(self.pattern.0[self.step] as u32)
+    }
+}
@@ -0,0 +1,246 @@
+//! Hardware-latched edge timestamps on Timer1's ICP1 pin.
+//!
+//! On an edge of ICP1 (pin 8 on the Uno), Timer1 copies its counter into
+//! ICR1 in hardware, so the timestamp doesn't depend on how quickly the
+//! interrupt is serviced.  The ISR extends the 16-bit captures to 32 bits
+//! with a count of timer overflows and queues them for the main loop.
+//!
+//! Timer1 runs freely for this and can't drive the time base at the same
+//! time.
+
+#[cfg(any(feature = "attiny85", feature = "atmega4809"))]
+compile_error!("the `input-capture` feature requires a device with Timer1 input capture");
+
+#[cfg(feature = "timer1")]
+compile_error!("the `input-capture` and `timer1` features are mutually exclusive");
+
+use core::cell;
+
+use avr_device::interrupt::{CriticalSection, Mutex};
+
+use crate::CLOCK_MHZ;
+
+/// The timer peripheral used for capturing.
+pub type CaptureTimer = crate::pac::TC1;
+
+/// The number of captures that can be queued before new ones are dropped.
+pub const QUEUE_LEN: usize = 8;
+
+// TCCR1B bits.
+const ICNC1: u8 = 1 << 7;
+const ICES1: u8 = 1 << 6;
+// TIMSK1 and TIFR1 bits.
+const ICIE1: u8 = 1 << 5;
+const TOIE1: u8 = 1 << 0;
+const ICF1: u8 = 1 << 5;
+const TOV1: u8 = 1 << 0;
+
+/// The edges to capture.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Edges {
+    Rising,
+    Falling,
+    Both,
+}
+
+/// The timer clock prescaler, trading resolution for the time covered by
+/// the 32-bit timestamps.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Prescaler {
+    /// One count per CPU cycle.
+    Direct,
+    Div8,
+    Div64,
+}
+
+impl Prescaler {
+    fn divisor(self) -> u32 {
+        match self {
+            Prescaler::Direct => 1,
+            Prescaler::Div8 => 8,
+            Prescaler::Div64 => 64,
+        }
+    }
+
+    fn clock_select(self) -> u8 {
+        match self {
+            Prescaler::Direct => 0b001,
+            Prescaler::Div8 => 0b010,
+            Prescaler::Div64 => 0b011,
+        }
+    }
+}
+
+/// A captured edge.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Capture {
+    /// The extended timer count at the edge.  It wraps around after 2^32
+    /// counts.
+    pub timestamp: u32,
+    /// `true` for a rising edge.
+    pub rising: bool,
+}
+
+#[derive(Clone, Copy)]
+struct State {
+    queue: [Capture; QUEUE_LEN],
+    head: u8,
+    len: u8,
+    dropped: u16,
+    overflows: u16,
+    both: bool,
+}
+
+static STATE: Mutex<cell::Cell<State>> = Mutex::new(cell::Cell::new(State {
+    queue: [Capture {
+        timestamp: 0,
+        rising: false,
+    }; QUEUE_LEN],
+    head: 0,
+    len: 0,
+    dropped: 0,
+    overflows: 0,
+    both: false,
+}));
+
+/// Timestamps edges on ICP1.
+pub struct InputCapture {
+    tc1: CaptureTimer,
+    prescaler: Prescaler,
+}
+
+impl InputCapture {
+    /// Starts capturing the given edges.
+    ///
+    /// The noise canceler delays each capture by four CPU cycles, whatever
+    /// the prescaler, but rejects glitches shorter than that; the delay is
+    /// the same for every edge, so intervals are unaffected.
+    pub fn new(
+        tc1: CaptureTimer,
+        edges: Edges,
+        prescaler: Prescaler,
+        noise_canceler: bool,
+    ) -> Self {
+        let mut tccr1b = prescaler.clock_select();
+        if noise_canceler {
+            tccr1b |= ICNC1;
+        }
+        if edges != Edges::Falling {
+            tccr1b |= ICES1;
+        }
+
+        avr_device::interrupt::free(|cs| {
+            let cell = STATE.borrow(cs);
+            let mut state = cell.get();
+            state.head = 0;
+            state.len = 0;
+            state.dropped = 0;
+            state.overflows = 0;
+            state.both = edges == Edges::Both;
+            cell.set(state);
+
+            // Normal mode, counting freely from 0 to 0xffff.
+            tc1.tccr1a.write(|w| unsafe { w.bits(0) });
+            tc1.tcnt1.write(|w| unsafe { w.bits(0) });
+            tc1.tccr1b.write(|w| unsafe { w.bits(tccr1b) });
+            tc1.tifr1.write(|w| unsafe { w.bits(ICF1 | TOV1) });
+            tc1.timsk1.write(|w| unsafe { w.bits(ICIE1 | TOIE1) });
+        });
+
+        InputCapture { tc1, prescaler }
+    }
+
+    /// Takes the oldest queued capture.
+    pub fn read(&mut self) -> Option<Capture> {
+        avr_device::interrupt::free(|cs| {
+            let cell = STATE.borrow(cs);
+            let mut state = cell.get();
+            if state.len == 0 {
+                return None;
+            }
+            let capture = state.queue[state.head as usize];
+            state.head = (state.head + 1) % QUEUE_LEN as u8;
+            state.len -= 1;
+            cell.set(state);
+            Some(capture)
+        })
+    }
+
+    /// Returns the number of captures dropped because the queue was full.
+    pub fn dropped(&self) -> u16 {
+        avr_device::interrupt::free(|cs| STATE.borrow(cs).get().dropped)
+    }
+
+    /// Converts a number of timer counts, e.g. the difference between two
+    /// timestamps, into nanoseconds.
+    pub fn counts_to_nanos(&self, counts: u32) -> u64 {
+        counts as u64 * self.prescaler.divisor() as u64 * 1000 / CLOCK_MHZ as u64
+    }
+
+    /// Converts a number of timer counts into microseconds.
+    pub fn counts_to_micros(&self, counts: u32) -> u32 {
+        (counts as u64 * self.prescaler.divisor() as u64 / CLOCK_MHZ as u64) as u32
+    }
+
+    /// Stops capturing and releases the timer.
+    pub fn release(self) -> CaptureTimer {
+        self.tc1.timsk1.write(|w| unsafe { w.bits(0) });
+        self.tc1.tccr1b.write(|w| unsafe { w.bits(0) });
+        self.tc1
+    }
+}
+
+fn regs() -> &'static crate::pac::tc1::RegisterBlock {
+    unsafe { &*CaptureTimer::ptr() }
+}
+
+fn on_capture(cs: &CriticalSection) {
+    let tc1 = regs();
+    let counts = tc1.icr1.read().bits();
+    let tccr1b = tc1.tccr1b.read().bits();
+    let rising = tccr1b & ICES1 != 0;
+
+    let cell = STATE.borrow(cs);
+    let mut state = cell.get();
+    let mut overflows = state.overflows;
+    // An overflow that is still pending happened before the capture if the
+    // captured count is low.
+    if tc1.tifr1.read().bits() & TOV1 != 0 && counts < 0x8000 {
+        overflows = overflows.wrapping_add(1);
+    }
+
+    if state.both {
+        // Changing the edge can set the capture flag, so clear it after.
+        tc1.tccr1b.write(|w| unsafe { w.bits(tccr1b ^ ICES1) });
+        tc1.tifr1.write(|w| unsafe { w.bits(ICF1) });
+    }
+
+    if state.len as usize == QUEUE_LEN {
+        state.dropped = state.dropped.saturating_add(1);
+    } else {
+        let tail = (state.head as usize + state.len as usize) % QUEUE_LEN;
+        state.queue[tail] = Capture {
+            timestamp: (overflows as u32) << 16 | counts as u32,
+            rising,
+        };
+        state.len += 1;
+    }
+    cell.set(state);
+}
+
+isr! {
+    fn TIMER1_CAPT() {
+        avr_device::interrupt::free(on_capture)
+    }
+}
+
+isr! {
+    fn TIMER1_OVF() {
+        avr_device::interrupt::free(|cs| {
+            let cell = STATE.borrow(cs);
+            let mut state = cell.get();
+            state.overflows = state.overflows.wrapping_add(1);
+            cell.set(state);
+        })
+    }
+}
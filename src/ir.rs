@@ -0,0 +1,174 @@
+//! NEC infrared remote decoding.
+//!
+//! An IR receiver module such as a TSOP38238 demodulates the 38 kHz carrier
+//! and pulls its output low during each burst ("mark").  NEC frames start
+//! with a 9 ms mark and a 4.5 ms space, followed by 32 bits sent least
+//! significant first: a 562 us mark, then a 562 us space for a zero or a
+//! 1.69 ms space for a one.  Holding a button sends repeat codes: a 9 ms
+//! mark, a 2.25 ms space and a final mark.
+//!
+//! [`on_edge`] should be called on every edge of the receiver output, e.g.
+//! from a pin change interrupt.  It times the marks and spaces between
+//! edges with [`now`](crate::now) and queues the codes it decodes in an
+//! [`EventQueue`] for [`read`].
+
+use core::cell;
+
+use avr_device::interrupt::{CriticalSection, Mutex};
+
+use crate::event_queue::EventQueue;
+use crate::time::{Duration, Instant};
+
+/// The number of codes that can be queued before new ones are dropped.
+pub const QUEUE_LEN: usize = 8;
+
+// Repeats more than this after the previous code belong to no known frame.
+const REPEAT_WINDOW: Duration = Duration::from_millis(120);
+
+/// A decoded button press.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Code {
+    /// The device address.  Plain NEC sends an 8 bit address and its
+    /// inverse; extended NEC uses both bytes for a 16 bit address.
+    pub address: u16,
+    /// The button.
+    pub command: u8,
+    /// `true` for a repeat code sent while the button is held.
+    pub repeat: bool,
+}
+
+#[derive(Clone, Copy)]
+enum Phase {
+    Idle,
+    // The leader mark has been seen.
+    Leader,
+    Data { bits: u8, data: u32 },
+    // The space of a repeat code has been seen.
+    Repeat,
+}
+
+#[derive(Clone, Copy)]
+struct State {
+    phase: Phase,
+    last_edge: Instant,
+    last_code: Option<(Code, Instant)>,
+}
+
+static STATE: Mutex<cell::Cell<State>> = Mutex::new(cell::Cell::new(State {
+    phase: Phase::Idle,
+    last_edge: Instant::from_micros(0),
+    last_code: None,
+}));
+
+static CODES: EventQueue<Code, QUEUE_LEN> = EventQueue::new();
+
+/// Processes an edge of the receiver output, which is now at `level`.
+pub fn on_edge(level: bool) {
+    let now = crate::now();
+    avr_device::interrupt::free(|cs| {
+        let cell = STATE.borrow(cs);
+        let mut state = cell.get();
+        let length = (now - state.last_edge).as_micros();
+        state.last_edge = now;
+
+        if level {
+            end_of_mark(cs, &mut state, length, now);
+        } else {
+            end_of_space(&mut state, length);
+        }
+        cell.set(state);
+    })
+}
+
+/// Takes the oldest queued code, along with the time its last mark ended.
+pub fn read() -> Option<(Instant, Code)> {
+    CODES.pop()
+}
+
+/// Returns the number of codes dropped because the queue was full.
+pub fn dropped() -> u16 {
+    CODES.dropped()
+}
+
+fn end_of_mark(cs: &CriticalSection, state: &mut State, length: u32, now: Instant) {
+    if matches(length, 9000) {
+        state.phase = Phase::Leader;
+        return;
+    }
+    if !matches(length, 562) {
+        state.phase = Phase::Idle;
+        return;
+    }
+
+    match state.phase {
+        Phase::Data { bits: 32, data } => {
+            state.phase = Phase::Idle;
+            let [address_low, address_high, command, inverse] = data.to_le_bytes();
+            if command != !inverse {
+                return;
+            }
+            let address = if address_high == !address_low {
+                address_low as u16
+            } else {
+                u16::from_le_bytes([address_low, address_high])
+            };
+            push(
+                cs,
+                state,
+                Code {
+                    address,
+                    command,
+                    repeat: false,
+                },
+                now,
+            );
+        }
+        Phase::Data { .. } => {}
+        Phase::Repeat => {
+            state.phase = Phase::Idle;
+            if let Some((code, at)) = state.last_code {
+                if now - at <= REPEAT_WINDOW {
+                    push(
+                        cs,
+                        state,
+                        Code {
+                            repeat: true,
+                            ..code
+                        },
+                        now,
+                    );
+                }
+            }
+        }
+        _ => state.phase = Phase::Idle,
+    }
+}
+
+fn end_of_space(state: &mut State, length: u32) {
+    state.phase = match state.phase {
+        Phase::Leader if matches(length, 4500) => Phase::Data { bits: 0, data: 0 },
+        Phase::Leader if matches(length, 2250) => Phase::Repeat,
+        Phase::Data { bits, data } if bits < 32 && matches(length, 562) => Phase::Data {
+            bits: bits + 1,
+            data,
+        },
+        Phase::Data { bits, data } if bits < 32 && matches(length, 1687) => Phase::Data {
+            bits: bits + 1,
+            data: data | (1 << bits),
+        },
+        _ => Phase::Idle,
+    };
+}
+
+fn push(cs: &CriticalSection, state: &mut State, code: Code, now: Instant) {
+    // Repeats refer to the code pressed, and extend its window.
+    state.last_code = Some((code, now));
+    CODES.push_in(cs, now, code);
+}
+
+/// Returns whether `length` is within 25% of `nominal`, which allows for
+/// the receiver stretching or shortening marks.
+fn matches(length: u32, nominal: u32) -> bool {
+    let tolerance = nominal / 4;
+    (nominal - tolerance..=nominal + tolerance).contains(&length)
+}
@@ -0,0 +1,91 @@
+//! Timing statistics of the timer ISR.
+//!
+//! With the `isr-metrics` feature the timer ISR reads the timer as it
+//! starts.  The timer restarts from zero at the compare match, so its count
+//! is how long the interrupt took to be serviced, delayed by other ISRs and
+//! critical sections.  The ISR records the number of runs, the worst
+//! latency and a histogram of latencies, which [`snapshot`] returns.
+
+use core::cell;
+
+use avr_device::interrupt::{CriticalSection, Mutex};
+
+use crate::CLOCK_MHZ;
+
+/// The number of histogram buckets.
+///
+/// Bucket 0 counts latencies of zero timer counts, and bucket `i` those of
+/// `2^(i - 1)` up to `2^i - 1` counts, with the last bucket taking all
+/// longer ones.
+pub const BUCKETS: usize = 8;
+
+static RUNS: Mutex<cell::Cell<u32>> = Mutex::new(cell::Cell::new(0));
+static WORST: Mutex<cell::Cell<u16>> = Mutex::new(cell::Cell::new(0));
+static HISTOGRAM: Mutex<cell::Cell<[u16; BUCKETS]>> = Mutex::new(cell::Cell::new([0; BUCKETS]));
+
+/// The statistics gathered since the time base started or [`reset`] was
+/// called.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Metrics {
+    /// The number of times the ISR ran.
+    pub runs: u32,
+    /// The longest latency seen, in timer counts.
+    pub worst_latency: u16,
+    /// The number of runs per latency bucket, see [`BUCKETS`].  Buckets
+    /// saturate rather than wrap.
+    pub histogram: [u16; BUCKETS],
+    /// The CPU cycles per timer count, i.e. the prescaler.
+    pub cycles_per_count: u32,
+}
+
+impl Metrics {
+    /// Returns the worst latency in microseconds, rounded down.
+    pub fn worst_latency_micros(&self) -> u32 {
+        self.worst_latency as u32 * self.cycles_per_count / CLOCK_MHZ
+    }
+
+    /// Returns the smallest latency in timer counts that falls into
+    /// `bucket`.
+    pub const fn bucket_start(bucket: usize) -> u16 {
+        if bucket == 0 {
+            0
+        } else {
+            1 << (bucket - 1)
+        }
+    }
+}
+
+/// Returns the statistics so far.
+pub fn snapshot() -> Metrics {
+    avr_device::interrupt::free(|cs| Metrics {
+        runs: RUNS.borrow(cs).get(),
+        worst_latency: WORST.borrow(cs).get(),
+        histogram: HISTOGRAM.borrow(cs).get(),
+        cycles_per_count: crate::SETTINGS.borrow(cs).get().prescaler,
+    })
+}
+
+/// Clears the statistics.
+pub fn reset() {
+    avr_device::interrupt::free(|cs| {
+        RUNS.borrow(cs).set(0);
+        WORST.borrow(cs).set(0);
+        HISTOGRAM.borrow(cs).set([0; BUCKETS]);
+    })
+}
+
+/// Records a run of the ISR that started `counts` timer counts after the
+/// compare match.
+#[inline(always)]
+pub(crate) fn record(cs: &CriticalSection, counts: u16) {
+    let runs = RUNS.borrow(cs);
+    runs.set(runs.get().wrapping_add(1));
+    let worst = WORST.borrow(cs);
+    if counts > worst.get() {
+        worst.set(counts);
+    }
+    let bucket = ((16 - counts.leading_zeros()) as usize).min(BUCKETS - 1);
+    let histogram: &cell::Cell<[u16]> = HISTOGRAM.borrow(cs);
+    let slot = &histogram.as_slice_of_cells()[bucket];
+    slot.set(slot.get().saturating_add(1));
+}
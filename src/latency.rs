@@ -0,0 +1,104 @@
+//! Measuring the dispatch latency of the timer interrupt.
+//!
+//! [`start`] makes Timer0 toggle its OC0A pin (D6 on the Uno) in hardware at
+//! every compare match, so the true match times can be watched on a scope
+//! or logic analyzer.  The ISR meanwhile reads the timer at entry, which has
+//! restarted from zero at the match, and counts the latencies in a
+//! histogram of one bin per timer count.  Comparing the histogram with and
+//! without another library's interrupts enabled shows how much they delay
+//! the time base.
+//!
+//! The latency includes the ISR's prologue, which is constant.  The
+//! resolution is one timer count, i.e. the prescaler in CPU cycles, so a
+//! configuration with a small prescaler shows the most detail.
+
+#[cfg(not(all(
+    feature = "atmega328p",
+    not(any(feature = "timer1", feature = "timer2", feature = "arduino-core"))
+)))]
+compile_error!("the `latency-probe` feature requires the ATmega328P with the time base on TC0");
+
+use core::cell;
+
+use avr_device::interrupt::{CriticalSection, Mutex};
+use embedded_hal::serial::Write;
+
+use crate::telemetry::write_decimal;
+use crate::timer::Timer;
+
+/// The number of histogram bins.  The last one also counts all longer
+/// latencies.
+pub const BINS: usize = 32;
+
+static ENABLED: Mutex<cell::Cell<bool>> = Mutex::new(cell::Cell::new(false));
+static HISTOGRAM: Mutex<cell::Cell<[u16; BINS]>> = Mutex::new(cell::Cell::new([0; BINS]));
+
+// TCCR0A: toggle OC0A on compare match.
+const COM0A0: u8 = 1 << 6;
+// OC0A is PD6.
+const OC0A: u8 = 1 << 6;
+
+/// Starts toggling OC0A at each compare match and recording latencies,
+/// clearing the histogram.  The time base must already be running.
+pub fn start(timer: &Timer) {
+    let portd = unsafe { &*crate::pac::PORTD::ptr() };
+    avr_device::interrupt::free(|cs| {
+        HISTOGRAM.borrow(cs).set([0; BINS]);
+        ENABLED.borrow(cs).set(true);
+        portd.ddrd.modify(|r, w| unsafe { w.bits(r.bits() | OC0A) });
+        timer
+            .tccr0a
+            .modify(|r, w| unsafe { w.bits(r.bits() | COM0A0) });
+    })
+}
+
+/// Stops toggling OC0A and recording latencies.  The histogram is kept.
+pub fn stop(timer: &Timer) {
+    avr_device::interrupt::free(|cs| {
+        ENABLED.borrow(cs).set(false);
+        timer
+            .tccr0a
+            .modify(|r, w| unsafe { w.bits(r.bits() & !COM0A0) });
+    })
+}
+
+/// Returns the number of interrupts serviced `i` timer counts after the
+/// compare match, for each bin `i`.  Bins saturate rather than wrap.
+pub fn histogram() -> [u16; BINS] {
+    avr_device::interrupt::free(|cs| HISTOGRAM.borrow(cs).get())
+}
+
+/// Writes the non-empty bins of the histogram as lines of latency in CPU
+/// cycles and count, e.g. `16 cycles: 9987`.
+pub fn report<W: Write<u8>>(writer: &mut W) -> Result<(), W::Error> {
+    let cycles_per_count =
+        avr_device::interrupt::free(|cs| crate::SETTINGS.borrow(cs).get().prescaler);
+    for (bin, &count) in histogram().iter().enumerate() {
+        if count == 0 {
+            continue;
+        }
+        write_decimal(writer, (bin as u32 * cycles_per_count) as i64)?;
+        if bin == BINS - 1 {
+            nb::block!(writer.write(b'+'))?;
+        }
+        for &byte in b" cycles: " {
+            nb::block!(writer.write(byte))?;
+        }
+        write_decimal(writer, count as i64)?;
+        nb::block!(writer.write(b'\r'))?;
+        nb::block!(writer.write(b'\n'))?;
+    }
+    Ok(())
+}
+
+/// Records an interrupt serviced `counts` timer counts after the match.
+#[inline(always)]
+pub(crate) fn record(cs: &CriticalSection, counts: u16) {
+    if !ENABLED.borrow(cs).get() {
+        return;
+    }
+    let bin = (counts as usize).min(BINS - 1);
+    let histogram: &cell::Cell<[u16]> = HISTOGRAM.borrow(cs);
+    let slot = &histogram.as_slice_of_cells()[bin];
+    slot.set(slot.get().saturating_add(1));
+}
@@ -0,0 +1,957 @@
+//! A basic implementation of the `micros()` function from Arduino:
+//!
+//!     https://www.arduino.cc/reference/en/language/functions/time/micros/
+//!
+//! Call [`micros_init`] once with the timer peripheral, enable interrupts
+//! globally and [`now`] will then return the current [`Instant`], from which
+//! [`Duration`]s can be measured.  [`micros`] returns the same value as a
+//! raw number of microseconds, like its Arduino namesake.  [`millis`] is
+//! maintained from the same interrupt for sketches that only need
+//! millisecond resolution, and [`micros64`] and [`millis64`] extend both
+//! counters to 64 bits for programs that run long enough to see the 32-bit
+//! values wrap.
+//!
+//! The crate targets the ATmega328P of the Arduino Uno by default.  Disable
+//! the default features and enable `atmega2560` to build for the Arduino
+//! Mega 2560, `atmega32u4` for the Arduino Leonardo and Micro, `attiny85` for
+//! the ATtiny85 running from its internal 8 MHz oscillator, or `atmega4809`
+//! for the Arduino Nano Every, instead.
+//!
+//! The time base runs on `TC0` by default.  Enable the `timer1` or `timer2`
+//! feature to use one of the other timers instead, e.g. to keep PWM on pins
+//! 5 and 6 available.  With `timer1-overflow`, Timer1 instead runs freely
+//! at 0.5 us per count and interrupts only every 32.768 ms, and [`micros`]
+//! adds the count to the overflows directly.  Alternatively, the
+//! `arduino-core` feature keeps Timer0 in Fast PWM mode and counts its
+//! overflows like the official Arduino core does, so time keeping and PWM
+//! on OC0A/OC0B can coexist.
+//!
+//! [`delay::MicrosDelay`] implements the `embedded-hal` delay traits on top
+//! of the counter for drivers that need a `DelayUs` or `DelayMs`, and
+//! [`timeout::Timeout`] provides a software `CountDown` timer.  With the
+//! `rtic` feature, `monotonic::MicrosMonotonic` implements RTIC's
+//! `Monotonic` trait on the time base and its second compare unit, and the
+//! `embassy` feature registers the time base as the `embassy-time` driver.
+//! The `embedded-time` feature exposes the counter as an
+//! `embedded_time::Clock` through `clock::MicrosClock`.
+//!
+//! Code that has to mask interrupts for longer than a timer period can do
+//! so in a [`masked::Masked`] section, which adds back the periods the
+//! hardware couldn't latch, and [`suspend::suspend`] stops the timer
+//! altogether until the time spent is handed back.  With the `deep-sleep`
+//! feature, `deep_sleep` builds on it to sleep in power-down for seconds,
+//! keeping time on the watchdog or the Timer2 watch crystal meanwhile.
+#![cfg_attr(not(test), no_std)]
+#![feature(abi_avr_interrupt)]
+#![feature(asm_experimental_arch)]
+#![cfg_attr(feature = "panic-serial", feature(panic_info_message))]
+
+use core::cell;
+use core::ptr;
+use core::sync::atomic::{compiler_fence, Ordering};
+
+#[cfg(any(
+    all(feature = "atmega328p", feature = "atmega2560"),
+    all(feature = "atmega328p", feature = "atmega32u4"),
+    all(feature = "atmega328p", feature = "attiny85"),
+    all(feature = "atmega328p", feature = "atmega4809"),
+    all(feature = "atmega2560", feature = "atmega32u4"),
+    all(feature = "atmega2560", feature = "attiny85"),
+    all(feature = "atmega2560", feature = "atmega4809"),
+    all(feature = "atmega32u4", feature = "attiny85"),
+    all(feature = "atmega32u4", feature = "atmega4809"),
+    all(feature = "attiny85", feature = "atmega4809"),
+))]
+compile_error!("only one device feature may be enabled");
+
+#[cfg(not(any(
+    feature = "atmega328p",
+    feature = "atmega2560",
+    feature = "atmega32u4",
+    feature = "attiny85",
+    feature = "atmega4809",
+)))]
+compile_error!("a device feature such as `atmega328p` must be enabled");
+
+#[cfg(feature = "atmega328p")]
+pub(crate) use avr_device::atmega328p as pac;
+
+#[cfg(feature = "atmega2560")]
+pub(crate) use avr_device::atmega2560 as pac;
+
+#[cfg(feature = "atmega32u4")]
+pub(crate) use avr_device::atmega32u4 as pac;
+
+#[cfg(feature = "attiny85")]
+pub(crate) use avr_device::attiny85 as pac;
+
+#[cfg(feature = "atmega4809")]
+pub(crate) use avr_device::atmega4809 as pac;
+
+#[cfg(any(
+    all(feature = "clock-8mhz", feature = "clock-16mhz"),
+    all(feature = "clock-8mhz", feature = "clock-20mhz"),
+    all(feature = "clock-16mhz", feature = "clock-20mhz"),
+))]
+compile_error!("only one `clock-*` feature may be enabled");
+
+// The CPU clock frequency, selected by the `clock-*` features.  Without one,
+// the ATtiny85 is assumed to run from its internal 8 MHz oscillator and the
+// other devices at 16 MHz.  On the ATmega4809 this requires the main clock
+// prescaler to be disabled, as the Arduino core does.
+#[cfg(feature = "clock-8mhz")]
+pub(crate) const CLOCK_HZ: u32 = 8_000_000;
+#[cfg(feature = "clock-16mhz")]
+pub(crate) const CLOCK_HZ: u32 = 16_000_000;
+#[cfg(feature = "clock-20mhz")]
+pub(crate) const CLOCK_HZ: u32 = 20_000_000;
+#[cfg(not(any(
+    feature = "clock-8mhz",
+    feature = "clock-16mhz",
+    feature = "clock-20mhz",
+    feature = "attiny85"
+)))]
+pub(crate) const CLOCK_HZ: u32 = 16_000_000;
+#[cfg(all(
+    feature = "attiny85",
+    not(any(
+        feature = "clock-8mhz",
+        feature = "clock-16mhz",
+        feature = "clock-20mhz"
+    ))
+))]
+pub(crate) const CLOCK_HZ: u32 = 8_000_000;
+
+const CLOCK_MHZ: u32 = CLOCK_HZ / 1_000_000;
+
+// Defines an interrupt handler for the selected device.
+macro_rules! isr {
+    (fn $name:ident() $body:block) => {
+        #[cfg_attr(feature = "atmega328p", avr_device::interrupt(atmega328p))]
+        #[cfg_attr(feature = "atmega2560", avr_device::interrupt(atmega2560))]
+        #[cfg_attr(feature = "atmega32u4", avr_device::interrupt(atmega32u4))]
+        #[cfg_attr(feature = "attiny85", avr_device::interrupt(attiny85))]
+        #[cfg_attr(feature = "atmega4809", avr_device::interrupt(atmega4809))]
+        #[cfg(target_arch = "avr")]
+        fn $name() $body
+    };
+}
+
+pub mod alarm;
+pub mod cli;
+#[cfg(feature = "embedded-time")]
+pub mod clock;
+pub mod compat;
+pub mod config;
+pub mod debounce;
+#[cfg(feature = "deep-sleep")]
+pub mod deep_sleep;
+#[cfg(feature = "defmt-serial")]
+pub mod defmt_serial;
+pub mod delay;
+pub mod dht;
+pub mod drift;
+pub mod ds_rtc;
+#[cfg(not(feature = "atmega4809"))]
+mod eeprom;
+#[cfg(feature = "embassy")]
+mod embassy_driver;
+pub mod encoder;
+#[cfg(feature = "entropy")]
+pub mod entropy;
+pub mod event_queue;
+#[cfg(feature = "executor")]
+pub mod executor;
+#[cfg(feature = "ext-int")]
+pub mod ext_int;
+#[cfg(feature = "freq-counter")]
+pub mod freq_counter;
+pub mod hc_sr04;
+pub mod heartbeat;
+#[cfg(feature = "input-capture")]
+pub mod input_capture;
+pub mod ir;
+#[cfg(feature = "isr-metrics")]
+pub mod isr_metrics;
+#[cfg(feature = "latency-probe")]
+pub mod latency;
+#[cfg(feature = "log")]
+pub mod log;
+#[cfg(not(feature = "atmega4809"))]
+pub mod marker;
+pub mod masked;
+#[cfg(feature = "rtic")]
+pub mod monotonic;
+pub mod nmea;
+pub mod one_wire;
+#[cfg(not(feature = "atmega4809"))]
+pub mod osccal;
+#[cfg(feature = "panic-serial")]
+mod panic_serial;
+#[cfg(feature = "pcint-log")]
+pub mod pcint_log;
+pub mod periodic;
+pub mod power;
+#[cfg(feature = "pps")]
+pub mod pps;
+pub mod profile;
+pub mod pulse;
+pub mod raw;
+pub mod reset;
+pub mod rng;
+pub mod rollover;
+#[cfg(feature = "rtc")]
+pub mod rtc;
+pub mod scheduler;
+pub mod selftest;
+#[cfg(feature = "serial-rx")]
+pub mod serial_rx;
+#[cfg(feature = "serial-tx")]
+pub mod serial_tx;
+#[cfg(feature = "servo")]
+pub mod servo;
+pub mod session;
+#[cfg(all(
+    feature = "atmega328p",
+    not(any(feature = "arduino-core", feature = "timer1-overflow"))
+))]
+pub mod square_wave;
+pub mod stopwatch;
+pub mod suspend;
+pub mod tachometer;
+pub mod telemetry;
+pub mod throttle;
+pub mod time;
+pub mod time_sync;
+pub mod timeout;
+mod timer;
+#[cfg(feature = "tone")]
+pub mod tone;
+pub mod trace;
+#[cfg(not(feature = "atmega4809"))]
+pub mod uptime;
+#[cfg(any(feature = "panic-serial", feature = "serial-rx", feature = "serial-tx"))]
+mod usart;
+pub mod wall_clock;
+#[cfg(not(feature = "atmega4809"))]
+pub mod watchdog;
+#[cfg(feature = "ws2812")]
+pub mod ws2812;
+
+pub use config::{DefaultConfig, TimerConfig};
+pub use time::{Duration, Instant};
+pub use timer::Timer;
+
+// The active timer settings, written by `micros_init_with` and read by the
+// ISR.
+static SETTINGS: avr_device::interrupt::Mutex<cell::Cell<config::Settings>> =
+    avr_device::interrupt::Mutex::new(cell::Cell::new(config::DefaultConfig::settings()));
+
+static MICROS_COUNTER: avr_device::interrupt::Mutex<cell::Cell<u32>> =
+    avr_device::interrupt::Mutex::new(cell::Cell::new(0));
+
+static MILLIS_COUNTER: avr_device::interrupt::Mutex<cell::Cell<u32>> =
+    avr_device::interrupt::Mutex::new(cell::Cell::new(0));
+
+// Number of times each 32-bit counter has wrapped, used as the upper half of
+// the 64-bit values.
+static MICROS_OVERFLOWS: avr_device::interrupt::Mutex<cell::Cell<u32>> =
+    avr_device::interrupt::Mutex::new(cell::Cell::new(0));
+
+static MILLIS_OVERFLOWS: avr_device::interrupt::Mutex<cell::Cell<u32>> =
+    avr_device::interrupt::Mutex::new(cell::Cell::new(0));
+
+static MILLIS_FRACT: avr_device::interrupt::Mutex<cell::Cell<u16>> =
+    avr_device::interrupt::Mutex::new(cell::Cell::new(0));
+
+// Millionths of a CPU cycle not yet making up a whole microsecond, for
+// periods that aren't a multiple of a microsecond.
+static MICROS_FRACT: avr_device::interrupt::Mutex<cell::Cell<u32>> =
+    avr_device::interrupt::Mutex::new(cell::Cell::new(0));
+
+// Timer periods found to have elapsed before the ISR for the previous one
+// ran, see `missed_ticks`.
+static MISSED_TICKS: avr_device::interrupt::Mutex<cell::Cell<u32>> =
+    avr_device::interrupt::Mutex::new(cell::Cell::new(0));
+
+// Timer periods counted since the counters were reset, see `raw::ticks`.
+static TICKS: avr_device::interrupt::Mutex<cell::Cell<u32>> =
+    avr_device::interrupt::Mutex::new(cell::Cell::new(0));
+
+// Incremented by the ISR whenever it advances the counters, so that a
+// reader running with interrupts enabled can tell whether it was
+// interrupted.
+static TICK_SEQUENCE: avr_device::interrupt::Mutex<cell::Cell<u8>> =
+    avr_device::interrupt::Mutex::new(cell::Cell::new(0));
+
+static PPM_CORRECTION: avr_device::interrupt::Mutex<cell::Cell<i16>> =
+    avr_device::interrupt::Mutex::new(cell::Cell::new(0));
+
+/// Configures the timer as the time base using [`DefaultConfig`] and resets
+/// the counters to zero.
+///
+/// Interrupts must be enabled globally afterwards for the counter to advance.
+/// The timer is only borrowed so it can still be handed to a PWM driver in
+/// `arduino-core` mode, but it must not be reconfigured otherwise.
+pub fn micros_init(timer: &Timer) {
+    micros_init_with(timer, DefaultConfig::new());
+}
+
+/// Configures the timer as the time base using the given configuration and
+/// resets the counters to zero.
+///
+/// For example, `TimerConfig::<8, 250>::new()` interrupts every 125 us
+/// instead of every 1 ms at 16 MHz, for a finer [`millis`] and resolution of
+/// [`micros`].  See [`config`] for other values.
+pub fn micros_init_with<const PRESCALER: u32, const COUNTS: u32>(
+    timer: &Timer,
+    _config: TimerConfig<PRESCALER, COUNTS>,
+) {
+    let settings = avr_device::interrupt::free(|cs| {
+        let ppm = PPM_CORRECTION.borrow(cs).get();
+        let settings = TimerConfig::<PRESCALER, COUNTS>::settings().corrected(ppm);
+        SETTINGS.borrow(cs).set(settings);
+        settings
+    });
+    timer::configure(timer, &settings);
+    reset_counters();
+}
+
+/// Corrects the time base for a crystal running `ppm` parts per million
+/// fast, or slow if negative.
+///
+/// The correction is applied by the ISR as the counters advance, so reading
+/// them costs nothing extra.  It takes effect from the next timer period and
+/// doesn't alter time that has already been counted.  Between interrupts
+/// [`micros`] is still interpolated at the nominal rate, so with long timer
+/// periods a positive correction can make it step back slightly at each
+/// interrupt.
+pub fn set_ppm_correction(ppm: i16) {
+    avr_device::interrupt::free(|cs| {
+        // Periods already counted run at the old rate.
+        #[cfg(feature = "fast-isr")]
+        fold_fast_ticks(cs);
+        let settings = SETTINGS.borrow(cs).get().corrected(ppm);
+        SETTINGS.borrow(cs).set(settings);
+        PPM_CORRECTION.borrow(cs).set(ppm);
+    })
+}
+
+/// Returns the correction set by [`set_ppm_correction`].
+pub fn get_ppm_correction() -> i16 {
+    avr_device::interrupt::free(|cs| PPM_CORRECTION.borrow(cs).get())
+}
+
+/// Returns the number of timer periods that ended while the interrupt for
+/// the previous one was still held off, e.g. by a long critical section.
+///
+/// The ISR accounts for one such period, so the counters stay correct, but
+/// a nonzero count means interrupts were masked for more than a period.
+/// Only one pending interrupt is latched by the hardware, so two or more
+/// periods masked beyond that are lost without a trace, and the count is a
+/// lower bound.  [`micros`] already accounts for the pending interrupt while
+/// it reads the timer, which isn't counted.
+pub fn missed_ticks() -> u32 {
+    avr_device::interrupt::free(|cs| MISSED_TICKS.borrow(cs).get())
+}
+
+/// Resets the global counters to zero.
+pub(crate) fn reset_counters() {
+    avr_device::interrupt::free(|cs| {
+        MICROS_COUNTER.borrow(cs).set(0);
+        MILLIS_COUNTER.borrow(cs).set(0);
+        MILLIS_FRACT.borrow(cs).set(0);
+        MICROS_FRACT.borrow(cs).set(0);
+        MICROS_OVERFLOWS.borrow(cs).set(0);
+        MILLIS_OVERFLOWS.borrow(cs).set(0);
+        MISSED_TICKS.borrow(cs).set(0);
+        TICKS.borrow(cs).set(0);
+        rollover::clear(cs);
+        #[cfg(feature = "fast-isr")]
+        timer::take_fast_ticks(cs);
+    });
+
+    #[cfg(feature = "isr-metrics")]
+    isr_metrics::reset();
+}
+
+/// Advances the counters by one timer period, or two if the next one has
+/// ended as well, and runs the hooks.  Called from the timer ISR.
+///
+/// ISRs run with interrupts disabled, so rather than `interrupt::free`,
+/// which would save SREG, disable interrupts and restore SREG again, the
+/// critical section token is created directly and passed to the hooks.
+/// That saves an `in`, a `cli` and an `out`, 3 cycles and a register, per
+/// tick for the counters and again for each hook.
+#[inline(always)]
+pub(crate) fn tick() {
+    // Safety: this only runs in the timer ISR, which AVR enters with
+    // interrupts disabled, and which doesn't enable them.
+    let cs = &unsafe { avr_device::interrupt::CriticalSection::new() };
+
+    #[cfg(feature = "isr-metrics")]
+    isr_metrics::record(cs, timer::counts());
+    #[cfg(feature = "latency-probe")]
+    latency::record(cs, timer::counts());
+
+    let settings = SETTINGS.borrow(cs).get();
+    // The flag was cleared on entry, so if it is set again another
+    // period ended while this interrupt was held off.  Account for it
+    // now rather than in a second interrupt, and count it as a sign that
+    // interrupts were masked for too long.
+    if timer::compare_pending() {
+        timer::clear_compare();
+        advance(cs, &settings);
+        let missed = MISSED_TICKS.borrow(cs);
+        missed.set(missed.get().wrapping_add(1));
+    }
+    advance(cs, &settings);
+
+    let sequence = TICK_SEQUENCE.borrow(cs);
+    sequence.set(sequence.get().wrapping_add(1));
+
+    #[cfg(feature = "embassy")]
+    embassy_driver::on_tick(cs);
+
+    #[cfg(feature = "rtic")]
+    monotonic::on_tick(cs);
+
+    #[cfg(feature = "isr-alarms")]
+    alarm::poll();
+
+    #[cfg(feature = "executor")]
+    executor::on_tick(cs);
+
+    #[cfg(feature = "tone")]
+    tone::on_tick(cs);
+}
+
+/// Runs the hooks of the time base timer's second compare unit.  Called
+/// from its ISR.
+///
+/// The unit serves one feature at a time, the embassy driver if it is
+/// enabled and the tone generator otherwise.  With the `rtic` feature the
+/// application owns the ISR and calls into the monotonic.
+#[cfg(all(
+    any(feature = "embassy", feature = "tone"),
+    not(any(
+        feature = "attiny85",
+        feature = "atmega4809",
+        feature = "arduino-core",
+        feature = "rtic"
+    ))
+))]
+#[inline(always)]
+pub(crate) fn on_compare_b() {
+    // Safety: as in `tick`, this only runs in an ISR.
+    let cs = &unsafe { avr_device::interrupt::CriticalSection::new() };
+
+    #[cfg(feature = "embassy")]
+    embassy_driver::on_compare(cs);
+
+    #[cfg(all(feature = "tone", not(feature = "embassy")))]
+    tone::on_compare(cs);
+}
+
+/// Returns the fewest counts a compare unit B match can be scheduled ahead,
+/// so that the timer hasn't passed it by the time an ISR has armed it.  256
+/// cycles cover the interrupt latency and the ISR up to that point.
+#[cfg(all(
+    any(feature = "embassy", feature = "rtic", feature = "tone"),
+    not(any(feature = "attiny85", feature = "atmega4809", feature = "arduino-core"))
+))]
+pub(crate) fn min_compare_step(settings: &config::Settings) -> u32 {
+    256 / settings.prescaler + 2
+}
+
+/// Arms compare unit B for `timestamp` if it comes before the end of the
+/// next period, and leaves it to the next tick otherwise.  The match is
+/// rounded up to a whole count, and to the fewest counts that can be
+/// scheduled, so it can only come early through the ppm correction, in
+/// which case the caller schedules another.  A `timestamp` that has
+/// already passed is matched as soon as possible.
+#[cfg(all(
+    any(feature = "embassy", feature = "rtic"),
+    not(any(feature = "attiny85", feature = "atmega4809", feature = "arduino-core"))
+))]
+pub(crate) fn schedule_compare_b(
+    cs: &avr_device::interrupt::CriticalSection,
+    timestamp: u64,
+    now: u64,
+) {
+    let settings = SETTINGS.borrow(cs).get();
+    let delta = timestamp.saturating_sub(now);
+    if delta >= settings.micros_increment as u64 {
+        return;
+    }
+    let cycles = delta as u32 * CLOCK_MHZ;
+    let ahead =
+        ((cycles + settings.prescaler - 1) / settings.prescaler).max(min_compare_step(&settings));
+    if ahead >= settings.counts {
+        return;
+    }
+    let at = (timer::counts() as u32 + ahead) % settings.counts;
+    timer::arm_compare_b(at as u16);
+}
+
+/// Advances the counters by one timer period, without running the hooks.
+#[inline(always)]
+fn advance(cs: &avr_device::interrupt::CriticalSection, settings: &config::Settings) {
+    let ticks = TICKS.borrow(cs);
+    ticks.set(ticks.get().wrapping_add(1));
+
+    // Carry the leftover fraction into a whole microsecond once it adds
+    // up to one.
+    let micros_fract_cell = MICROS_FRACT.borrow(cs);
+    let mut micros_fract = micros_fract_cell.get() + settings.micros_fract_increment;
+    let mut carry = 0;
+    if micros_fract >= CLOCK_HZ {
+        micros_fract -= CLOCK_HZ;
+        carry = 1;
+    }
+    micros_fract_cell.set(micros_fract);
+
+    let counter_cell = MICROS_COUNTER.borrow(cs);
+    let (counter, wrapped) = counter_cell
+        .get()
+        .overflowing_add(settings.micros_increment + carry);
+    counter_cell.set(counter);
+    if wrapped {
+        let overflows_cell = MICROS_OVERFLOWS.borrow(cs);
+        let overflows = overflows_cell.get().wrapping_add(1);
+        overflows_cell.set(overflows);
+        rollover::wrapped(cs, overflows);
+    }
+
+    let millis_cell = MILLIS_COUNTER.borrow(cs);
+    let fract_cell = MILLIS_FRACT.borrow(cs);
+    let millis = millis_cell.get();
+    let mut fract = fract_cell.get() + settings.millis_fract_increment + carry as u16;
+    let mut increment = settings.millis_increment;
+    if fract >= 1000 {
+        fract -= 1000;
+        increment += 1;
+    }
+    let (millis, wrapped) = millis.overflowing_add(increment);
+    millis_cell.set(millis);
+    fract_cell.set(fract);
+    if wrapped {
+        let overflows_cell = MILLIS_OVERFLOWS.borrow(cs);
+        overflows_cell.set(overflows_cell.get().wrapping_add(1));
+    }
+}
+
+/// Advances the counters by `periods` timer periods at once.
+fn advance_by(
+    cs: &avr_device::interrupt::CriticalSection,
+    settings: &config::Settings,
+    periods: u32,
+) {
+    let ticks = TICKS.borrow(cs);
+    ticks.set(ticks.get().wrapping_add(periods));
+
+    let periods = periods as u64;
+    let micros_fract_cell = MICROS_FRACT.borrow(cs);
+    let micros_fract =
+        micros_fract_cell.get() as u64 + periods * settings.micros_fract_increment as u64;
+    let carry = micros_fract / CLOCK_HZ as u64;
+    micros_fract_cell.set((micros_fract % CLOCK_HZ as u64) as u32);
+    let overflows = MICROS_OVERFLOWS.borrow(cs).get();
+    add_wide(
+        cs,
+        &MICROS_COUNTER,
+        &MICROS_OVERFLOWS,
+        periods * settings.micros_increment as u64 + carry,
+    );
+    if MICROS_OVERFLOWS.borrow(cs).get() != overflows {
+        rollover::wrapped(cs, MICROS_OVERFLOWS.borrow(cs).get());
+    }
+
+    let millis_fract_cell = MILLIS_FRACT.borrow(cs);
+    let millis_fract =
+        millis_fract_cell.get() as u64 + periods * settings.millis_fract_increment as u64 + carry;
+    millis_fract_cell.set((millis_fract % 1000) as u16);
+    add_wide(
+        cs,
+        &MILLIS_COUNTER,
+        &MILLIS_OVERFLOWS,
+        periods * settings.millis_increment as u64 + millis_fract / 1000,
+    );
+}
+
+/// Adds `increment` to the 64-bit value made of a counter and its overflows.
+fn add_wide(
+    cs: &avr_device::interrupt::CriticalSection,
+    low: &avr_device::interrupt::Mutex<cell::Cell<u32>>,
+    high: &avr_device::interrupt::Mutex<cell::Cell<u32>>,
+    increment: u64,
+) {
+    let (low, high) = (low.borrow(cs), high.borrow(cs));
+    let value = (((high.get() as u64) << 32) | low.get() as u64).wrapping_add(increment);
+    low.set(value as u32);
+    high.set((value >> 32) as u32);
+}
+
+/// Moves the periods counted by the minimal ISR of the `fast-isr` feature
+/// into the counters.  Called before every read.
+///
+/// This runs with interrupts disabled, so rather than multiplying and
+/// dividing in 64 bits like [`advance_by`], the periods are added by the
+/// bits of their number: the increments of one period are doubled for each
+/// bit and added for the bits that are set, with adds and compares only.
+/// That takes under a hundred cycles per bit of the number, so the 250
+/// periods of 4 us that pass in a millisecond without a read fold in under
+/// a thousand.
+#[cfg(feature = "fast-isr")]
+fn fold_fast_ticks(cs: &avr_device::interrupt::CriticalSection) {
+    let (mut periods, missed) = timer::take_fast_ticks(cs);
+    if missed != 0 {
+        let missed_cell = MISSED_TICKS.borrow(cs);
+        missed_cell.set(missed_cell.get().wrapping_add(missed as u32));
+    }
+    if periods == 0 {
+        return;
+    }
+    let ticks = TICKS.borrow(cs);
+    ticks.set(ticks.get().wrapping_add(periods));
+
+    let settings = SETTINGS.borrow(cs).get();
+    let mut step = Periods {
+        micros: settings.micros_increment as u64,
+        micros_fract: settings.micros_fract_increment,
+        millis: settings.millis_increment as u64,
+        millis_fract: settings.millis_fract_increment,
+    };
+    let overflows = MICROS_OVERFLOWS.borrow(cs).get();
+    let mut total = Periods {
+        micros: read_wide(cs, &MICROS_COUNTER, &MICROS_OVERFLOWS),
+        micros_fract: MICROS_FRACT.borrow(cs).get(),
+        millis: read_wide(cs, &MILLIS_COUNTER, &MILLIS_OVERFLOWS),
+        millis_fract: MILLIS_FRACT.borrow(cs).get(),
+    };
+    loop {
+        if periods & 1 != 0 {
+            total.add(&step);
+        }
+        periods >>= 1;
+        if periods == 0 {
+            break;
+        }
+        let once = step;
+        step.add(&once);
+    }
+
+    write_wide(cs, &MICROS_COUNTER, &MICROS_OVERFLOWS, total.micros);
+    MICROS_FRACT.borrow(cs).set(total.micros_fract);
+    write_wide(cs, &MILLIS_COUNTER, &MILLIS_OVERFLOWS, total.millis);
+    MILLIS_FRACT.borrow(cs).set(total.millis_fract);
+    if MICROS_OVERFLOWS.borrow(cs).get() != overflows {
+        rollover::wrapped(cs, MICROS_OVERFLOWS.borrow(cs).get());
+    }
+}
+
+/// The counters, or what a number of periods adds to them, in the form
+/// [`advance`] keeps them.
+#[cfg(feature = "fast-isr")]
+#[derive(Clone, Copy)]
+struct Periods {
+    micros: u64,
+    micros_fract: u32,
+    millis: u64,
+    millis_fract: u16,
+}
+
+#[cfg(feature = "fast-isr")]
+impl Periods {
+    /// Adds `other`, carrying the fractions the way [`advance`] does.
+    #[inline(always)]
+    fn add(&mut self, other: &Periods) {
+        self.micros_fract += other.micros_fract;
+        let mut carry = 0;
+        if self.micros_fract >= CLOCK_HZ {
+            self.micros_fract -= CLOCK_HZ;
+            carry = 1;
+        }
+        self.micros = self.micros.wrapping_add(other.micros + carry);
+
+        self.millis_fract += other.millis_fract + carry as u16;
+        let mut increment = other.millis;
+        if self.millis_fract >= 1000 {
+            self.millis_fract -= 1000;
+            increment += 1;
+        }
+        self.millis = self.millis.wrapping_add(increment);
+    }
+}
+
+/// Reads the 64-bit value made of a counter and its overflows.
+#[cfg(feature = "fast-isr")]
+fn read_wide(
+    cs: &avr_device::interrupt::CriticalSection,
+    low: &avr_device::interrupt::Mutex<cell::Cell<u32>>,
+    high: &avr_device::interrupt::Mutex<cell::Cell<u32>>,
+) -> u64 {
+    ((high.borrow(cs).get() as u64) << 32) | low.borrow(cs).get() as u64
+}
+
+/// Writes the 64-bit value made of a counter and its overflows.
+#[cfg(feature = "fast-isr")]
+fn write_wide(
+    cs: &avr_device::interrupt::CriticalSection,
+    low: &avr_device::interrupt::Mutex<cell::Cell<u32>>,
+    high: &avr_device::interrupt::Mutex<cell::Cell<u32>>,
+    value: u64,
+) {
+    low.borrow(cs).set(value as u32);
+    high.borrow(cs).set((value >> 32) as u32);
+}
+
+/// Reads the timer and whether a compare match is pending, consistently:
+/// if the flag is set, the count was read after the match.
+pub(crate) fn timer_snapshot() -> (u16, bool) {
+    if timer::compare_pending() {
+        return (timer::counts(), true);
+    }
+    let counts = timer::counts();
+    if timer::compare_pending() {
+        // The timer wrapped between the two reads.
+        (timer::counts(), true)
+    } else {
+        (counts, false)
+    }
+}
+
+/// Accounts for the timer periods that passed while interrupts were masked
+/// for about `cycles` CPU cycles, starting when [`timer_snapshot`] returned
+/// `start_counts` and `start_pending`.
+///
+/// The timer is read again to place the end within a period, so `cycles`
+/// only has to be right to within half a period.  Only one compare match
+/// can be latched, so if it was latched during the section it is left to
+/// the ISR and the other periods are added here.  Must be called before
+/// interrupts are enabled again.
+pub(crate) fn catch_up(
+    cs: &avr_device::interrupt::CriticalSection,
+    start_counts: u16,
+    start_pending: bool,
+    cycles: u32,
+) {
+    let (end_counts, end_pending) = timer_snapshot();
+    if !start_pending && !end_pending {
+        // The timer didn't reach the end of a period.
+        return;
+    }
+    let settings = SETTINGS.borrow(cs).get();
+    let period = settings.counts;
+    let observed = (end_counts as u32 + period - start_counts as u32) % period;
+    // The number of whole periods that brings the observed counts closest
+    // to the estimate.
+    let periods = (cycles / settings.prescaler + period / 2).saturating_sub(observed) / period;
+    let periods = if end_pending && !start_pending {
+        periods.max(1) - 1
+    } else {
+        periods
+    };
+    for _ in 0..periods {
+        advance(cs, &settings);
+    }
+}
+
+/// Advances the time base by `cycles` CPU cycles that passed while the
+/// timer was stopped, carrying the part of a period into the timer count.
+/// Cycles short of a whole timer count are dropped.  Must be called with
+/// the timer stopped.
+pub(crate) fn skip_cycles(cs: &avr_device::interrupt::CriticalSection, cycles: u64) {
+    let settings = SETTINGS.borrow(cs).get();
+    let counts = timer::counts() as u64 + cycles / settings.prescaler as u64;
+    timer::set_counts((counts % settings.counts as u64) as u16);
+    advance_by(cs, &settings, (counts / settings.counts as u64) as u32);
+}
+
+/// Returns the timer counts that have elapsed since the ISR last advanced
+/// the counter, based on the current value of the hardware timer.
+///
+/// Must be called with interrupts disabled, or be retried if the ISR ran
+/// meanwhile.
+fn pending_counts(settings: &config::Settings) -> u32 {
+    let counts = timer::counts();
+    if timer::compare_pending() {
+        // The compare match happened after interrupts were disabled, so the
+        // ISR has not accounted for it yet.  The timer is sampled again as
+        // the first read may have been taken just before it was cleared.
+        let counts = timer::counts();
+        settings.counts + counts as u32
+    } else {
+        counts as u32
+    }
+}
+
+/// Returns the microseconds that have elapsed since the ISR last advanced
+/// the counter.
+///
+/// The fraction of a microsecond carried by the ISR is left out to keep the
+/// read fast.
+fn pending_micros(cs: &avr_device::interrupt::CriticalSection) -> u32 {
+    let settings = SETTINGS.borrow(cs).get();
+    // With the prescaler fixed the conversion folds into a shift, e.g. half
+    // the counts at 16 MHz.
+    #[cfg(feature = "timer1-overflow")]
+    let prescaler = 8;
+    #[cfg(not(feature = "timer1-overflow"))]
+    let prescaler = settings.prescaler;
+    pending_counts(&settings) * prescaler / CLOCK_MHZ
+}
+
+// How often `micros` tries to read the counter between two interrupts.
+const LOCK_FREE_ATTEMPTS: u8 = 3;
+
+/// Returns the number of microseconds since [`micros_init`] was called.
+///
+/// The value is interpolated from the hardware timer, so its resolution is
+/// a single timer count regardless of the overflow interval.  It wraps
+/// around after roughly 71 minutes.
+///
+/// It doesn't disable interrupts, so it doesn't delay the timer ISR or
+/// any other.  Instead it reads the counter and the timer again if the ISR
+/// ran in between, and only falls back to a critical section if that keeps
+/// happening, as with very short timer periods.
+pub fn micros() -> u32 {
+    // Reading folds the periods counted by the minimal ISR into the
+    // counters, which must not be interrupted.
+    if cfg!(feature = "fast-isr") {
+        return avr_device::interrupt::free(micros_in);
+    }
+
+    // Only the timer ISR changes the counters while interrupts are enabled,
+    // and it can't be interrupted, so the counter and timer are consistent
+    // if the sequence number is the same before and after reading them.
+    // Torn reads are discarded, and code running with interrupts disabled
+    // never sees the sequence change.  The token doesn't mask interrupts; it
+    // only grants access to the counters, which the check makes safe.
+    let cs = unsafe { avr_device::interrupt::CriticalSection::new() };
+    let sequence = TICK_SEQUENCE.borrow(&cs);
+    for _ in 0..LOCK_FREE_ATTEMPTS {
+        let before = unsafe { ptr::read_volatile(sequence.as_ptr()) };
+        compiler_fence(Ordering::SeqCst);
+        let micros = micros_in(&cs);
+        compiler_fence(Ordering::SeqCst);
+        if unsafe { ptr::read_volatile(sequence.as_ptr()) } == before {
+            return micros;
+        }
+    }
+    avr_device::interrupt::free(micros_in)
+}
+
+/// Like [`micros`], but within a critical section the caller already holds,
+/// e.g. in an ISR, which saves entering another one.
+pub fn micros_in(cs: &avr_device::interrupt::CriticalSection) -> u32 {
+    #[cfg(feature = "fast-isr")]
+    fold_fast_ticks(cs);
+    MICROS_COUNTER
+        .borrow(cs)
+        .get()
+        .wrapping_add(pending_micros(cs))
+}
+
+/// Returns the current point in time.
+pub fn now() -> Instant {
+    Instant::from_micros(micros())
+}
+
+/// Like [`now`], but within a critical section the caller already holds.
+pub fn now_in(cs: &avr_device::interrupt::CriticalSection) -> Instant {
+    Instant::from_micros(micros_in(cs))
+}
+
+/// Returns the microsecond counter as last advanced by the ISR, without
+/// interpolating from the hardware timer, so it only moves while the ISR
+/// runs.
+pub(crate) fn ticked_micros() -> u32 {
+    avr_device::interrupt::free(|cs| {
+        #[cfg(feature = "fast-isr")]
+        fold_fast_ticks(cs);
+        MICROS_COUNTER.borrow(cs).get()
+    })
+}
+
+/// Returns the number of milliseconds since [`micros_init`] was called.
+///
+/// The value wraps around after roughly 49 days.
+pub fn millis() -> u32 {
+    avr_device::interrupt::free(|cs| {
+        #[cfg(feature = "fast-isr")]
+        fold_fast_ticks(cs);
+        MILLIS_COUNTER.borrow(cs).get()
+    })
+}
+
+/// Returns the number of microseconds since [`micros_init`] was called as a
+/// 64-bit value that will not wrap around in practice.
+pub fn micros64() -> u64 {
+    avr_device::interrupt::free(micros64_in)
+}
+
+/// Like [`micros64`], but within a critical section the caller already
+/// holds.
+pub fn micros64_in(cs: &avr_device::interrupt::CriticalSection) -> u64 {
+    #[cfg(feature = "fast-isr")]
+    fold_fast_ticks(cs);
+    let high = MICROS_OVERFLOWS.borrow(cs).get();
+    let low = MICROS_COUNTER.borrow(cs).get();
+    ((high as u64) << 32 | low as u64) + pending_micros(cs) as u64
+}
+
+/// Returns how often the microsecond counter has wrapped.
+pub(crate) fn micros_wraps(cs: &avr_device::interrupt::CriticalSection) -> u32 {
+    #[cfg(feature = "fast-isr")]
+    fold_fast_ticks(cs);
+    MICROS_OVERFLOWS.borrow(cs).get()
+}
+
+/// Returns the number of milliseconds since [`micros_init`] was called as a
+/// 64-bit value that will not wrap around in practice.
+pub fn millis64() -> u64 {
+    avr_device::interrupt::free(|cs| {
+        #[cfg(feature = "fast-isr")]
+        fold_fast_ticks(cs);
+        let high = MILLIS_OVERFLOWS.borrow(cs).get();
+        let low = MILLIS_COUNTER.borrow(cs).get();
+        (high as u64) << 32 | low as u64
+    })
+}
+
+/// Returns the length of a timer period in whole microseconds.
+pub(crate) fn period_micros() -> u32 {
+    avr_device::interrupt::free(|cs| SETTINGS.borrow(cs).get().micros_increment)
+}
+
+/// Returns the number of CPU cycles since [`micros_init`] was called, to the
+/// resolution of a single timer count.
+///
+/// A count lasts `PRESCALER` cycles: 4 us with the default Timer0
+/// configuration, 0.5 us on Timer1 and 62.5 ns with a prescaler of 1 at
+/// 16 MHz.  The phase of the prescaler itself can't be read, so that is the
+/// finest resolution the hardware offers.  For profiling tight code, pick a
+/// small prescaler with [`micros_init_with`].
+pub fn cycles64() -> u64 {
+    avr_device::interrupt::free(|cs| {
+        #[cfg(feature = "fast-isr")]
+        fold_fast_ticks(cs);
+        let high = MICROS_OVERFLOWS.borrow(cs).get();
+        let low = MICROS_COUNTER.borrow(cs).get();
+        let settings = SETTINGS.borrow(cs).get();
+        let base = (high as u64) << 32 | low as u64;
+        let fract = MICROS_FRACT.borrow(cs).get() / 1_000_000;
+        let pending = pending_counts(&settings) * settings.prescaler;
+        base * CLOCK_MHZ as u64 + (fract + pending) as u64
+    })
+}
+
+/// Returns the number of nanoseconds since [`micros_init`] was called,
+/// wrapping around after about 4.3 seconds.
+///
+/// It is derived from [`cycles64`] and has the same resolution, so it is
+/// meant for measuring short sections rather than keeping time.
+pub fn nanos() -> u32 {
+    (cycles64() * 1000 / CLOCK_MHZ as u64) as u32
+}
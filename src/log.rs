@@ -0,0 +1,151 @@
+//! Timestamped logging over `ufmt`.
+//!
+//! The [`info!`], [`warn!`] and [`error!`] macros take a `ufmt::uWrite`
+//! writer and a `uwrite!` format string, and print a line prefixed with the
+//! time since [`micros_init`](crate::micros_init) and the level:
+//!
+//! `[12.345678] WARN  queue full`
+//!
+//! They expand to `ufmt::uwrite!`, so the calling crate must depend on
+//! `ufmt`, and evaluate to the writer's `Result`.
+//!
+//! Records below the maximum level are removed at compile time, format
+//! strings included.  Every level is enabled by default; the
+//! `log-max-warn` and `log-max-error` features drop the less severe ones
+//! and `log-off` drops all of them.  If several are enabled, the strictest
+//! applies.
+
+use ufmt_write::uWrite;
+
+/// The severity of a record.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    /// Something failed.
+    Error,
+    /// Something unexpected that the program can recover from.
+    Warn,
+    /// Normal operation.
+    Info,
+}
+
+impl Level {
+    /// The name printed in the prefix, padded to the same width for every
+    /// level.
+    pub const fn name(self) -> &'static str {
+        match self {
+            Level::Error => "ERROR",
+            Level::Warn => "WARN ",
+            Level::Info => "INFO ",
+        }
+    }
+}
+
+/// The most verbose level compiled in, or `None` with `log-off`.
+#[cfg(feature = "log-off")]
+pub const MAX_LEVEL: Option<Level> = None;
+#[cfg(all(feature = "log-max-error", not(feature = "log-off")))]
+pub const MAX_LEVEL: Option<Level> = Some(Level::Error);
+#[cfg(all(
+    feature = "log-max-warn",
+    not(any(feature = "log-max-error", feature = "log-off"))
+))]
+pub const MAX_LEVEL: Option<Level> = Some(Level::Warn);
+#[cfg(not(any(
+    feature = "log-max-warn",
+    feature = "log-max-error",
+    feature = "log-off"
+)))]
+pub const MAX_LEVEL: Option<Level> = Some(Level::Info);
+
+/// Returns `true` if records at `level` are compiled in.
+pub const fn enabled(level: Level) -> bool {
+    match MAX_LEVEL {
+        Some(max) => level as u8 <= max as u8,
+        None => false,
+    }
+}
+
+/// Writes the `[seconds.micros] LEVEL ` prefix of a record.
+pub fn write_prefix<W: uWrite + ?Sized>(writer: &mut W, level: Level) -> Result<(), W::Error> {
+    let micros = crate::micros64();
+    // "[", up to 14 digits of seconds, ".", 6 digits and "] ".
+    let mut buffer = [0u8; 24];
+    let mut len = buffer.len() - 2;
+    buffer[len..].copy_from_slice(b"] ");
+    let mut fraction = (micros % 1_000_000) as u32;
+    for _ in 0..6 {
+        len -= 1;
+        buffer[len] = b'0' + (fraction % 10) as u8;
+        fraction /= 10;
+    }
+    len -= 1;
+    buffer[len] = b'.';
+    let mut seconds = micros / 1_000_000;
+    loop {
+        len -= 1;
+        buffer[len] = b'0' + (seconds % 10) as u8;
+        seconds /= 10;
+        if seconds == 0 {
+            break;
+        }
+    }
+    len -= 1;
+    buffer[len] = b'[';
+
+    // Only ASCII digits and punctuation were written.
+    writer.write_str(core::str::from_utf8(&buffer[len..]).unwrap_or(""))?;
+    writer.write_str(level.name())?;
+    writer.write_str(" ")
+}
+
+/// Ends a record.
+pub fn write_end<W: uWrite + ?Sized>(writer: &mut W) -> Result<(), W::Error> {
+    writer.write_str("\r\n")
+}
+
+/// Logs a record at the given [`Level`](crate::log::Level).
+///
+/// Prefer [`info!`], [`warn!`] and [`error!`].
+#[macro_export]
+macro_rules! log {
+    ($level:expr, $writer:expr, $($arg:tt)+) => {{
+        let level: $crate::log::Level = $level;
+        if $crate::log::enabled(level) {
+            let writer = $writer;
+            match $crate::log::write_prefix(writer, level) {
+                Ok(()) => match ufmt::uwrite!(writer, $($arg)+) {
+                    Ok(()) => $crate::log::write_end(writer),
+                    Err(e) => Err(e),
+                },
+                Err(e) => Err(e),
+            }
+        } else {
+            Ok(())
+        }
+    }};
+}
+
+/// Logs a record at [`Level::Info`](crate::log::Level::Info), e.g.
+/// `info!(&mut serial, "{} bytes", len)`.
+#[macro_export]
+macro_rules! info {
+    ($writer:expr, $($arg:tt)+) => {
+        $crate::log!($crate::log::Level::Info, $writer, $($arg)+)
+    };
+}
+
+/// Logs a record at [`Level::Warn`](crate::log::Level::Warn).
+#[macro_export]
+macro_rules! warn {
+    ($writer:expr, $($arg:tt)+) => {
+        $crate::log!($crate::log::Level::Warn, $writer, $($arg)+)
+    };
+}
+
+/// Logs a record at [`Level::Error`](crate::log::Level::Error).
+#[macro_export]
+macro_rules! error {
+    ($writer:expr, $($arg:tt)+) => {
+        $crate::log!($crate::log::Level::Error, $writer, $($arg)+)
+    };
+}
@@ -0,0 +1,104 @@
+//! Pulse trains on spare pins, for lining up firmware events with a logic
+//! analyzer capture.
+//!
+//! [`marker!`](crate::marker) emits a burst of `id` pulses on a
+//! [`Marker`] pin, so several code locations can share one analyzer
+//! channel and still be told apart.  Each pulse is exactly 5 cycles high
+//! and 5 low, 312.5 ns each at 16 MHz, with interrupts masked for the
+//! burst, so it is visible at 10 MS/s and its edges are placed to the
+//! cycle.  With a block, the pin is also held high while the block runs:
+//!
+//! ```ignore
+//! let marker = unsafe { Marker::new(0x23 as *mut u8, 0) }; // PINB0, D8
+//! marker!(marker, 2, {
+//!     read_sensor();
+//! });
+//! ```
+//!
+//! The pins are toggled by writing to their PINx register, which every
+//! classic AVR supports, so a marker pin must be low to start with for the
+//! pulses to read as high.
+
+#[cfg(target_arch = "avr")]
+use core::arch::asm;
+use core::ptr;
+
+/// A pin used for markers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Marker {
+    pin: *mut u8,
+    mask: u8,
+}
+
+impl Marker {
+    /// Makes bit `bit` of the port whose PINx register is at `pin` a low
+    /// output for markers, e.g. `0x23` and 0 for PB0.
+    ///
+    /// # Safety
+    ///
+    /// `pin` must be the data space address of a PINx register, with DDRx
+    /// and PORTx following it as on every classic AVR, and nothing else may
+    /// use the pin.
+    pub unsafe fn new(pin: *mut u8, bit: u8) -> Self {
+        let mask = 1 << bit;
+        let ddr = pin.add(1);
+        let port = pin.add(2);
+        ptr::write_volatile(port, ptr::read_volatile(port) & !mask);
+        ptr::write_volatile(ddr, ptr::read_volatile(ddr) | mask);
+        Marker { pin, mask }
+    }
+
+    /// Toggles the pin with a single store.
+    #[inline(always)]
+    pub fn toggle(&self) {
+        unsafe { ptr::write_volatile(self.pin, self.mask) }
+    }
+
+    /// Emits `count` pulses, 5 cycles high and 5 low each, with interrupts
+    /// masked.  A count of zero emits none.
+    #[inline(always)]
+    pub fn pulses(&self, count: u8) {
+        if count == 0 {
+            return;
+        }
+        #[cfg(target_arch = "avr")]
+        avr_device::interrupt::free(|_| unsafe {
+            // Each ST toggles the pin.  The high phase is three NOPs and the
+            // second ST, the low phase DEC, the taken BRNE and the first ST,
+            // both 5 cycles.
+            asm!(
+                "1:",
+                "st Z, {mask}",
+                "nop",
+                "nop",
+                "nop",
+                "st Z, {mask}",
+                "dec {count}",
+                "brne 1b",
+                mask = in(reg) self.mask,
+                count = inout(reg) count => _,
+                in("Z") self.pin,
+                options(nostack),
+            )
+        });
+    }
+}
+
+/// Emits `id` pulses on a [`Marker`](crate::marker::Marker), e.g.
+/// `marker!(marker, 3)`.  With a block, e.g. `marker!(marker, 3, { ... })`,
+/// the pin then stays high until the block finishes, and the macro
+/// evaluates to the block's value.
+#[macro_export]
+macro_rules! marker {
+    ($marker:expr, $id:expr) => {
+        $marker.pulses($id)
+    };
+    ($marker:expr, $id:expr, $body:block) => {{
+        let marker: &$crate::marker::Marker = &$marker;
+        marker.pulses($id);
+        marker.toggle();
+        let result = $body;
+        marker.toggle();
+        result
+    }};
+}
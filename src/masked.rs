@@ -0,0 +1,89 @@
+//! Interrupt-masked sections that don't lose time.
+//!
+//! The timer keeps counting while interrupts are masked, but only one
+//! compare match is latched, so a section lasting more than a timer period
+//! silently drops the others.  A [`Masked`] section reads the timer when it
+//! starts, and when it ends adds back the whole periods the ISR can't
+//! account for, given roughly how long it took.
+
+use avr_device::interrupt::{self, CriticalSection};
+
+use crate::time::Duration;
+
+// The status register, through the data address space, and its global
+// interrupt enable bit.  The ATmega4809 maps the I/O registers from data
+// address 0 rather than 0x20.
+#[cfg(not(feature = "atmega4809"))]
+const SREG: *const u8 = 0x5f as *const u8;
+#[cfg(feature = "atmega4809")]
+const SREG: *const u8 = 0x3f as *const u8;
+const I: u8 = 1 << 7;
+
+/// Returns whether interrupts are enabled globally, from the I flag in
+/// SREG.
+pub(crate) fn interrupts_enabled() -> bool {
+    let sreg = unsafe { core::ptr::read_volatile(SREG) };
+    sreg & I != 0
+}
+
+/// A section with interrupts disabled.
+///
+/// Interrupts are enabled again when it is dropped if they were enabled
+/// when it started.  Dropping it without [`exit_after_cycles`] or
+/// [`exit_after`] only counts the period the hardware latched.
+///
+/// [`exit_after_cycles`]: Masked::exit_after_cycles
+/// [`exit_after`]: Masked::exit_after
+pub struct Masked {
+    cs: CriticalSection,
+    enabled: bool,
+    start_counts: u16,
+    start_pending: bool,
+}
+
+impl Masked {
+    /// Disables interrupts and reads the timer.
+    pub fn enter() -> Self {
+        let enabled = interrupts_enabled();
+        interrupt::disable();
+        let (start_counts, start_pending) = crate::timer_snapshot();
+        Masked {
+            // Safety: interrupts stay disabled until the section is dropped.
+            cs: unsafe { CriticalSection::new() },
+            enabled,
+            start_counts,
+            start_pending,
+        }
+    }
+
+    /// Returns the critical section token, e.g. to borrow a `Mutex`.
+    pub fn cs(&self) -> &CriticalSection {
+        &self.cs
+    }
+
+    /// Ends the section, which took about `cycles` CPU cycles, and adds the
+    /// timer periods that passed meanwhile.
+    ///
+    /// The timer is read again to tell where in a period the section ended,
+    /// so the estimate only has to be right to within half a timer period,
+    /// e.g. 0.5 ms with the default configuration.
+    pub fn exit_after_cycles(self, cycles: u32) {
+        crate::catch_up(&self.cs, self.start_counts, self.start_pending, cycles);
+    }
+
+    /// Ends the section, which took about `duration`, and adds the timer
+    /// periods that passed meanwhile.
+    pub fn exit_after(self, duration: Duration) {
+        let cycles = duration.as_micros().saturating_mul(crate::CLOCK_MHZ);
+        self.exit_after_cycles(cycles)
+    }
+}
+
+impl Drop for Masked {
+    fn drop(&mut self) {
+        if self.enabled {
+            // Safety: they were enabled when the section started.
+            unsafe { interrupt::enable() };
+        }
+    }
+}
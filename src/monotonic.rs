@@ -0,0 +1,97 @@
+//! RTIC monotonic backed by the time base.
+//!
+//! The time base keeps its own timer ISR, and the monotonic schedules its
+//! compare on the timer's second compare unit: once the compare is due
+//! within a timer period it is armed there, so tasks are dispatched within
+//! a timer count of their instant plus the interrupt latency.  The
+//! application binds the compare unit B interrupt of the selected backend,
+//! e.g. `#[monotonic(binds = TIMER0_COMPB, default = true)]`, or calls
+//! `clear_compare_flag` and `on_interrupt` from its own ISR for that
+//! vector when it drives the trait without RTIC's macros.
+//!
+//! That compare unit can't also serve the embassy driver, and the backends
+//! without one to spare aren't supported.
+
+use core::cell;
+
+use avr_device::interrupt::{CriticalSection, Mutex};
+use rtic_monotonic::Monotonic;
+
+use crate::{micros64, micros64_in, micros_init, Timer};
+
+#[cfg(feature = "embassy")]
+compile_error!("the `rtic` and `embassy` features both need the second compare unit");
+
+#[cfg(any(feature = "attiny85", feature = "atmega4809", feature = "arduino-core"))]
+compile_error!("the `rtic` feature requires a time base timer with a free second compare unit");
+
+/// A point in time, in microseconds since the time base was initialized.
+pub type Instant = fugit::TimerInstantU64<1_000_000>;
+
+/// A span of time in microseconds.
+pub type Duration = fugit::TimerDurationU64<1_000_000>;
+
+// The instant of the pending compare, or `u64::MAX` if there is none.
+static COMPARE: Mutex<cell::Cell<u64>> = Mutex::new(cell::Cell::new(u64::MAX));
+
+/// An RTIC monotonic counting microseconds on the time base.
+pub struct MicrosMonotonic {
+    _private: (),
+}
+
+impl MicrosMonotonic {
+    /// Initializes the time base on `timer` and returns the monotonic.
+    pub fn new(timer: &Timer) -> Self {
+        micros_init(timer);
+        MicrosMonotonic { _private: () }
+    }
+}
+
+impl Monotonic for MicrosMonotonic {
+    // The compare only interrupts while one is pending, and the time base
+    // interrupt has to keep running regardless.
+    const DISABLE_INTERRUPT_ON_EMPTY_QUEUE: bool = false;
+
+    type Instant = Instant;
+    type Duration = Duration;
+
+    fn now(&mut self) -> Self::Instant {
+        Instant::from_ticks(micros64())
+    }
+
+    fn zero() -> Self::Instant {
+        Instant::from_ticks(0)
+    }
+
+    unsafe fn reset(&mut self) {
+        crate::reset_counters();
+    }
+
+    fn set_compare(&mut self, instant: Self::Instant) {
+        avr_device::interrupt::free(|cs| {
+            let timestamp = instant.ticks();
+            COMPARE.borrow(cs).set(timestamp);
+            crate::schedule_compare_b(cs, timestamp, micros64_in(cs));
+        })
+    }
+
+    fn clear_compare_flag(&mut self) {
+        // The flag is cleared by hardware on entry to the ISR.  Dropping the
+        // compare keeps the tick from arming it again; RTIC sets the next
+        // one if tasks are still queued.
+        avr_device::interrupt::free(|cs| {
+            crate::timer::disarm_compare_b();
+            COMPARE.borrow(cs).set(u64::MAX);
+        })
+    }
+}
+
+/// Arms compare unit B for the pending compare once it is due within a
+/// period.  Called from the timer ISR after the counters have been
+/// advanced.
+pub(crate) fn on_tick(cs: &CriticalSection) {
+    let timestamp = COMPARE.borrow(cs).get();
+    if timestamp != u64::MAX {
+        crate::schedule_compare_b(cs, timestamp, micros64_in(cs));
+    }
+}
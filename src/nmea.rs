@@ -0,0 +1,294 @@
+//! UTC time from a GPS receiver's NMEA sentences.
+//!
+//! Bytes read from the UART are fed to a [`Parser`] one at a time.  It
+//! recognizes `RMC` and `ZDA` sentences from any talker (`$GPRMC`, `$GNZDA`,
+//! ...), validates their checksum and extracts the UTC date and time.  The
+//! arrival of each sentence's `$` is timestamped, with
+//! [`micros64`](crate::micros64) as it is fed or with the timestamp passed
+//! to [`Parser::feed_at`], so the rest of the transfer and the parsing don't
+//! add to the error of the time applied to a [`WallClock`].  Whatever
+//! passes between the `$` arriving and being timestamped does, as does the
+//! receiver's own output latency, which is usually constant but can reach
+//! tens of milliseconds.
+
+use core::convert::TryFrom;
+
+use crate::micros64;
+use crate::wall_clock::{DateTime, WallClock};
+
+// The longest sentence allowed by the standard, including `$` and CR LF.
+const MAX_SENTENCE: usize = 82;
+
+// The most digits a numeric field may have, so that it always fits a `u32`.
+const MAX_DIGITS: usize = 9;
+
+// The years a `u32` Unix timestamp can represent in full.
+const YEARS: core::ops::RangeInclusive<u16> = 1970..=2105;
+
+/// A UTC time read from a sentence.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NmeaTime {
+    /// The date and time the sentence refers to.
+    pub date_time: DateTime,
+    /// The fraction of the second, in microseconds.
+    pub subsec_micros: u32,
+    /// When the sentence started arriving, as a [`micros64`] timestamp.
+    pub received_at: u64,
+}
+
+impl NmeaTime {
+    /// Sets `clock` to this time as of the sentence's arrival.
+    pub fn discipline(&self, clock: &mut WallClock) {
+        let second_start = self.received_at.saturating_sub(self.subsec_micros as u64);
+        clock.set_unix_at(self.date_time.to_unix(), second_start);
+    }
+}
+
+/// Assembles sentences from serial bytes.
+pub struct Parser {
+    buf: [u8; MAX_SENTENCE],
+    len: usize,
+    received_at: u64,
+    in_sentence: bool,
+}
+
+impl Parser {
+    /// Creates a parser waiting for the start of a sentence.
+    pub const fn new() -> Self {
+        Parser {
+            buf: [0; MAX_SENTENCE],
+            len: 0,
+            received_at: 0,
+            in_sentence: false,
+        }
+    }
+
+    /// Feeds a received byte, returning the time once a valid `RMC` or `ZDA`
+    /// sentence is complete.
+    ///
+    /// Call it as soon as each byte arrives, since the arrival of `$` is
+    /// timestamped when it is fed.
+    pub fn feed(&mut self, byte: u8) -> Option<NmeaTime> {
+        self.feed_at(byte, micros64())
+    }
+
+    /// Feeds a byte received at `at`, a [`micros64`] timestamp taken e.g.
+    /// in the receive interrupt.  Only the timestamp of the `$` is kept.
+    pub fn feed_at(&mut self, byte: u8, at: u64) -> Option<NmeaTime> {
+        match byte {
+            b'$' => {
+                self.received_at = at;
+                self.len = 0;
+                self.in_sentence = true;
+                None
+            }
+            b'\r' | b'\n' if self.in_sentence => {
+                self.in_sentence = false;
+                let time = parse(&self.buf[..self.len]);
+                time.map(|(date_time, subsec_micros)| NmeaTime {
+                    date_time,
+                    subsec_micros,
+                    received_at: self.received_at,
+                })
+            }
+            _ if self.in_sentence => {
+                if self.len == self.buf.len() {
+                    // Too long to be valid.
+                    self.in_sentence = false;
+                } else {
+                    self.buf[self.len] = byte;
+                    self.len += 1;
+                }
+                None
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Default for Parser {
+    fn default() -> Self {
+        Parser::new()
+    }
+}
+
+/// Parses a sentence without its leading `$`.
+fn parse(sentence: &[u8]) -> Option<(DateTime, u32)> {
+    let body = checked_body(sentence)?;
+    let mut fields = body.split(|&b| b == b',');
+    let kind = fields.next()?;
+    if kind.len() != 5 {
+        return None;
+    }
+    match &kind[2..] {
+        b"RMC" => {
+            // hhmmss.ss,status,lat,N/S,lon,E/W,speed,course,ddmmyy
+            let time = fields.next()?;
+            if fields.next()? != b"A" {
+                // No fix, so the time may not be valid either.
+                return None;
+            }
+            let date = fields.nth(6)?;
+            if date.len() != 6 {
+                return None;
+            }
+            let day = number(&date[0..2])?;
+            let month = number(&date[2..4])?;
+            let year = 2000 + number::<u16>(&date[4..6])?;
+            with_time(time, year, month, day)
+        }
+        b"ZDA" => {
+            // hhmmss.ss,dd,mm,yyyy,...
+            let time = fields.next()?;
+            let day = number(fields.next()?)?;
+            let month = number(fields.next()?)?;
+            let year = number(fields.next()?)?;
+            with_time(time, year, month, day)
+        }
+        _ => None,
+    }
+}
+
+/// Returns the part of the sentence covered by the checksum if the checksum
+/// matches.
+fn checked_body(sentence: &[u8]) -> Option<&[u8]> {
+    let star = sentence.iter().position(|&b| b == b'*')?;
+    let (body, checksum) = (&sentence[..star], &sentence[star + 1..]);
+    if checksum.len() != 2 {
+        return None;
+    }
+    let expected = hex(checksum[0])? << 4 | hex(checksum[1])?;
+    let actual = body.iter().fold(0, |sum, &b| sum ^ b);
+    if actual == expected {
+        Some(body)
+    } else {
+        None
+    }
+}
+
+/// Combines an `hhmmss.sss` field with a date, rejecting values out of
+/// range.  A second of 60 is accepted for leap seconds.
+fn with_time(field: &[u8], year: u16, month: u8, day: u8) -> Option<(DateTime, u32)> {
+    if field.len() < 6
+        || !YEARS.contains(&year)
+        || !(1..=12).contains(&month)
+        || !(1..=31).contains(&day)
+    {
+        return None;
+    }
+    let mut subsec_micros = 0;
+    if field.len() > 7 && field[6] == b'.' {
+        let mut scale = 100_000;
+        for &b in field[7..].iter().take(6) {
+            subsec_micros += digits(&[b])? * scale;
+            scale /= 10;
+        }
+    }
+    let date_time = DateTime {
+        year,
+        month,
+        day,
+        hour: number(&field[0..2])?,
+        minute: number(&field[2..4])?,
+        second: number(&field[4..6])?,
+    };
+    if date_time.hour > 23 || date_time.minute > 59 || date_time.second > 60 {
+        return None;
+    }
+    Some((date_time, subsec_micros))
+}
+
+/// Parses a field of decimal digits into a `T`, rejecting values that don't
+/// fit.
+fn number<T: TryFrom<u32>>(field: &[u8]) -> Option<T> {
+    T::try_from(digits(field)?).ok()
+}
+
+fn digits(field: &[u8]) -> Option<u32> {
+    if field.is_empty() || field.len() > MAX_DIGITS {
+        return None;
+    }
+    field.iter().try_fold(0u32, |value, &b| match b {
+        b'0'..=b'9' => value.checked_mul(10)?.checked_add((b - b'0') as u32),
+        _ => None,
+    })
+}
+
+fn hex(b: u8) -> Option<u8> {
+    match b {
+        b'0'..=b'9' => Some(b - b'0'),
+        b'A'..=b'F' => Some(b - b'A' + 10),
+        b'a'..=b'f' => Some(b - b'a' + 10),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_rmc() {
+        let (date_time, subsec_micros) =
+            parse(b"GPRMC,123519.25,A,4807.038,N,01131.000,E,022.4,084.4,230394,003.1,W*43")
+                .unwrap();
+        assert_eq!(
+            date_time,
+            DateTime {
+                year: 2094,
+                month: 3,
+                day: 23,
+                hour: 12,
+                minute: 35,
+                second: 19,
+            }
+        );
+        assert_eq!(subsec_micros, 250_000);
+    }
+
+    #[test]
+    fn parses_zda() {
+        let (date_time, subsec_micros) = parse(b"GNZDA,201530.00,04,07,2024,00,00*7A").unwrap();
+        assert_eq!(date_time.to_unix(), 1_720_124_130);
+        assert_eq!(subsec_micros, 0);
+    }
+
+    #[test]
+    fn rejects_bad_checksums() {
+        assert_eq!(parse(b"GNZDA,201530.00,04,07,2024,00,00*7B"), None);
+        assert_eq!(parse(b"GNZDA,201530.00,04,07,2024,00,00*7"), None);
+        assert_eq!(parse(b"GNZDA,201530.00,04,07,2024,00,00"), None);
+    }
+
+    #[test]
+    fn rejects_rmc_without_fix() {
+        assert_eq!(
+            parse(b"GPRMC,123519,V,4807.038,N,01131.000,E,022.4,084.4,230394,003.1,W*7D"),
+            None
+        );
+    }
+
+    #[test]
+    fn rejects_out_of_range_fields() {
+        // Before 1970, and too many digits to fit a `u32`.
+        assert_eq!(parse(b"GPZDA,201530.00,04,07,1969,00,00*67"), None);
+        assert_eq!(parse(b"GPZDA,201530.00,04,07,99999999999,00,00*59"), None);
+        // Hour 24.
+        assert_eq!(parse(b"GPZDA,241530.00,04,07,2024,00,00*60"), None);
+    }
+
+    #[test]
+    fn stamps_sentences_with_the_arrival_of_the_dollar() {
+        let mut parser = Parser::new();
+        let sentence = b"$GNZDA,201530.00,04,07,2024,00,00*7A\r\n";
+        let mut times = sentence
+            .iter()
+            .enumerate()
+            .filter_map(|(i, &byte)| parser.feed_at(byte, 1_000 + i as u64 * 174));
+        let time = times.next().unwrap();
+        assert_eq!(time.received_at, 1_000);
+        assert_eq!(time.date_time.to_unix(), 1_720_124_130);
+        // The LF after the CR is outside any sentence.
+        assert_eq!(times.next(), None);
+    }
+}
@@ -0,0 +1,160 @@
+//! A bit-banged 1-Wire bus master, e.g. for DS18B20 temperature sensors.
+//!
+//! The standard speed slot lengths are used.  The parts of a slot shorter
+//! than 15 us, the pulse starting a one or a read and the wait until a read
+//! is sampled, are timed with [`delay_cycles`] with interrupts disabled, as
+//! they are finer than the time base's resolution and can't be stretched by
+//! an interrupt.  The reset pulse and the rest of each slot come from
+//! [`delay_micros`], and tolerate other interrupts.  Reads should still be
+//! checked with [`crc8`].
+//!
+//! The data pin must be open drain with a pull-up, so that setting it high
+//! releases the bus, as with avr-hal's `OpenDrain` or `TriState` modes.
+
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+use crate::delay::{delay_cycles, delay_micros};
+use crate::CLOCK_MHZ;
+
+/// Selects all devices on the bus without addressing them.
+pub const SKIP_ROM: u8 = 0xcc;
+
+/// Selects the device whose ROM code follows.
+pub const MATCH_ROM: u8 = 0x55;
+
+/// Reads the ROM code of the only device on the bus.
+pub const READ_ROM: u8 = 0x33;
+
+/// A 1-Wire bus on an open drain pin.
+pub struct OneWire<P> {
+    pin: P,
+}
+
+impl<P: InputPin + OutputPin> OneWire<P> {
+    /// Creates a bus master, releasing the bus.
+    pub fn new(mut pin: P) -> Self {
+        pin.set_high().ok();
+        OneWire { pin }
+    }
+
+    /// Sends a reset pulse and returns whether any device answered with a
+    /// presence pulse.
+    pub fn reset(&mut self) -> bool {
+        self.pin.set_low().ok();
+        delay_micros(480);
+        self.pin.set_high().ok();
+        delay_micros(70);
+        let present = self.pin.is_low().unwrap_or(false);
+        // Let the presence pulse and the rest of the recovery time pass.
+        delay_micros(410);
+        present
+    }
+
+    /// Writes a single bit in one 60 us slot.
+    pub fn write_bit(&mut self, bit: bool) {
+        if bit {
+            avr_device::interrupt::free(|_| {
+                self.pin.set_low().ok();
+                delay_cycles(6 * CLOCK_MHZ);
+                self.pin.set_high().ok();
+            });
+            delay_micros(64);
+        } else {
+            self.pin.set_low().ok();
+            delay_micros(60);
+            self.pin.set_high().ok();
+            delay_micros(10);
+        }
+    }
+
+    /// Reads a single bit in one 60 us slot.
+    pub fn read_bit(&mut self) -> bool {
+        let bit = avr_device::interrupt::free(|_| {
+            self.pin.set_low().ok();
+            delay_cycles(6 * CLOCK_MHZ);
+            self.pin.set_high().ok();
+            // Devices hold the bus low for at least 15 us from the start of
+            // the slot to send a zero.
+            delay_cycles(9 * CLOCK_MHZ);
+            self.pin.is_high().unwrap_or(true)
+        });
+        delay_micros(55);
+        bit
+    }
+
+    /// Writes a byte, least significant bit first.
+    pub fn write_byte(&mut self, byte: u8) {
+        for i in 0..8 {
+            self.write_bit(byte & (1 << i) != 0);
+        }
+    }
+
+    /// Reads a byte, least significant bit first.
+    pub fn read_byte(&mut self) -> u8 {
+        (0..8).fold(0, |byte, i| byte | ((self.read_bit() as u8) << i))
+    }
+
+    /// Writes all of `bytes`.
+    pub fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.write_byte(byte);
+        }
+    }
+
+    /// Fills `buffer` with bytes read from the bus.
+    pub fn read(&mut self, buffer: &mut [u8]) {
+        for byte in buffer {
+            *byte = self.read_byte();
+        }
+    }
+
+    /// Resets the bus and selects the device with ROM code `rom`, or all
+    /// devices if `None`.  Returns `false` if no device is present.
+    pub fn select(&mut self, rom: Option<&[u8; 8]>) -> bool {
+        if !self.reset() {
+            return false;
+        }
+        match rom {
+            Some(rom) => {
+                self.write_byte(MATCH_ROM);
+                self.write(rom);
+            }
+            None => self.write_byte(SKIP_ROM),
+        }
+        true
+    }
+
+    /// Reads the ROM code of the only device on the bus, or `None` if no
+    /// device is present or the code is corrupted.
+    pub fn read_rom(&mut self) -> Option<[u8; 8]> {
+        if !self.reset() {
+            return None;
+        }
+        self.write_byte(READ_ROM);
+        let mut rom = [0; 8];
+        self.read(&mut rom);
+        if crc8(&rom[..7]) == rom[7] {
+            Some(rom)
+        } else {
+            None
+        }
+    }
+
+    /// Releases the pin.
+    pub fn release(self) -> P {
+        self.pin
+    }
+}
+
+/// Computes the Dallas/Maxim CRC-8 used by ROM codes and scratchpads.
+pub fn crc8(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0, |crc, &byte| {
+        (0..8).fold(crc ^ byte, |crc, _| {
+            if crc & 1 != 0 {
+                (crc >> 1) ^ 0x8c
+            } else {
+                crc >> 1
+            }
+        })
+    })
+}
@@ -0,0 +1,117 @@
+//! Trimming the internal RC oscillator against a reference signal.
+//!
+//! Boards running from the internal oscillator are only accurate to a few
+//! percent out of the factory, which shows up directly as drift in
+//! [`micros`](crate::micros).  [`tune`] steps the OSCCAL register until the
+//! period of a reference signal, e.g. a 1 kHz square wave or a stream of
+//! `'U'` characters on the UART's RX pin, measures as expected.  The result
+//! can be stored in EEPROM with [`save`] and applied at boot with [`load`].
+
+use crate::eeprom;
+use crate::time::Duration;
+use crate::timeout::Timeout;
+
+pub use crate::eeprom::Eeprom;
+
+/// The peripheral holding the OSCCAL register.
+pub type Cpu = crate::pac::CPU;
+
+// The number of reference periods averaged by each measurement.
+const AVERAGED_PERIODS: u32 = 8;
+
+// The furthest `tune` will move OSCCAL from its starting value.
+const MAX_STEPS: u8 = 64;
+
+// An erased EEPROM cell reads as all ones.
+const ERASED: u8 = 0xff;
+
+/// Applies the OSCCAL value stored at `address`, returning `false` if none
+/// has been saved.
+pub fn load(cpu: &Cpu, eeprom: &Eeprom, address: u16) -> bool {
+    match eeprom::read(eeprom, address) {
+        ERASED => false,
+        value => {
+            cpu.osccal.write(|w| unsafe { w.bits(value) });
+            true
+        }
+    }
+}
+
+/// Stores the current OSCCAL value at `address`.
+pub fn save(cpu: &Cpu, eeprom: &Eeprom, address: u16) {
+    eeprom::write(eeprom, address, cpu.osccal.read().bits());
+}
+
+/// Trims OSCCAL until a reference signal with the given `period` measures
+/// as close to it as possible, and returns the chosen value.
+///
+/// `level` samples the reference, returning `true` while it is high, and is
+/// timed between rising edges.  For UART bit timing, send `'U'` (0x55) in a
+/// loop: its rising edges are two bit periods apart.  Returns `None`, with
+/// the best value found so far applied, if the reference stops toggling.
+///
+/// The time base must be running.  The steps stay within the current
+/// range of the oscillator, as the two ranges overlap on some devices.
+pub fn tune(cpu: &Cpu, period: Duration, mut level: impl FnMut() -> bool) -> Option<u8> {
+    let start = cpu.osccal.read().bits();
+    let mut best = (start, measure(period, &mut level)?);
+    let step_down = best.1 > 0;
+
+    let mut value = start;
+    for _ in 0..MAX_STEPS {
+        value = if step_down {
+            value.wrapping_sub(1)
+        } else {
+            value.wrapping_add(1)
+        };
+        if value & 0x80 != start & 0x80 {
+            break;
+        }
+        cpu.osccal.write(|w| unsafe { w.bits(value) });
+        let error = match measure(period, &mut level) {
+            Some(error) => error,
+            None => {
+                cpu.osccal.write(|w| unsafe { w.bits(best.0) });
+                return None;
+            }
+        };
+        if error.abs() < best.1.abs() {
+            best = (value, error);
+        }
+        // Stop once the error has crossed zero.
+        if (error > 0) != step_down {
+            break;
+        }
+    }
+
+    cpu.osccal.write(|w| unsafe { w.bits(best.0) });
+    Some(best.0)
+}
+
+/// Returns how many microseconds longer than expected the reference
+/// periods measured, or `None` if an edge didn't arrive in time.
+fn measure(period: Duration, level: &mut impl FnMut() -> bool) -> Option<i32> {
+    let timeout = Timeout::new(period * (AVERAGED_PERIODS + 2) * 2);
+    let start = rising_edge(level, &timeout)?;
+    let mut end = start;
+    for _ in 0..AVERAGED_PERIODS {
+        end = rising_edge(level, &timeout)?;
+    }
+    let expected = period.as_micros() * AVERAGED_PERIODS;
+    Some(end.wrapping_sub(start) as i32 - expected as i32)
+}
+
+/// Waits for the next rising edge and returns its time.
+fn rising_edge(level: &mut impl FnMut() -> bool, timeout: &Timeout) -> Option<u32> {
+    while level() {
+        if timeout.expired() {
+            return None;
+        }
+    }
+    while !level() {
+        if timeout.expired() {
+            return None;
+        }
+    }
+    Some(crate::micros())
+}
@@ -0,0 +1,85 @@
+//! A panic handler that reports the panic over serial before halting.
+//!
+//! With the `panic-serial` feature the crate provides the program's
+//! `#[panic_handler]`, so `panic_halt` or a similar crate must not be linked
+//! as well.  On a panic it disables interrupts, sends anything still queued
+//! in the [`SerialTx`](crate::serial_tx::SerialTx) buffer, then writes
+//!
+//! `panicked at 1234567 us: message, src/main.rs:12:5`
+//!
+//! on USART0 by polling, and halts.  If the transmitter wasn't enabled it is
+//! set up at 57600 baud.  To keep `core::fmt` out of the binary, only
+//! messages without arguments are printed; others show as `...`.
+
+use core::panic::PanicInfo;
+use core::sync::atomic::{self, Ordering};
+
+#[cfg(feature = "serial-tx")]
+use avr_device::interrupt::CriticalSection;
+
+use crate::usart::{self, Registers, Usart, TXEN0, UDRE0};
+
+// The baud rate used if the program never enabled the transmitter.
+const FALLBACK_BAUD: u32 = 57600;
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    avr_device::interrupt::disable();
+    let usart: &Registers = unsafe { &*Usart::ptr() };
+
+    if usart.ucsr0b.read().bits() & TXEN0 == 0 {
+        usart::configure(usart, FALLBACK_BAUD);
+        usart::modify_control(usart, TXEN0, 0);
+    }
+    // Interrupts stay disabled from here on.
+    #[cfg(feature = "serial-tx")]
+    crate::serial_tx::drain_in(&unsafe { CriticalSection::new() }, usart);
+
+    write_str(usart, "\r\npanicked at ");
+    write_u32(usart, crate::micros());
+    write_str(usart, " us: ");
+    match info.message().and_then(|message| message.as_str()) {
+        Some(message) => write_str(usart, message),
+        None => write_str(usart, "..."),
+    }
+    if let Some(location) = info.location() {
+        write_str(usart, ", ");
+        write_str(usart, location.file());
+        write_str(usart, ":");
+        write_u32(usart, location.line());
+        write_str(usart, ":");
+        write_u32(usart, location.column());
+    }
+    write_str(usart, "\r\n");
+
+    loop {
+        atomic::compiler_fence(Ordering::SeqCst);
+    }
+}
+
+fn write_byte(usart: &Registers, byte: u8) {
+    while usart.ucsr0a.read().bits() & UDRE0 == 0 {}
+    usart.udr0.write(|w| unsafe { w.bits(byte) });
+}
+
+fn write_str(usart: &Registers, s: &str) {
+    for &byte in s.as_bytes() {
+        write_byte(usart, byte);
+    }
+}
+
+fn write_u32(usart: &Registers, mut value: u32) {
+    let mut digits = [0u8; 10];
+    let mut len = 0;
+    loop {
+        digits[len] = b'0' + (value % 10) as u8;
+        len += 1;
+        value /= 10;
+        if value == 0 {
+            break;
+        }
+    }
+    for &digit in digits[..len].iter().rev() {
+        write_byte(usart, digit);
+    }
+}
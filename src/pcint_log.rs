@@ -0,0 +1,163 @@
+//! Timestamped logging of pin changes.
+//!
+//! The pin change interrupts fire on any edge of the enabled pins of a port.
+//! Each ISR reads the port, works out which pins changed since the previous
+//! interrupt and queues an [`Event`] with the time for each of them in an
+//! [`EventQueue`], to be read out later, e.g. over serial.
+//!
+//! Pins that toggle again before the ISR has read the port are missed, so
+//! pulses shorter than the interrupt latency, a few microseconds, may not
+//! be logged.
+
+#[cfg(not(feature = "atmega328p"))]
+compile_error!("the `pcint-log` feature is only supported on the ATmega328P");
+
+use core::cell;
+
+use avr_device::interrupt::{CriticalSection, Mutex};
+
+use crate::event_queue::EventQueue;
+use crate::time::Instant;
+
+/// The external interrupt peripheral controlling the pin change interrupts.
+pub type ExtInt = crate::pac::EXINT;
+
+/// The number of events that can be queued before new ones are dropped.
+pub const QUEUE_LEN: usize = 16;
+
+/// A port whose pins can be logged, each with its own pin change interrupt.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Port {
+    /// PCINT0-7, pins 8-13 on the Uno.
+    B,
+    /// PCINT8-14, pins A0-A5 on the Uno.
+    C,
+    /// PCINT16-23, pins 0-7 on the Uno.
+    D,
+}
+
+impl Port {
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// A logged pin change.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Event {
+    /// The port of the pin.
+    pub port: Port,
+    /// The bit within the port.
+    pub pin: u8,
+    /// `true` if the pin went high.
+    pub rising: bool,
+}
+
+#[derive(Clone, Copy)]
+struct State {
+    masks: [u8; 3],
+    levels: [u8; 3],
+}
+
+static STATE: Mutex<cell::Cell<State>> = Mutex::new(cell::Cell::new(State {
+    masks: [0; 3],
+    levels: [0; 3],
+}));
+
+static EVENTS: EventQueue<Event, QUEUE_LEN> = EventQueue::new();
+
+/// Starts logging changes of the pins of `port` set in `mask`, replacing
+/// the pins previously logged on that port.
+pub fn start(exint: &ExtInt, port: Port, mask: u8) {
+    avr_device::interrupt::free(|cs| {
+        let cell = STATE.borrow(cs);
+        let mut state = cell.get();
+        state.masks[port.index()] = mask;
+        state.levels[port.index()] = read_port(port);
+        cell.set(state);
+
+        let bit = 1 << port.index();
+        match port {
+            Port::B => exint.pcmsk0.write(|w| unsafe { w.bits(mask) }),
+            Port::C => exint.pcmsk1.write(|w| unsafe { w.bits(mask) }),
+            Port::D => exint.pcmsk2.write(|w| unsafe { w.bits(mask) }),
+        }
+        exint.pcifr.write(|w| unsafe { w.bits(bit) });
+        exint.pcicr.modify(|r, w| unsafe { w.bits(r.bits() | bit) });
+    })
+}
+
+/// Stops logging changes on `port`.  Events already queued are kept.
+pub fn stop(exint: &ExtInt, port: Port) {
+    let bit = 1 << port.index();
+    exint
+        .pcicr
+        .modify(|r, w| unsafe { w.bits(r.bits() & !bit) });
+    avr_device::interrupt::free(|cs| {
+        let cell = STATE.borrow(cs);
+        let mut state = cell.get();
+        state.masks[port.index()] = 0;
+        cell.set(state);
+    })
+}
+
+/// Takes the oldest queued event, along with the time the ISR ran.
+pub fn read() -> Option<(Instant, Event)> {
+    EVENTS.pop()
+}
+
+/// Returns the number of events dropped because the queue was full.
+pub fn dropped() -> u16 {
+    EVENTS.dropped()
+}
+
+fn read_port(port: Port) -> u8 {
+    unsafe {
+        match port {
+            Port::B => (*crate::pac::PORTB::ptr()).pinb.read().bits(),
+            Port::C => (*crate::pac::PORTC::ptr()).pinc.read().bits(),
+            Port::D => (*crate::pac::PORTD::ptr()).pind.read().bits(),
+        }
+    }
+}
+
+fn on_change(cs: &CriticalSection, port: Port) {
+    let now = crate::now_in(cs);
+    #[cfg(feature = "entropy")]
+    crate::entropy::mix_in(cs);
+    let levels = read_port(port);
+    let cell = STATE.borrow(cs);
+    let mut state = cell.get();
+    let changed = (levels ^ state.levels[port.index()]) & state.masks[port.index()];
+    state.levels[port.index()] = levels;
+    cell.set(state);
+
+    for pin in 0..8 {
+        if changed & (1 << pin) != 0 {
+            let event = Event {
+                port,
+                pin,
+                rising: levels & (1 << pin) != 0,
+            };
+            EVENTS.push_in(cs, now, event);
+        }
+    }
+}
+
+isr! {
+    fn PCINT0() {
+        avr_device::interrupt::free(|cs| on_change(cs, Port::B))
+    }
+}
+
+isr! {
+    fn PCINT1() {
+        avr_device::interrupt::free(|cs| on_change(cs, Port::C))
+    }
+}
+
+isr! {
+    fn PCINT2() {
+        avr_device::interrupt::free(|cs| on_change(cs, Port::D))
+    }
+}
@@ -0,0 +1,74 @@
+//! Non-blocking periodic blocks, as written with [`every!`](crate::every).
+
+use core::cell;
+
+use avr_device::interrupt::Mutex;
+
+use crate::time::{time_after, Duration, Instant};
+
+/// The schedule of a periodic block, meant to live in a `static`.
+pub struct Periodic {
+    next: Mutex<cell::Cell<Option<Instant>>>,
+}
+
+impl Periodic {
+    /// Creates a schedule that is due right away.
+    pub const fn new() -> Self {
+        Periodic {
+            next: Mutex::new(cell::Cell::new(None)),
+        }
+    }
+
+    /// Returns `true` if the block is due, and schedules it one `period`
+    /// later.
+    ///
+    /// The next run is scheduled from when this one was due, not from now,
+    /// so a block polled late doesn't drift.  After falling behind by more
+    /// than a period, e.g. across a long blocking call, it is rescheduled
+    /// from now instead of running several times to catch up.
+    pub fn due(&self, period: Duration) -> bool {
+        let now = crate::now();
+        avr_device::interrupt::free(|cs| {
+            let next = self.next.borrow(cs);
+            let due = match next.get() {
+                Some(due) if time_after(due.as_micros(), now.as_micros()) => return false,
+                Some(due) => due,
+                None => now,
+            };
+            let from = if now - due >= period { now } else { due };
+            next.set(Some(from + period));
+            true
+        })
+    }
+}
+
+impl Default for Periodic {
+    fn default() -> Self {
+        Periodic::new()
+    }
+}
+
+/// Runs a block every given period, e.g. `every!(500_000 us => { ... })`,
+/// without blocking.
+///
+/// The period is a literal followed by `us`, `ms` or `s`, or any expression
+/// evaluating to a [`Duration`](crate::Duration).  Each use expands to its
+/// own static [`Periodic`](crate::periodic::Periodic), so the block runs
+/// the first time it is reached and then once per period, as long as the
+/// surrounding loop comes by often enough.
+#[macro_export]
+macro_rules! every {
+    ($period:literal us => $body:block) => {
+        $crate::every!($crate::Duration::from_micros($period) => $body)
+    };
+    ($period:literal ms => $body:block) => {
+        $crate::every!($crate::Duration::from_millis($period) => $body)
+    };
+    ($period:literal s => $body:block) => {
+        $crate::every!($crate::Duration::from_secs($period) => $body)
+    };
+    ($period:expr => $body:block) => {{
+        static PERIODIC: $crate::periodic::Periodic = $crate::periodic::Periodic::new();
+        if PERIODIC.due($period) $body
+    }};
+}
@@ -0,0 +1,192 @@
+//! Sleep modes that keep the time base running, and switching off unused
+//! peripherals to save power.
+//!
+//! For the deeper modes that stop it, see `deep_sleep`, enabled by the
+//! `deep-sleep` feature.
+
+#[cfg(any(feature = "executor", not(feature = "atmega4809")))]
+#[cfg(target_arch = "avr")]
+use core::arch::asm;
+
+#[cfg(any(feature = "executor", not(feature = "atmega4809")))]
+use avr_device::interrupt::CriticalSection;
+
+/// The sleep modes the crate uses.
+#[derive(Clone, Copy)]
+pub(crate) enum Mode {
+    Idle,
+    #[cfg(not(feature = "atmega4809"))]
+    PowerDown,
+    #[cfg(feature = "rtc")]
+    PowerSave,
+}
+
+// PRR bits.
+#[cfg(feature = "atmega328p")]
+pub(crate) const PRADC: u8 = 1 << 0;
+#[cfg(feature = "atmega328p")]
+const PRUSART0: u8 = 1 << 1;
+#[cfg(feature = "atmega328p")]
+const PRSPI: u8 = 1 << 2;
+#[cfg(feature = "atmega328p")]
+const PRTIM1: u8 = 1 << 3;
+#[cfg(feature = "atmega328p")]
+const PRTIM0: u8 = 1 << 5;
+#[cfg(feature = "atmega328p")]
+const PRTIM2: u8 = 1 << 6;
+#[cfg(feature = "atmega328p")]
+const PRTWI: u8 = 1 << 7;
+
+/// Peripherals for [`disable_unused`] to leave powered, besides the ones
+/// the crate's enabled features use.
+#[cfg(feature = "atmega328p")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Keep {
+    pub adc: bool,
+    pub spi: bool,
+    pub twi: bool,
+    pub usart0: bool,
+    pub timer0: bool,
+    pub timer1: bool,
+    pub timer2: bool,
+}
+
+/// Shuts down the peripherals that neither the crate nor `keep` needs
+/// through the power reduction register, which cuts their clocks.
+///
+/// The crate needs the time base timer, Timer1 for `servo`,
+/// `input-capture` and `freq-counter`, Timer2 for `rtc`, and USART0 for the
+/// serial features including `panic-serial`.  For the common case of
+/// timing and serial, `disable_unused(Keep::default())` leaves just those
+/// running.  A peripheral that is shut down keeps its registers, but can't
+/// be used until its PRR bit is cleared again.
+#[cfg(feature = "atmega328p")]
+pub fn disable_unused(keep: Keep) {
+    let timer0 = cfg!(not(any(feature = "timer1", feature = "timer2")));
+    let timer1 = cfg!(any(
+        feature = "timer1",
+        feature = "servo",
+        feature = "input-capture",
+        feature = "freq-counter"
+    ));
+    let timer2 = cfg!(any(feature = "timer2", feature = "rtc"));
+    let usart0 = cfg!(any(
+        feature = "serial-rx",
+        feature = "serial-tx",
+        feature = "panic-serial"
+    ));
+
+    let mut off = 0;
+    for &(used, bit) in &[
+        (keep.adc, PRADC),
+        (keep.spi, PRSPI),
+        (keep.twi, PRTWI),
+        (keep.usart0 || usart0, PRUSART0),
+        (keep.timer0 || timer0, PRTIM0),
+        (keep.timer1 || timer1, PRTIM1),
+        (keep.timer2 || timer2, PRTIM2),
+    ] {
+        if !used {
+            off |= bit;
+        }
+    }
+
+    if off & PRADC != 0 {
+        // The datasheet requires the ADC to be disabled before it is shut
+        // down.
+        let adc = unsafe { &*crate::pac::ADC::ptr() };
+        adc.adcsra.modify(|_, w| w.aden().clear_bit());
+    }
+    let cpu = unsafe { &*crate::pac::CPU::ptr() };
+    cpu.prr.modify(|r, w| unsafe { w.bits(r.bits() | off) });
+}
+
+/// Puts the CPU into IDLE sleep until the next interrupt.
+///
+/// The timers keep running in IDLE mode, so the timer interrupt wakes the
+/// CPU again after at most one period.  Interrupts must be enabled.
+pub fn idle() {
+    enable(Mode::Idle);
+    avr_device::asm::sleep();
+    disable_sleep();
+}
+
+/// Puts the CPU to sleep in `mode` unless `ready` returns `true`.
+///
+/// `ready` is checked with interrupts disabled, and they are only enabled
+/// again by the instruction before `SLEEP`.  An interrupt can't run until
+/// one instruction after `SEI`, so one that makes `ready` true after the
+/// check still wakes the CPU instead of being missed until the next one.
+#[cfg(any(feature = "executor", not(feature = "atmega4809")))]
+pub(crate) fn sleep_unless<F: FnOnce(&CriticalSection) -> bool>(mode: Mode, ready: F) {
+    avr_device::interrupt::disable();
+    // Safety: interrupts were just disabled.
+    if ready(&unsafe { CriticalSection::new() }) {
+        unsafe { avr_device::interrupt::enable() };
+        return;
+    }
+    enable(mode);
+    #[cfg(target_arch = "avr")]
+    unsafe {
+        asm!("sei", "sleep")
+    };
+    disable_sleep();
+}
+
+fn enable(mode: Mode) {
+    #[cfg(not(any(feature = "attiny85", feature = "atmega4809")))]
+    {
+        let cpu = unsafe { &*crate::pac::CPU::ptr() };
+        cpu.smcr.write(|w| {
+            match mode {
+                Mode::Idle => w.sm().idle(),
+                Mode::PowerDown => w.sm().pdown(),
+                #[cfg(feature = "rtc")]
+                Mode::PowerSave => w.sm().psave(),
+            };
+            w.se().set_bit()
+        });
+    }
+
+    // The sleep mode bits live in MCUCR on the ATtiny85.
+    #[cfg(feature = "attiny85")]
+    {
+        let cpu = unsafe { &*crate::pac::CPU::ptr() };
+        cpu.mcucr.modify(|_, w| {
+            match mode {
+                Mode::Idle => w.sm().idle(),
+                Mode::PowerDown => w.sm().pdown(),
+            };
+            w.se().set_bit()
+        });
+    }
+
+    // The megaAVR-0 devices have a dedicated sleep controller, and only
+    // IDLE is used on them.
+    #[cfg(feature = "atmega4809")]
+    {
+        let Mode::Idle = mode;
+        let slpctrl = unsafe { &*crate::pac::SLPCTRL::ptr() };
+        slpctrl.ctrla.write(|w| w.smode().idle().sen().set_bit());
+    }
+}
+
+fn disable_sleep() {
+    #[cfg(not(any(feature = "attiny85", feature = "atmega4809")))]
+    {
+        let cpu = unsafe { &*crate::pac::CPU::ptr() };
+        cpu.smcr.write(|w| w.se().clear_bit());
+    }
+
+    #[cfg(feature = "attiny85")]
+    {
+        let cpu = unsafe { &*crate::pac::CPU::ptr() };
+        cpu.mcucr.modify(|_, w| w.se().clear_bit());
+    }
+
+    #[cfg(feature = "atmega4809")]
+    {
+        let slpctrl = unsafe { &*crate::pac::SLPCTRL::ptr() };
+        slpctrl.ctrla.write(|w| w.sen().clear_bit());
+    }
+}
@@ -0,0 +1,121 @@
+//! Crystal calibration against a 1 PPS reference.
+//!
+//! A GPS receiver's pulse-per-second output is far more accurate than the
+//! board's crystal.  Connected to INT0 (pin 2 on the Uno), each rising edge
+//! is timestamped in CPU cycles and the crystal error is estimated over the
+//! whole measurement window, so it keeps improving the longer the pulses are
+//! received.  A pulse that arrives too early or late, e.g. after the receiver
+//! loses its fix, restarts the measurement.
+
+#[cfg(any(feature = "attiny85", feature = "atmega4809"))]
+compile_error!("the `pps` feature is not supported on this device");
+
+use core::cell;
+
+use avr_device::interrupt::Mutex;
+
+use crate::CLOCK_HZ;
+
+/// The external interrupt peripheral the reference is connected to.
+pub type ExtInt = crate::pac::EXINT;
+
+// Pulses further than this from one second apart, in ppm, are rejected.
+const MAX_ERROR_PPM: u32 = 1000;
+
+#[derive(Clone, Copy)]
+struct Window {
+    first: u64,
+    last: u64,
+    pulses: u32,
+}
+
+static WINDOW: Mutex<cell::Cell<Option<Window>>> = Mutex::new(cell::Cell::new(None));
+
+/// Starts timestamping rising edges on INT0, discarding any previous
+/// measurement.
+///
+/// The time base must already be running.
+pub fn start(exint: &ExtInt) {
+    avr_device::interrupt::free(|cs| WINDOW.borrow(cs).set(None));
+    // ISC01:0 = 0b11 triggers on the rising edge.
+    exint
+        .eicra
+        .modify(|r, w| unsafe { w.bits(r.bits() | 0b11) });
+    exint.eifr.write(|w| unsafe { w.bits(0b1) });
+    exint.eimsk.modify(|r, w| unsafe { w.bits(r.bits() | 0b1) });
+}
+
+/// Stops timestamping edges.  The measurement so far is kept.
+pub fn stop(exint: &ExtInt) {
+    exint
+        .eimsk
+        .modify(|r, w| unsafe { w.bits(r.bits() & !0b1) });
+}
+
+/// Returns the number of consecutive pulses the estimate is based on.
+pub fn pulses() -> u32 {
+    avr_device::interrupt::free(|cs| WINDOW.borrow(cs).get().map_or(0, |w| w.pulses))
+}
+
+/// Returns the crystal error in ppm, positive if the crystal runs fast, or
+/// `None` until at least one full second has been measured.
+pub fn ppm() -> Option<i32> {
+    let window = avr_device::interrupt::free(|cs| WINDOW.borrow(cs).get())?;
+    if window.pulses == 0 {
+        return None;
+    }
+    let expected = window.pulses as i64 * CLOCK_HZ as i64;
+    let error = (window.last - window.first) as i64 - expected;
+    Some((error * 1_000_000 / expected) as i32)
+}
+
+/// Corrects a number of microseconds measured by the crystal by the
+/// estimated error.  Returns it unchanged before the first estimate.
+pub fn correct(micros: u64) -> u64 {
+    let ppm = match ppm() {
+        Some(ppm) => ppm as i64,
+        None => return micros,
+    };
+    // Split the value to keep the product from overflowing.
+    let whole = (micros / 1_000_000) as i64 * ppm;
+    let part = (micros % 1_000_000) as i64 * ppm / 1_000_000;
+    (micros as i64 - whole - part) as u64
+}
+
+/// Returns [`micros64`](crate::micros64) corrected by the estimated crystal
+/// error.
+pub fn corrected_micros64() -> u64 {
+    correct(crate::micros64())
+}
+
+/// Returns `true` if `cycles` is within `MAX_ERROR_PPM` of one second.
+fn plausible(cycles: u64) -> bool {
+    let tolerance = (CLOCK_HZ / 1_000_000 * MAX_ERROR_PPM) as u64;
+    (CLOCK_HZ as u64 - tolerance..=CLOCK_HZ as u64 + tolerance).contains(&cycles)
+}
+
+fn on_pulse() {
+    let now = crate::cycles64();
+    avr_device::interrupt::free(|cs| {
+        let cell = WINDOW.borrow(cs);
+        let window = match cell.get() {
+            Some(window) if plausible(now - window.last) => Window {
+                last: now,
+                pulses: window.pulses + 1,
+                ..window
+            },
+            _ => Window {
+                first: now,
+                last: now,
+                pulses: 0,
+            },
+        };
+        cell.set(Some(window));
+    });
+}
+
+isr! {
+    fn INT0() {
+        on_pulse()
+    }
+}
@@ -0,0 +1,65 @@
+//! Measuring the length of a pulse, like Arduino's `pulseIn()`.
+
+use embedded_hal::digital::v2::InputPin;
+
+use crate::time::Duration;
+use crate::timeout::Timeout;
+
+/// Measures a pulse on `pin` at `level`, `true` for a high pulse.
+///
+/// A pulse already in progress is skipped, then the function waits for the
+/// next one to start and returns its length.  The pin is polled, so the
+/// result is accurate to a few microseconds.  Returns `None` if the whole
+/// measurement takes longer than `timeout` or the pin can't be read.
+pub fn pulse_in<P: InputPin>(pin: &P, level: bool, timeout: Duration) -> Option<Duration> {
+    let timeout = Timeout::new(timeout);
+    let is_level = || pin.is_high().map(|high| high == level).ok();
+
+    while is_level()? {
+        if timeout.expired() {
+            return None;
+        }
+    }
+    while !is_level()? {
+        if timeout.expired() {
+            return None;
+        }
+    }
+    let start = crate::now();
+    while is_level()? {
+        if timeout.expired() {
+            return None;
+        }
+    }
+    Some(crate::now() - start)
+}
+
+/// Measures a pulse at `level` with the ICP1 input capture unit.
+///
+/// The length comes from hardware timestamps, so it is accurate to a single
+/// timer count.  `capture` must have been set up for [`Edges::Both`], and
+/// captures queued before the call are discarded.
+///
+/// [`Edges::Both`]: crate::input_capture::Edges::Both
+#[cfg(feature = "input-capture")]
+pub fn pulse_in_capture(
+    capture: &mut crate::input_capture::InputCapture,
+    level: bool,
+    timeout: Duration,
+) -> Option<Duration> {
+    while capture.read().is_some() {}
+
+    let timeout = Timeout::new(timeout);
+    let mut start = None;
+    loop {
+        match (capture.read(), start) {
+            (Some(edge), None) if edge.rising == level => start = Some(edge.timestamp),
+            (Some(edge), Some(start)) if edge.rising != level => {
+                let counts = edge.timestamp.wrapping_sub(start);
+                return Some(Duration::from_micros(capture.counts_to_micros(counts)));
+            }
+            _ if timeout.expired() => return None,
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,68 @@
+//! The raw counters behind the time base.
+//!
+//! These are for building custom time math, e.g. fixed-point seconds or
+//! cycle counts, without going through the microsecond conversion.  Time
+//! since [`micros_init`](crate::micros_init) is `ticks * counts_per_tick +
+//! counts` timer counts of `prescaler` CPU cycles each, which
+//! [`snapshot`] reads consistently.
+
+use crate::timer;
+
+pub use crate::rollover::wraps;
+
+/// The raw counters at one point in time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Snapshot {
+    /// Timer periods since the counters were reset, wrapping around.
+    pub ticks: u32,
+    /// Timer counts into the current period.
+    pub counts: u16,
+    /// Timer counts per period.
+    pub counts_per_tick: u32,
+    /// CPU cycles per timer count.
+    pub prescaler: u32,
+}
+
+impl Snapshot {
+    /// Returns the timer counts since the counters were reset, as far as
+    /// the wrapping tick count allows.
+    pub fn total_counts(&self) -> u64 {
+        self.ticks as u64 * self.counts_per_tick as u64 + self.counts as u64
+    }
+}
+
+/// Returns the timer periods the ISR has counted since the counters were
+/// reset, wrapping around.
+///
+/// Periods accounted for after interrupts were masked, or while the time
+/// base was suspended, are included.
+pub fn ticks() -> u32 {
+    avr_device::interrupt::free(|cs| {
+        #[cfg(feature = "fast-isr")]
+        crate::fold_fast_ticks(cs);
+        crate::TICKS.borrow(cs).get()
+    })
+}
+
+/// Returns the hardware timer count, e.g. `TCNT0`, without accounting for
+/// a pending interrupt.
+pub fn counts() -> u16 {
+    timer::counts()
+}
+
+/// Reads the tick count and the timer together, with a period whose
+/// interrupt is still pending counted as a tick.
+pub fn snapshot() -> Snapshot {
+    avr_device::interrupt::free(|cs| {
+        #[cfg(feature = "fast-isr")]
+        crate::fold_fast_ticks(cs);
+        let settings = crate::SETTINGS.borrow(cs).get();
+        let (counts, pending) = crate::timer_snapshot();
+        Snapshot {
+            ticks: crate::TICKS.borrow(cs).get().wrapping_add(pending as u32),
+            counts,
+            counts_per_tick: settings.counts,
+            prescaler: settings.prescaler,
+        }
+    })
+}
@@ -0,0 +1,120 @@
+//! The cause of the last reset.
+//!
+//! The reset flags accumulate until cleared, so [`take_cause`] should be
+//! called once, early in `main`.  `watchdog::disable` and
+//! `watchdog::Watchdog::stop` clear them too, since the watchdog can't be
+//! turned off otherwise; call `take_cause` before them, or use the cause
+//! `watchdog::disable` returns.  Note that Arduino bootloaders may clear
+//! the flags themselves before starting the program, in which case the cause
+//! reads as [`Cause::Unknown`].
+//!
+//! Along with [`report`], `UptimeLog::report` from the `uptime` module can
+//! tell how long the previous session ran.
+
+use embedded_hal::serial::Write;
+
+use crate::telemetry::write_str;
+
+// MCUSR bits on the classic devices.
+#[cfg(not(feature = "atmega4809"))]
+mod flag {
+    pub const POWER_ON: u8 = 1 << 0;
+    pub const EXTERNAL: u8 = 1 << 1;
+    pub const BROWNOUT: u8 = 1 << 2;
+    pub const WATCHDOG: u8 = 1 << 3;
+}
+
+// RSTCTRL.RSTFR bits on the ATmega4809.  The UPDI reset counts as external.
+#[cfg(feature = "atmega4809")]
+mod flag {
+    pub const POWER_ON: u8 = 1 << 0;
+    pub const BROWNOUT: u8 = 1 << 1;
+    pub const EXTERNAL: u8 = (1 << 2) | (1 << 5);
+    pub const WATCHDOG: u8 = 1 << 3;
+    pub const SOFTWARE: u8 = 1 << 4;
+}
+
+/// Why the device was reset.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Cause {
+    /// The supply was turned on.
+    PowerOn,
+    /// The supply dropped below the brown-out detector's level.
+    Brownout,
+    /// The watchdog timer expired.
+    Watchdog,
+    /// The reset pin was pulled low, e.g. by the reset button or by the
+    /// serial adapter when a new program is uploaded.
+    External,
+    /// The program requested a reset, on the ATmega4809 only.
+    Software,
+    /// No flag was set, e.g. because a bootloader cleared them or the
+    /// program jumped to the reset vector.
+    Unknown,
+}
+
+impl Cause {
+    /// A short lowercase description, e.g. `"watchdog"`.
+    pub const fn name(self) -> &'static str {
+        match self {
+            Cause::PowerOn => "power-on",
+            Cause::Brownout => "brown-out",
+            Cause::Watchdog => "watchdog",
+            Cause::External => "external",
+            Cause::Software => "software",
+            Cause::Unknown => "unknown",
+        }
+    }
+
+    // Several flags can be set at once, e.g. power-on along with brown-out;
+    // the most fundamental one wins.
+    fn from_flags(flags: u8) -> Self {
+        if flags & flag::POWER_ON != 0 {
+            Cause::PowerOn
+        } else if flags & flag::BROWNOUT != 0 {
+            Cause::Brownout
+        } else if flags & flag::WATCHDOG != 0 {
+            Cause::Watchdog
+        } else if flags & flag::EXTERNAL != 0 {
+            Cause::External
+        } else {
+            #[cfg(feature = "atmega4809")]
+            if flags & flag::SOFTWARE != 0 {
+                return Cause::Software;
+            }
+            Cause::Unknown
+        }
+    }
+}
+
+/// Reads and clears the reset flags, and returns the cause of the last
+/// reset.
+pub fn take_cause() -> Cause {
+    #[cfg(not(feature = "atmega4809"))]
+    let flags = {
+        let cpu = unsafe { &*crate::pac::CPU::ptr() };
+        let flags = cpu.mcusr.read().bits();
+        // The watchdog stays enabled after a watchdog reset as long as WDRF
+        // is set, so this also lets it be turned off.
+        cpu.mcusr.write(|w| unsafe { w.bits(0) });
+        flags
+    };
+
+    // The flags are cleared by writing ones.
+    #[cfg(feature = "atmega4809")]
+    let flags = {
+        let rstctrl = unsafe { &*crate::pac::RSTCTRL::ptr() };
+        let flags = rstctrl.rstfr.read().bits();
+        rstctrl.rstfr.write(|w| unsafe { w.bits(flags) });
+        flags
+    };
+
+    Cause::from_flags(flags)
+}
+
+/// Writes a line such as `reset: watchdog` and CRLF.
+pub fn report<W: Write<u8>>(writer: &mut W, cause: Cause) -> Result<(), W::Error> {
+    write_str(writer, "reset: ")?;
+    write_str(writer, cause.name())?;
+    write_str(writer, "\r\n")
+}
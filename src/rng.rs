@@ -0,0 +1,118 @@
+//! A small pseudo-random number generator, xoshiro128**.
+//!
+//! It keeps 16 bytes of state and only needs 32-bit shifts, rotates and
+//! multiplies, so it is quick enough on AVR for randomized backoff, jitter
+//! or test data.  It is not cryptographically secure.
+//!
+//! An [`Rng`] is only as unpredictable as its seed.  On the ATmega328P
+//! [`Rng::from_noise`] collects one at boot from the low bits of a floating
+//! ADC input and the time each conversion finished, plus the
+//! [`entropy`](crate::entropy) pool with that feature.  With the `rand_core`
+//! feature, `Rng` implements `rand_core::RngCore`.
+
+/// The ADC, for [`Rng::from_noise`].
+#[cfg(feature = "atmega328p")]
+pub type Adc = crate::pac::ADC;
+
+/// A xoshiro128** generator.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rng {
+    state: [u32; 4],
+}
+
+impl Rng {
+    /// Creates a generator from `seed`.  Equal seeds give equal sequences.
+    pub fn new(seed: u32) -> Self {
+        // Expanding the seed with SplitMix32 gives four distinct words, so
+        // the state is never all zero.
+        let mut state = [0; 4];
+        let mut x = seed;
+        for word in state.iter_mut() {
+            x = x.wrapping_add(0x9e37_79b9);
+            let mut z = x;
+            z = (z ^ (z >> 16)).wrapping_mul(0x21f0_aaad);
+            z = (z ^ (z >> 15)).wrapping_mul(0x735a_2d97);
+            *word = z ^ (z >> 15);
+        }
+        Rng { state }
+    }
+
+    /// Creates a generator seeded from noise on the ADC input `channel`,
+    /// which should be an unconnected pin, e.g. 5 for A5.
+    ///
+    /// Takes 32 conversions, about 3.5 ms at 16 MHz.  The ADC is powered up
+    /// for them if [`power::disable_unused`](crate::power::disable_unused)
+    /// shut it down, and its registers are restored afterwards.
+    #[cfg(feature = "atmega328p")]
+    pub fn from_noise(adc: &Adc, channel: u8) -> Self {
+        let cpu = unsafe { &*crate::pac::CPU::ptr() };
+        let prr = cpu.prr.read().bits();
+        let admux = adc.admux.read().bits();
+        let adcsra = adc.adcsra.read().bits();
+        cpu.prr
+            .write(|w| unsafe { w.bits(prr & !crate::power::PRADC) });
+        // AVcc as the reference.
+        adc.admux
+            .write(|w| unsafe { w.bits(0x40 | (channel & 0x0f)) });
+
+        let mut seed = 0u32;
+        for _ in 0..32 {
+            // ADEN, ADSC and a prescaler of 128, the slowest ADC clock.
+            adc.adcsra.write(|w| unsafe { w.bits(0xc7) });
+            while adc.adcsra.read().adsc().bit_is_set() {}
+            let reading = adc.adc.read().bits() as u32;
+            seed = seed.rotate_left(5) ^ reading ^ crate::micros();
+        }
+        #[cfg(feature = "entropy")]
+        {
+            seed ^= crate::entropy::random_u32();
+        }
+
+        adc.admux.write(|w| unsafe { w.bits(admux) });
+        adc.adcsra.write(|w| unsafe { w.bits(adcsra & !0x40) });
+        cpu.prr.write(|w| unsafe { w.bits(prr) });
+        Rng::new(seed)
+    }
+
+    /// Returns the next 32 random bits.
+    pub fn next_u32(&mut self) -> u32 {
+        let s = &mut self.state;
+        let result = s[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = s[1] << 9;
+        s[2] ^= s[0];
+        s[3] ^= s[1];
+        s[1] ^= s[2];
+        s[0] ^= s[3];
+        s[2] ^= t;
+        s[3] = s[3].rotate_left(11);
+        result
+    }
+
+    /// Fills `bytes` with random bytes.
+    pub fn fill_bytes(&mut self, bytes: &mut [u8]) {
+        for chunk in bytes.chunks_mut(4) {
+            let random = self.next_u32().to_le_bytes();
+            chunk.copy_from_slice(&random[..chunk.len()]);
+        }
+    }
+}
+
+#[cfg(feature = "rand_core")]
+impl rand_core::RngCore for Rng {
+    fn next_u32(&mut self) -> u32 {
+        Rng::next_u32(self)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        rand_core::impls::next_u64_via_u32(self)
+    }
+
+    fn fill_bytes(&mut self, bytes: &mut [u8]) {
+        Rng::fill_bytes(self, bytes)
+    }
+
+    fn try_fill_bytes(&mut self, bytes: &mut [u8]) -> Result<(), rand_core::Error> {
+        Rng::fill_bytes(self, bytes);
+        Ok(())
+    }
+}
@@ -0,0 +1,49 @@
+//! Notification when the 32-bit microsecond counter wraps around.
+//!
+//! [`micros`](crate::micros) wraps after about 71 minutes.  Applications
+//! that keep `u32` timestamps can register a callback with [`on_rollover`]
+//! or poll [`take_rollover`], and use [`wraps`] to tell how many times it
+//! has wrapped, so they can still reason about absolute elapsed time.
+
+use core::cell;
+
+use avr_device::interrupt::{CriticalSection, Mutex};
+
+static CALLBACK: Mutex<cell::Cell<Option<fn(u32)>>> = Mutex::new(cell::Cell::new(None));
+static PENDING: Mutex<cell::Cell<bool>> = Mutex::new(cell::Cell::new(false));
+
+/// Registers `callback` to run each time the counter wraps, with the wrap
+/// count, or removes it if `None`.
+///
+/// The callback runs from the timer ISR with interrupts disabled, so it must
+/// be kept short.  The counter wraps in the ISR, up to a timer period after
+/// [`micros`](crate::micros) already started interpolating from zero.  With
+/// the `fast-isr` feature it runs when the counters are next read instead.
+pub fn on_rollover(callback: Option<fn(u32)>) {
+    avr_device::interrupt::free(|cs| CALLBACK.borrow(cs).set(callback))
+}
+
+/// Returns `true` if the counter has wrapped since the last call.
+pub fn take_rollover() -> bool {
+    avr_device::interrupt::free(|cs| PENDING.borrow(cs).replace(false))
+}
+
+/// Returns how often the counter has wrapped since
+/// [`micros_init`](crate::micros_init), i.e. the upper half of
+/// [`micros64`](crate::micros64) as last advanced.
+pub fn wraps() -> u32 {
+    avr_device::interrupt::free(crate::micros_wraps)
+}
+
+/// Records a wrap, the `count`th.  Called with interrupts disabled.
+pub(crate) fn wrapped(cs: &CriticalSection, count: u32) {
+    PENDING.borrow(cs).set(true);
+    if let Some(callback) = CALLBACK.borrow(cs).get() {
+        callback(count);
+    }
+}
+
+/// Forgets a wrap not yet taken, e.g. when the counters restart.
+pub(crate) fn clear(cs: &CriticalSection) {
+    PENDING.borrow(cs).set(false);
+}
@@ -0,0 +1,113 @@
+//! Real-time clock on a 32.768 kHz watch crystal.
+//!
+//! Timer2 can be clocked asynchronously from a watch crystal on TOSC1/TOSC2,
+//! independently of the CPU clock.  With a prescaler of 128 it overflows
+//! exactly once per second, which drives a seconds counter that is as
+//! accurate as the watch crystal and keeps running in power-save sleep.  It
+//! runs alongside the high-resolution counter on the main time base timer.
+//!
+//! In asynchronous mode, writes to the Timer2 registers are synchronized to
+//! the slow crystal clock and take a couple of its cycles to complete.  The
+//! functions here wait for the update-busy flags in ASSR as needed.
+
+#[cfg(any(feature = "atmega32u4", feature = "attiny85", feature = "atmega4809"))]
+compile_error!("the `rtc` feature requires a device with an asynchronous Timer2");
+
+#[cfg(feature = "timer2")]
+compile_error!("the `rtc` and `timer2` features are mutually exclusive");
+
+use core::cell;
+
+use avr_device::interrupt::Mutex;
+
+/// The timer peripheral driven by the watch crystal.
+pub type RtcTimer = crate::pac::TC2;
+
+// ASSR bits.
+const AS2: u8 = 1 << 5;
+const UPDATE_BUSY: u8 = 0b1_1111;
+
+// Clock select for clkT2S/128: 32768 / 128 / 256 = 1 overflow per second.
+const PRESCALE_128: u8 = 0b101;
+
+/// The number of counts of the timer per second.
+pub const COUNTS_PER_SEC: u32 = 256;
+
+static SECONDS: Mutex<cell::Cell<u32>> = Mutex::new(cell::Cell::new(0));
+
+/// Switches Timer2 to the watch crystal and starts counting seconds from
+/// zero.
+///
+/// The crystal takes up to a second to stabilize after power-up, which
+/// the datasheet recommends waiting out before calling this.
+pub fn rtc_init(tc2: &RtcTimer) {
+    // The interrupts must be disabled while changing the clock source, as
+    // the registers may be corrupted by the switch.
+    tc2.timsk2.write(|w| unsafe { w.bits(0) });
+    tc2.assr.write(|w| unsafe { w.bits(AS2) });
+    tc2.tcnt2.write(|w| unsafe { w.bits(0) });
+    tc2.tccr2a.write(|w| unsafe { w.bits(0) });
+    tc2.tccr2b.write(|w| unsafe { w.bits(PRESCALE_128) });
+    wait_for_update(tc2);
+    // Writing ones clears any flags set during the switch.
+    tc2.tifr2.write(|w| unsafe { w.bits(0b111) });
+    avr_device::interrupt::free(|cs| SECONDS.borrow(cs).set(0));
+    tc2.timsk2.write(|w| w.toie2().set_bit());
+}
+
+/// Sets the seconds counter.
+pub fn set_seconds(seconds: u32) {
+    avr_device::interrupt::free(|cs| SECONDS.borrow(cs).set(seconds));
+}
+
+/// Returns the number of seconds counted since [`rtc_init`] or the last
+/// [`set_seconds`].
+pub fn seconds() -> u32 {
+    avr_device::interrupt::free(|cs| SECONDS.borrow(cs).get())
+}
+
+/// Returns the seconds and the fraction of the current second in units of
+/// 1/[`COUNTS_PER_SEC`].
+pub fn now() -> (u32, u8) {
+    let tc2 = regs();
+    avr_device::interrupt::free(|cs| {
+        let seconds = SECONDS.borrow(cs).get();
+        let counts = tc2.tcnt2.read().bits();
+        if tc2.tifr2.read().tov2().bit_is_set() {
+            // The overflow hasn't been counted yet.
+            (seconds.wrapping_add(1), tc2.tcnt2.read().bits())
+        } else {
+            (seconds, counts)
+        }
+    })
+}
+
+/// Waits until a full cycle of the crystal clock has passed since the last
+/// wakeup.
+///
+/// Call this before entering power-save sleep.  If the CPU goes back to
+/// sleep too soon after the Timer2 interrupt woke it, the interrupt logic
+/// hasn't been reset yet and the next wakeup is missed.  Writing a register
+/// and waiting for it to synchronize guarantees the cycle has passed.
+pub fn sync() {
+    let tc2 = regs();
+    tc2.tccr2b.write(|w| unsafe { w.bits(PRESCALE_128) });
+    wait_for_update(tc2);
+}
+
+fn regs() -> &'static crate::pac::tc2::RegisterBlock {
+    unsafe { &*RtcTimer::ptr() }
+}
+
+fn wait_for_update(tc2: &crate::pac::tc2::RegisterBlock) {
+    while tc2.assr.read().bits() & UPDATE_BUSY != 0 {}
+}
+
+isr! {
+    fn TIMER2_OVF() {
+        avr_device::interrupt::free(|cs| {
+            let cell = SECONDS.borrow(cs);
+            cell.set(cell.get().wrapping_add(1));
+        })
+    }
+}
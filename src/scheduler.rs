@@ -0,0 +1,95 @@
+//! A cooperative scheduler for periodic tasks in the main loop.
+//!
+//! A [`Scheduler`] runs a fixed number of tasks at regular intervals, but
+//! only when the main loop calls [`Scheduler::poll`]: nothing runs from the
+//! timer ISR, so a task is as late as the loop is slow, and tasks never
+//! preempt each other or the rest of the loop.  Tasks are rescheduled
+//! relative to when they were due rather than when they actually ran, so
+//! their period doesn't drift even if `poll` is called late.
+//!
+//! Work that has to run on time regardless of the main loop belongs in an
+//! [`alarm`](crate::alarm) with the `isr-alarms` feature, which fires from
+//! the timer ISR; a periodic one can set the next alarm from its callback.
+
+use crate::time::{time_after, Duration, Instant};
+
+/// Errors returned when adding a task.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// All task slots of the scheduler are in use.
+    Full,
+    /// The period is zero.
+    ZeroPeriod,
+}
+
+/// Identifies a scheduled task so that it can be cancelled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TaskId(u8);
+
+#[derive(Clone, Copy)]
+struct Task {
+    next: Instant,
+    period: Duration,
+    run: fn(),
+}
+
+/// Runs up to `N` tasks periodically when polled from the main loop.
+pub struct Scheduler<const N: usize> {
+    tasks: [Option<Task>; N],
+}
+
+impl<const N: usize> Scheduler<N> {
+    /// Creates a scheduler without any tasks.
+    pub const fn new() -> Self {
+        Scheduler { tasks: [None; N] }
+    }
+
+    /// Schedules `task` to run every `period`, starting one period from now.
+    pub fn every(&mut self, period: Duration, task: fn()) -> Result<TaskId, Error> {
+        if period.as_micros() == 0 {
+            return Err(Error::ZeroPeriod);
+        }
+        let index = self
+            .tasks
+            .iter()
+            .position(Option::is_none)
+            .ok_or(Error::Full)?;
+        self.tasks[index] = Some(Task {
+            next: crate::now() + period,
+            period,
+            run: task,
+        });
+        Ok(TaskId(index as u8))
+    }
+
+    /// Removes a task from the scheduler.
+    pub fn cancel(&mut self, id: TaskId) {
+        self.tasks[id.0 as usize] = None;
+    }
+
+    /// Runs every task that is due.
+    ///
+    /// A task that has fallen behind by more than one period runs only once,
+    /// keeping its original phase rather than running again to catch up.
+    pub fn poll(&mut self) {
+        for task in self.tasks.iter_mut().flatten() {
+            let now = crate::now();
+            if time_after(task.next.as_micros(), now.as_micros()) {
+                continue;
+            }
+
+            // Skip every period that has passed in one step.
+            let period = task.period.as_micros();
+            let late = now.as_micros().wrapping_sub(task.next.as_micros());
+            let skip = (late / period + 1).wrapping_mul(period);
+            task.next = Instant::from_micros(task.next.as_micros().wrapping_add(skip));
+            (task.run)();
+        }
+    }
+}
+
+impl<const N: usize> Default for Scheduler<N> {
+    fn default() -> Self {
+        Scheduler::new()
+    }
+}
@@ -0,0 +1,107 @@
+//! A boot-time check that the time base is running as configured.
+//!
+//! [`selftest`] checks that the timer ISR is firing, that a 100 ms busy
+//! delay and 100 ms of timer time agree, see [`delay::check_clock`], and
+//! that the per-period increments the ISR adds up reproduce the timer
+//! period exactly.  It takes 100 ms plus two timer periods, and is meant
+//! to be run once after [`micros_init`](crate::micros_init) and enabling
+//! interrupts, with the result written to serial before entering the main
+//! loop:
+//!
+//! ```ignore
+//! let result = selftest::selftest();
+//! result.report(&mut serial).ok();
+//! ```
+
+use embedded_hal::serial::Write;
+
+use crate::delay::{self, delay_cycles, ClockCheck};
+use crate::telemetry::{write_decimal, write_str};
+use crate::{raw, CLOCK_HZ, CLOCK_MHZ};
+
+/// How far the busy delay and the timer may disagree, in parts per
+/// million.  The timer ISR stretches the delay by a few hundred.
+pub const TOLERANCE_PPM: i32 = 10_000;
+
+/// The outcome of [`selftest`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SelfTest {
+    /// Whether the timer ISR ran while waiting for two periods.
+    pub isr_firing: bool,
+    /// The busy delay timed against the time base.
+    pub clock: ClockCheck,
+    /// Whether the increments add up to the timer period, including the
+    /// fraction of a microsecond carried between periods.
+    pub math_exact: bool,
+    /// Whether the period is a whole number of microseconds, so no
+    /// fraction needs carrying.  Informational only.
+    pub whole_micros: bool,
+}
+
+impl SelfTest {
+    /// Returns `true` if the clock is within [`TOLERANCE_PPM`].
+    pub fn clock_ok(&self) -> bool {
+        self.clock.error_ppm().abs() <= TOLERANCE_PPM
+    }
+
+    /// Returns `true` if every check passed.
+    pub fn passed(&self) -> bool {
+        self.isr_firing && self.clock_ok() && self.math_exact
+    }
+
+    /// Writes a line per check and a summary, e.g. `clock: pass (312 ppm)`
+    /// and `selftest: pass`, each ending in CRLF.
+    pub fn report<W: Write<u8>>(&self, writer: &mut W) -> Result<(), W::Error> {
+        write_str(writer, "isr: ")?;
+        write_result(writer, self.isr_firing)?;
+        write_str(writer, "\r\nclock: ")?;
+        write_result(writer, self.clock_ok())?;
+        write_str(writer, " (")?;
+        write_decimal(writer, self.clock.error_ppm() as i64)?;
+        write_str(writer, " ppm)\r\nmath: ")?;
+        write_result(writer, self.math_exact)?;
+        if !self.whole_micros {
+            write_str(writer, " (fractional period)")?;
+        }
+        write_str(writer, "\r\nselftest: ")?;
+        write_result(writer, self.passed())?;
+        write_str(writer, "\r\n")
+    }
+}
+
+/// Runs the checks.  The time base must be running with interrupts
+/// enabled, otherwise the ISR check fails.
+pub fn selftest() -> SelfTest {
+    let settings = avr_device::interrupt::free(|cs| crate::SETTINGS.borrow(cs).get());
+    let ppm = crate::get_ppm_correction();
+
+    let ticks = raw::ticks();
+    delay_cycles(2 * settings.micros_increment.max(1) * CLOCK_MHZ);
+    let isr_firing = raw::ticks() != ticks;
+
+    let clock = delay::check_clock();
+
+    // The ISR adds `micros_increment` whole microseconds and
+    // `micros_fract_increment` millionths of a cycle per period, which must
+    // come to exactly the period's cycles, corrected by `ppm`, and the
+    // millisecond counter must split the same microseconds.
+    let cycles = settings.prescaler as u64 * settings.counts as u64;
+    let scaled = cycles * (1_000_000 - ppm as i64) as u64;
+    let rebuilt =
+        settings.micros_increment as u64 * CLOCK_HZ as u64 + settings.micros_fract_increment as u64;
+    let millis = settings.millis_increment * 1000 + settings.millis_fract_increment as u32;
+    let math_exact = rebuilt == scaled
+        && (settings.micros_fract_increment as u64) < CLOCK_HZ as u64
+        && millis == settings.micros_increment;
+
+    SelfTest {
+        isr_firing,
+        clock,
+        math_exact,
+        whole_micros: settings.micros_fract_increment == 0,
+    }
+}
+
+fn write_result<W: Write<u8>>(writer: &mut W, pass: bool) -> Result<(), W::Error> {
+    write_str(writer, if pass { "pass" } else { "FAIL" })
+}
@@ -0,0 +1,237 @@
+//! Interrupt-driven serial reception with arrival timestamps.
+//!
+//! The receive complete ISR moves each byte out of the USART as soon as it
+//! arrives and queues it with the time in an [`EventQueue`], so the
+//! timestamp is when the byte's stop bit was received rather than when the
+//! main loop got around to reading it, and bytes aren't lost while the main
+//! loop is busy.
+//!
+//! [`record_gaps`] additionally has the ISR gather statistics of the time
+//! between consecutive bytes.  A host that writes a block at once through a
+//! USB-serial adapter shows up as gaps of one character time, 174 us at
+//! 57600 baud, separated by the adapter's latency timer, often 1 to 16 ms,
+//! so these characterize the host driver and any buffering on the way.
+
+use core::cell;
+
+use avr_device::interrupt::{CriticalSection, Mutex};
+use embedded_hal::serial::Write;
+
+use crate::event_queue::EventQueue;
+use crate::telemetry::{write_decimal, write_str};
+use crate::time::{Duration, Instant};
+pub use crate::usart::Usart;
+use crate::usart::{self, Registers, DOR0, FE0, RXCIE0, RXEN0};
+
+/// The number of bytes that can be queued before new ones are dropped.
+pub const QUEUE_LEN: usize = 32;
+
+static BYTES: EventQueue<u8, QUEUE_LEN> = EventQueue::new();
+
+static ERRORS: Mutex<cell::Cell<u16>> = Mutex::new(cell::Cell::new(0));
+
+/// The number of buckets in the histogram of gaps between bytes.
+///
+/// Bucket 0 counts gaps under 1 us, and bucket `i` those of `2^(i - 1)` up
+/// to `2^i - 1` us, with the last bucket taking all gaps of 16.384 ms or
+/// more.
+pub const GAP_BUCKETS: usize = 16;
+
+#[derive(Clone, Copy)]
+struct GapState {
+    enabled: bool,
+    last: Option<Instant>,
+    gaps: Gaps,
+}
+
+const NO_GAPS: Gaps = Gaps {
+    count: 0,
+    min: Duration::from_micros(u32::MAX),
+    max: Duration::from_micros(0),
+    total_micros: 0,
+    histogram: [0; GAP_BUCKETS],
+};
+
+static GAPS: Mutex<cell::Cell<GapState>> = Mutex::new(cell::Cell::new(GapState {
+    enabled: false,
+    last: None,
+    gaps: NO_GAPS,
+}));
+
+/// Statistics of the time between consecutive received bytes, gathered
+/// since [`record_gaps`] or [`reset_gaps`] was called.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Gaps {
+    /// The number of gaps measured, one less than the bytes received.
+    pub count: u32,
+    /// The shortest gap, or `u32::MAX` microseconds if there was none.
+    pub min: Duration,
+    /// The longest gap.
+    pub max: Duration,
+    total_micros: u64,
+    /// The number of gaps per bucket, see [`GAP_BUCKETS`].  Buckets
+    /// saturate rather than wrap.
+    pub histogram: [u16; GAP_BUCKETS],
+}
+
+impl Gaps {
+    /// Returns the mean gap, or zero if there was none.
+    pub fn mean(&self) -> Duration {
+        match self.count {
+            0 => Duration::from_micros(0),
+            count => Duration::from_micros((self.total_micros / count as u64) as u32),
+        }
+    }
+
+    /// Returns the shortest gap that falls into `bucket`.
+    pub const fn bucket_start(bucket: usize) -> Duration {
+        if bucket == 0 {
+            Duration::from_micros(0)
+        } else {
+            Duration::from_micros(1 << (bucket - 1))
+        }
+    }
+
+    /// Writes the statistics, then the non-empty buckets of the histogram
+    /// as lines of their shortest gap and count, e.g. `>= 128 us: 57`.
+    pub fn report<W: Write<u8>>(&self, writer: &mut W) -> Result<(), W::Error> {
+        write_decimal(writer, self.count as i64)?;
+        if self.count > 0 {
+            write_str(writer, " gaps, min ")?;
+            write_decimal(writer, self.min.as_micros() as i64)?;
+            write_str(writer, " us, mean ")?;
+            write_decimal(writer, self.mean().as_micros() as i64)?;
+            write_str(writer, " us, max ")?;
+            write_decimal(writer, self.max.as_micros() as i64)?;
+            write_str(writer, " us\r\n")?;
+        } else {
+            write_str(writer, " gaps\r\n")?;
+        }
+        for (bucket, &count) in self.histogram.iter().enumerate() {
+            if count == 0 {
+                continue;
+            }
+            write_str(writer, ">= ")?;
+            write_decimal(writer, Gaps::bucket_start(bucket).as_micros() as i64)?;
+            write_str(writer, " us: ")?;
+            write_decimal(writer, count as i64)?;
+            write_str(writer, "\r\n")?;
+        }
+        Ok(())
+    }
+}
+
+/// Enables the receiver at `baud` bits per second, 8N1.  The transmitter,
+/// if any, is left running at the new rate.
+pub fn start(usart: &Usart, baud: u32) {
+    BYTES.clear();
+    avr_device::interrupt::free(|cs| {
+        ERRORS.borrow(cs).set(0);
+        usart::configure(usart, baud);
+        usart::modify_control(usart, RXEN0 | RXCIE0, 0);
+    })
+}
+
+/// Disables the receiver.  Bytes already queued are kept.
+pub fn stop(usart: &Usart) {
+    usart::modify_control(usart, 0, RXEN0 | RXCIE0);
+}
+
+/// Takes the oldest received byte, along with the time it arrived.
+pub fn read() -> Option<(Instant, u8)> {
+    BYTES.pop()
+}
+
+/// Returns the number of bytes dropped because the queue was full.
+pub fn dropped() -> u16 {
+    BYTES.dropped()
+}
+
+/// Returns the number of bytes received with a framing error or lost to an
+/// overrun of the USART, which are discarded.
+pub fn errors() -> u16 {
+    avr_device::interrupt::free(|cs| ERRORS.borrow(cs).get())
+}
+
+/// Starts or stops gathering statistics of the gaps between received
+/// bytes.  Starting clears the statistics.
+pub fn record_gaps(enable: bool) {
+    avr_device::interrupt::free(|cs| {
+        GAPS.borrow(cs).set(GapState {
+            enabled: enable,
+            last: None,
+            gaps: NO_GAPS,
+        })
+    })
+}
+
+/// Returns the statistics of the gaps between received bytes so far.
+pub fn gaps() -> Gaps {
+    avr_device::interrupt::free(|cs| GAPS.borrow(cs).get().gaps)
+}
+
+/// Clears the statistics of the gaps between received bytes.  The next
+/// byte starts a new gap.
+pub fn reset_gaps() {
+    avr_device::interrupt::free(|cs| {
+        let cell = GAPS.borrow(cs);
+        let state = cell.get();
+        cell.set(GapState {
+            last: None,
+            gaps: NO_GAPS,
+            ..state
+        })
+    })
+}
+
+fn record_gap(cs: &CriticalSection, now: Instant) {
+    let cell = GAPS.borrow(cs);
+    let mut state = cell.get();
+    if !state.enabled {
+        return;
+    }
+    if let Some(last) = state.last {
+        let gap = now - last;
+        let gaps = &mut state.gaps;
+        gaps.count = gaps.count.wrapping_add(1);
+        gaps.min = gaps.min.min(gap);
+        gaps.max = gaps.max.max(gap);
+        gaps.total_micros += gap.as_micros() as u64;
+        let bucket = ((32 - gap.as_micros().leading_zeros()) as usize).min(GAP_BUCKETS - 1);
+        gaps.histogram[bucket] = gaps.histogram[bucket].saturating_add(1);
+    }
+    state.last = Some(now);
+    cell.set(state);
+}
+
+fn on_receive(usart: &Registers) {
+    avr_device::interrupt::free(|cs| {
+        let now = crate::now_in(cs);
+        #[cfg(feature = "entropy")]
+        crate::entropy::mix_in(cs);
+        // The status must be read before the data register.
+        let status = usart.ucsr0a.read().bits();
+        let byte = usart.udr0.read().bits();
+        record_gap(cs, now);
+        if status & (FE0 | DOR0) != 0 {
+            let errors = ERRORS.borrow(cs);
+            errors.set(errors.get().wrapping_add(1));
+        } else {
+            BYTES.push_in(cs, now, byte);
+        }
+    })
+}
+
+#[cfg(feature = "atmega328p")]
+isr! {
+    fn USART_RX() {
+        on_receive(unsafe { &*Usart::ptr() })
+    }
+}
+
+#[cfg(feature = "atmega2560")]
+isr! {
+    fn USART0_RX() {
+        on_receive(unsafe { &*Usart::ptr() })
+    }
+}
@@ -0,0 +1,197 @@
+//! Interrupt-driven serial transmission.
+//!
+//! Writing to a blocking serial port spins for a whole byte time, about
+//! 170 us at 57600 baud, for every character, which throws off the timing
+//! of the main loop.  [`SerialTx`] instead copies the bytes into a ring
+//! buffer and returns, and the data register empty (UDRE) interrupt feeds
+//! them to the USART in the background.  Only when the buffer is full does
+//! a write wait for space.
+//!
+//! [`SerialTx`] implements `ufmt::uWrite`, so `uwriteln!` works as with the
+//! HAL's serial port, and the `embedded-hal` serial `Write` trait.
+
+use core::cell;
+use core::convert::Infallible;
+
+use avr_device::interrupt::{CriticalSection, Mutex};
+
+pub use crate::usart::Usart;
+use crate::usart::{self, Registers, TXC0, TXEN0, U2X0, UDRE0, UDRIE0};
+
+/// The number of bytes that can be buffered.
+pub const BUFFER_LEN: usize = 64;
+
+static BUFFER: Mutex<cell::Cell<[u8; BUFFER_LEN]>> = Mutex::new(cell::Cell::new([0; BUFFER_LEN]));
+static HEAD: Mutex<cell::Cell<u8>> = Mutex::new(cell::Cell::new(0));
+static LEN: Mutex<cell::Cell<u8>> = Mutex::new(cell::Cell::new(0));
+
+// Whether a byte has been sent, since the transmit complete flag is only
+// set after the first one.
+static WRITTEN: Mutex<cell::Cell<bool>> = Mutex::new(cell::Cell::new(false));
+
+/// A buffered, interrupt-driven serial transmitter.
+pub struct SerialTx {
+    usart: Usart,
+}
+
+impl SerialTx {
+    /// Enables the transmitter at `baud` bits per second, 8N1.
+    ///
+    /// Interrupts must be enabled for buffered bytes to be sent.  The
+    /// receiver, if started with [`serial_rx::start`], is left running at
+    /// the new rate.
+    ///
+    /// [`serial_rx::start`]: crate::serial_rx::start
+    pub fn new(usart: Usart, baud: u32) -> Self {
+        avr_device::interrupt::free(|cs| {
+            HEAD.borrow(cs).set(0);
+            LEN.borrow(cs).set(0);
+            WRITTEN.borrow(cs).set(false);
+            usart::configure(&usart, baud);
+            usart::modify_control(&usart, TXEN0, UDRIE0);
+        });
+        SerialTx { usart }
+    }
+
+    /// Queues a byte, waiting for space if the buffer is full.
+    pub fn write_byte(&mut self, byte: u8) {
+        while !self.try_write_byte(byte) {}
+    }
+
+    /// Queues all of `bytes`.
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.write_byte(byte);
+        }
+    }
+
+    /// Queues a byte, or returns `false` if the buffer is full.
+    pub fn try_write_byte(&mut self, byte: u8) -> bool {
+        avr_device::interrupt::free(|cs| {
+            let len = LEN.borrow(cs);
+            if len.get() as usize == BUFFER_LEN {
+                self.poll(cs);
+                return false;
+            }
+            let tail = (HEAD.borrow(cs).get() as usize + len.get() as usize) % BUFFER_LEN;
+            buffer(cs)[tail].set(byte);
+            len.set(len.get() + 1);
+            usart::modify_control(&self.usart, UDRIE0, 0);
+            true
+        })
+    }
+
+    /// Returns the number of bytes waiting to be sent.
+    pub fn pending(&self) -> usize {
+        avr_device::interrupt::free(|cs| LEN.borrow(cs).get() as usize)
+    }
+
+    /// Waits until every queued byte has been sent, including the stop bit
+    /// of the last one.
+    pub fn flush(&mut self) {
+        while !self.is_idle() {
+            avr_device::interrupt::free(|cs| self.poll(cs));
+        }
+    }
+
+    /// Stops the transmitter and releases the USART, discarding any bytes
+    /// not yet sent.
+    pub fn release(self) -> Usart {
+        usart::modify_control(&self.usart, 0, TXEN0 | UDRIE0);
+        self.usart
+    }
+
+    // Sends the next byte if the USART is ready, so that waiting for space
+    // makes progress even if called with interrupts disabled.
+    fn poll(&self, cs: &CriticalSection) {
+        if LEN.borrow(cs).get() > 0 && self.usart.ucsr0a.read().bits() & UDRE0 != 0 {
+            send_next(cs, &self.usart);
+        }
+    }
+
+    fn is_idle(&self) -> bool {
+        avr_device::interrupt::free(|cs| {
+            LEN.borrow(cs).get() == 0
+                && (!WRITTEN.borrow(cs).get() || self.usart.ucsr0a.read().bits() & TXC0 != 0)
+        })
+    }
+}
+
+impl ufmt_write::uWrite for SerialTx {
+    type Error = Infallible;
+
+    fn write_str(&mut self, s: &str) -> Result<(), Infallible> {
+        self.write_bytes(s.as_bytes());
+        Ok(())
+    }
+}
+
+impl embedded_hal::serial::Write<u8> for SerialTx {
+    type Error = Infallible;
+
+    fn write(&mut self, byte: u8) -> nb::Result<(), Infallible> {
+        if self.try_write_byte(byte) {
+            Ok(())
+        } else {
+            Err(nb::Error::WouldBlock)
+        }
+    }
+
+    fn flush(&mut self) -> nb::Result<(), Infallible> {
+        if self.is_idle() {
+            Ok(())
+        } else {
+            Err(nb::Error::WouldBlock)
+        }
+    }
+}
+
+fn buffer(cs: &CriticalSection) -> &[cell::Cell<u8>] {
+    let buffer: &cell::Cell<[u8]> = BUFFER.borrow(cs);
+    buffer.as_slice_of_cells()
+}
+
+/// Moves the next buffered byte to the data register, or disables the
+/// interrupt once the buffer is empty.
+fn send_next(cs: &CriticalSection, usart: &Registers) {
+    let len = LEN.borrow(cs);
+    if len.get() == 0 {
+        usart::modify_control(usart, 0, UDRIE0);
+        return;
+    }
+    let head = HEAD.borrow(cs);
+    let byte = buffer(cs)[head.get() as usize].get();
+    head.set(((head.get() as usize + 1) % BUFFER_LEN) as u8);
+    len.set(len.get() - 1);
+    WRITTEN.borrow(cs).set(true);
+    // Clear the transmit complete flag so `flush` waits for this byte.
+    usart.ucsr0a.write(|w| unsafe { w.bits(U2X0 | TXC0) });
+    usart.udr0.write(|w| unsafe { w.bits(byte) });
+}
+
+/// Sends every buffered byte by polling, e.g. from the panic handler.
+#[cfg(feature = "panic-serial")]
+pub(crate) fn drain_in(cs: &CriticalSection, usart: &Registers) {
+    while LEN.borrow(cs).get() > 0 {
+        while usart.ucsr0a.read().bits() & UDRE0 == 0 {}
+        send_next(cs, usart);
+    }
+}
+
+fn on_data_register_empty() {
+    avr_device::interrupt::free(|cs| send_next(cs, unsafe { &*Usart::ptr() }))
+}
+
+#[cfg(feature = "atmega328p")]
+isr! {
+    fn USART_UDRE() {
+        on_data_register_empty()
+    }
+}
+
+#[cfg(feature = "atmega2560")]
+isr! {
+    fn USART0_UDRE() {
+        on_data_register_empty()
+    }
+}
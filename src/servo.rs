@@ -0,0 +1,207 @@
+//! Hobby servos driven from Timer1, like Arduino's `Servo` library.
+//!
+//! Up to [`MAX_SERVOS`] servos share Timer1.  Their pulses are generated one
+//! after another within each 20 ms frame: the compare match ISR ends the
+//! pulse of one servo, starts the next and sets OCR1A to when it must end.
+//! The timer keeps counting freely otherwise, so the time base can run on
+//! Timer0 or Timer2 alongside it.
+//!
+//! Each servo's pin is driven through a callback setting its level, since
+//! the ISR can't own a HAL pin.
+
+#[cfg(any(feature = "attiny85", feature = "atmega4809"))]
+compile_error!("the `servo` feature requires a device with Timer1");
+
+#[cfg(any(
+    feature = "timer1",
+    feature = "input-capture",
+    feature = "freq-counter"
+))]
+compile_error!("the `servo` feature needs Timer1 to itself");
+
+use core::cell;
+
+use avr_device::interrupt::Mutex;
+
+use crate::CLOCK_MHZ;
+
+/// The timer peripheral generating the pulses.
+pub type ServoTimer = crate::pac::TC1;
+
+/// The number of servos that can be attached at once.
+pub const MAX_SERVOS: usize = 12;
+
+/// The shortest pulse accepted by [`Servo::write_micros`].
+pub const MIN_PULSE_MICROS: u16 = 500;
+
+/// The longest pulse accepted by [`Servo::write_micros`].
+pub const MAX_PULSE_MICROS: u16 = 2500;
+
+// The pulse length for a centered servo.
+const CENTER_PULSE_MICROS: u16 = 1500;
+
+// The length of a frame, after which the sequence starts over.
+const REFRESH_MICROS: u32 = 20_000;
+
+// The timer runs at the CPU clock divided by 8.
+const PRESCALE_8: u8 = 0b010;
+const OCIE1A: u8 = 1 << 1;
+const OCF1A: u8 = 1 << 1;
+const TOV1: u8 = 1 << 0;
+
+// How long the wait for the end of the frame lasts at least, if the pulses
+// took up all of it.
+const MIN_GAP_MICROS: u32 = 4;
+
+/// Errors returned when attaching a servo.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// All [`MAX_SERVOS`] channels are in use.
+    Full,
+}
+
+#[derive(Clone, Copy)]
+struct Channel {
+    set_pin: fn(bool),
+    ticks: u16,
+}
+
+static CHANNELS: Mutex<cell::Cell<[Option<Channel>; MAX_SERVOS]>> =
+    Mutex::new(cell::Cell::new([None; MAX_SERVOS]));
+
+// The channel whose pulse is being generated, or `MAX_SERVOS` while waiting
+// for the end of the frame.
+static CURRENT: Mutex<cell::Cell<u8>> = Mutex::new(cell::Cell::new(MAX_SERVOS as u8));
+
+/// Starts Timer1 generating servo frames.
+pub fn servo_init(tc1: &ServoTimer) {
+    avr_device::interrupt::free(|cs| {
+        CURRENT.borrow(cs).set(MAX_SERVOS as u8);
+        tc1.tccr1a.write(|w| unsafe { w.bits(0) });
+        tc1.tcnt1.write(|w| unsafe { w.bits(0) });
+        tc1.ocr1a
+            .write(|w| unsafe { w.bits(micros_to_ticks(REFRESH_MICROS)) });
+        tc1.tccr1b.write(|w| unsafe { w.bits(PRESCALE_8) });
+        tc1.tifr1.write(|w| unsafe { w.bits(OCF1A | TOV1) });
+        tc1.timsk1.write(|w| unsafe { w.bits(OCIE1A) });
+    });
+}
+
+/// A servo attached to one of the channels.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Servo(u8);
+
+impl Servo {
+    /// Attaches a servo whose pin is set by `set_pin`, centered.
+    ///
+    /// The pin must already be configured as an output.
+    pub fn attach(set_pin: fn(bool)) -> Result<Servo, Error> {
+        avr_device::interrupt::free(|cs| {
+            let channels = channels(cs);
+            let index = channels
+                .iter()
+                .position(|channel| channel.get().is_none())
+                .ok_or(Error::Full)?;
+            channels[index].set(Some(Channel {
+                set_pin,
+                ticks: micros_to_ticks(CENTER_PULSE_MICROS as u32),
+            }));
+            Ok(Servo(index as u8))
+        })
+    }
+
+    /// Sets the pulse length in microseconds, clamped to
+    /// [`MIN_PULSE_MICROS`]..=[`MAX_PULSE_MICROS`].  It takes effect from
+    /// the next frame.
+    pub fn write_micros(&mut self, micros: u16) {
+        let micros = micros.max(MIN_PULSE_MICROS).min(MAX_PULSE_MICROS);
+        let ticks = micros_to_ticks(micros as u32);
+        avr_device::interrupt::free(|cs| {
+            let channel = &channels(cs)[self.0 as usize];
+            if let Some(mut c) = channel.get() {
+                c.ticks = ticks;
+                channel.set(Some(c));
+            }
+        })
+    }
+
+    /// Sets the angle in degrees from 0 to 180, mapped to pulses of 1 to
+    /// 2 ms.
+    pub fn write_angle(&mut self, degrees: u8) {
+        let degrees = degrees.min(180) as u16;
+        self.write_micros(1000 + degrees * 1000 / 180);
+    }
+
+    /// Detaches the servo, freeing its channel.
+    pub fn detach(self) {
+        avr_device::interrupt::free(|cs| {
+            let channel = &channels(cs)[self.0 as usize];
+            if let Some(c) = channel.take() {
+                if CURRENT.borrow(cs).get() == self.0 {
+                    (c.set_pin)(false);
+                }
+            }
+        })
+    }
+}
+
+fn channels(cs: &avr_device::interrupt::CriticalSection) -> &[cell::Cell<Option<Channel>>] {
+    let channels: &cell::Cell<[Option<Channel>]> = CHANNELS.borrow(cs);
+    channels.as_slice_of_cells()
+}
+
+const fn micros_to_ticks(micros: u32) -> u16 {
+    (micros * CLOCK_MHZ / 8) as u16
+}
+
+isr! {
+    fn TIMER1_COMPA() {
+        avr_device::interrupt::free(|cs| {
+            let tc1 = unsafe { &*ServoTimer::ptr() };
+            let channels = channels(cs);
+            let current_cell = CURRENT.borrow(cs);
+            let mut current = current_cell.get() as usize;
+
+            if current == MAX_SERVOS {
+                // A new frame starts.
+                tc1.tcnt1.write(|w| unsafe { w.bits(0) });
+                tc1.tifr1.write(|w| unsafe { w.bits(TOV1) });
+                current = 0;
+            } else {
+                if let Some(channel) = channels[current].get() {
+                    (channel.set_pin)(false);
+                }
+                current += 1;
+            }
+
+            // Skip the unused channels.
+            while current < MAX_SERVOS && channels[current].get().is_none() {
+                current += 1;
+            }
+
+            let now = tc1.tcnt1.read().bits();
+            let next = match channels.get(current).and_then(|c| c.get()) {
+                Some(channel) => {
+                    (channel.set_pin)(true);
+                    now.wrapping_add(channel.ticks)
+                }
+                // Wait out the rest of the frame, leaving a little time
+                // if the pulses took longer than that.  Once the timer has
+                // wrapped, which takes longer than a frame, its count no
+                // longer tells how far into the frame it is.
+                None => {
+                    let end = micros_to_ticks(REFRESH_MICROS);
+                    let gap = micros_to_ticks(MIN_GAP_MICROS);
+                    let wrapped = tc1.tifr1.read().bits() & TOV1 != 0;
+                    if wrapped || now >= end {
+                        now.wrapping_add(gap)
+                    } else {
+                        end.max(now + gap)
+                    }
+                }
+            };
+            tc1.ocr1a.write(|w| unsafe { w.bits(next) });
+            current_cell.set(current as u8);
+        })
+    }
+}
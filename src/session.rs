@@ -0,0 +1,59 @@
+//! Timers that can be zeroed without touching the counters since boot.
+//!
+//! Restarting the global counters with `micros_init` would break every
+//! other consumer of the time base, from timeouts to the uptime log.  A
+//! [`SessionTimer`] instead records where its session started, so it can
+//! be reset at any time, e.g. to track the time since the last command.
+
+use crate::micros64;
+use crate::time::Duration;
+
+/// Measures the time since it was created or last reset.
+///
+/// It keeps a 64-bit start time, so it doesn't wrap around.
+#[derive(Clone, Copy, Debug)]
+pub struct SessionTimer {
+    started_at: u64,
+}
+
+impl SessionTimer {
+    /// Starts a session now.
+    pub fn new() -> Self {
+        SessionTimer {
+            started_at: micros64(),
+        }
+    }
+
+    /// Starts a new session now.
+    pub fn reset(&mut self) {
+        self.started_at = micros64();
+    }
+
+    /// Returns the microseconds since the session started.
+    pub fn micros(&self) -> u64 {
+        micros64() - self.started_at
+    }
+
+    /// Returns the milliseconds since the session started.
+    pub fn millis(&self) -> u64 {
+        self.micros() / 1000
+    }
+
+    /// Returns the time since the session started, saturating after about
+    /// 71 minutes.
+    pub fn elapsed(&self) -> Duration {
+        Duration::from_micros(self.micros().min(u32::MAX as u64) as u32)
+    }
+
+    /// Returns the time since boot at which the session started, in
+    /// microseconds.
+    pub fn started_at(&self) -> u64 {
+        self.started_at
+    }
+}
+
+impl Default for SessionTimer {
+    fn default() -> Self {
+        SessionTimer::new()
+    }
+}
@@ -0,0 +1,96 @@
+//! A hardware square wave at a known fraction of the timer period, for
+//! checking the crystal and configuration with a scope or frequency
+//! counter.
+//!
+//! [`start`] makes the time base timer toggle its OCxA pin at every compare
+//! match: OC0A on D6 for Timer0, OC1A on D9 for Timer1, or OC2A on D11 for
+//! Timer2.  The output therefore completes a cycle every two timer periods,
+//! 500 Hz with the default 1 ms, and runs from the same clock and
+//! prescaler as [`micros`](crate::micros), with no ISR involved.  For
+//! exactly 1 kHz at 16 MHz, configure a 500 us period, e.g.
+//! `micros_init_with(&dp.TC0, TimerConfig::<64, 125>::new())`.
+//!
+//! A frequency counter reading `f` instead of [`frequency_millihertz`]
+//! means the crystal runs `(f / expected - 1) * 10^6` ppm fast, which is
+//! the value to pass to [`set_ppm_correction`](crate::set_ppm_correction).
+//! The correction only changes the counters, not the output.
+//!
+//! The `latency-probe` feature toggles OC0A as well; stopping either stops
+//! the output for both.
+
+use crate::timer::Timer;
+use crate::CLOCK_HZ;
+
+// TCCRnA: toggle OCnA on compare match.  The bit is the same for all three
+// timers.
+const COMNA0: u8 = 1 << 6;
+
+/// Starts toggling the timer's OCxA pin at each compare match, and makes
+/// the pin an output.  The time base must already be running.
+pub fn start(timer: &Timer) {
+    avr_device::interrupt::free(|_| {
+        let (port, bit) = pin();
+        port.modify(|r, w| unsafe { w.bits(r.bits() | bit) });
+        set_toggle(timer, true);
+    })
+}
+
+/// Stops toggling the pin, leaving it at its current level.  The pin stays
+/// an output.
+pub fn stop(timer: &Timer) {
+    avr_device::interrupt::free(|_| set_toggle(timer, false))
+}
+
+/// Returns the frequency of the output in millihertz, e.g. 500000 for the
+/// default 1 ms period, according to the configured clock.
+pub fn frequency_millihertz() -> u32 {
+    let settings = avr_device::interrupt::free(|cs| crate::SETTINGS.borrow(cs).get());
+    let cycles = 2 * settings.prescaler as u64 * settings.counts as u64;
+    (CLOCK_HZ as u64 * 1000 / cycles) as u32
+}
+
+#[cfg(not(any(feature = "timer1", feature = "timer2")))]
+fn pin() -> (&'static crate::pac::portd::DDRD, u8) {
+    // OC0A is PD6.
+    (unsafe { &(*crate::pac::PORTD::ptr()).ddrd }, 1 << 6)
+}
+
+#[cfg(any(feature = "timer1", feature = "timer2"))]
+fn pin() -> (&'static crate::pac::portb::DDRB, u8) {
+    // OC1A is PB1 and OC2A is PB3.
+    let bit = if cfg!(feature = "timer1") {
+        1 << 1
+    } else {
+        1 << 3
+    };
+    (unsafe { &(*crate::pac::PORTB::ptr()).ddrb }, bit)
+}
+
+#[cfg(not(any(feature = "timer1", feature = "timer2")))]
+fn set_toggle(timer: &Timer, enable: bool) {
+    timer
+        .tccr0a
+        .modify(|r, w| unsafe { w.bits(toggled(r.bits(), enable)) });
+}
+
+#[cfg(feature = "timer1")]
+fn set_toggle(timer: &Timer, enable: bool) {
+    timer
+        .tccr1a
+        .modify(|r, w| unsafe { w.bits(toggled(r.bits(), enable)) });
+}
+
+#[cfg(feature = "timer2")]
+fn set_toggle(timer: &Timer, enable: bool) {
+    timer
+        .tccr2a
+        .modify(|r, w| unsafe { w.bits(toggled(r.bits(), enable)) });
+}
+
+fn toggled(tccra: u8, enable: bool) -> u8 {
+    if enable {
+        tccra | COMNA0
+    } else {
+        tccra & !COMNA0
+    }
+}
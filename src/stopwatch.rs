@@ -0,0 +1,162 @@
+//! Stopwatches for measuring elapsed time.
+
+use crate::micros64;
+use crate::time::Duration;
+
+/// A stopwatch that can be stopped and resumed.
+///
+/// Any number of stopwatches can run at once since they only record
+/// timestamps.  Elapsed times longer than a [`Duration`] can represent
+/// (about 71 minutes) saturate instead of wrapping around.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Stopwatch {
+    accumulated: u32,
+    started_at: Option<u64>,
+}
+
+impl Stopwatch {
+    /// Creates a stopped stopwatch with no elapsed time.
+    pub const fn new() -> Self {
+        Stopwatch {
+            accumulated: 0,
+            started_at: None,
+        }
+    }
+
+    /// Creates a stopwatch that is already running.
+    pub fn started() -> Self {
+        let mut stopwatch = Stopwatch::new();
+        stopwatch.start();
+        stopwatch
+    }
+
+    /// Clears the elapsed time and starts the stopwatch.
+    pub fn start(&mut self) {
+        self.accumulated = 0;
+        self.started_at = Some(micros64());
+    }
+
+    /// Stops the stopwatch, keeping the elapsed time.
+    pub fn stop(&mut self) {
+        self.accumulated = self.elapsed_micros();
+        self.started_at = None;
+    }
+
+    /// Continues a stopped stopwatch without clearing the elapsed time.
+    pub fn resume(&mut self) {
+        if self.started_at.is_none() {
+            self.started_at = Some(micros64());
+        }
+    }
+
+    /// Stops the stopwatch and clears the elapsed time.
+    pub fn reset(&mut self) {
+        *self = Stopwatch::new();
+    }
+
+    /// Returns `true` if the stopwatch is running.
+    pub fn is_running(&self) -> bool {
+        self.started_at.is_some()
+    }
+
+    /// Returns the total time the stopwatch has been running.
+    pub fn elapsed(&self) -> Duration {
+        Duration::from_micros(self.elapsed_micros())
+    }
+
+    fn elapsed_micros(&self) -> u32 {
+        match self.started_at {
+            Some(start) => {
+                let running = micros64() - start;
+                let running = if running > u32::MAX as u64 {
+                    u32::MAX
+                } else {
+                    running as u32
+                };
+                self.accumulated.saturating_add(running)
+            }
+            None => self.accumulated,
+        }
+    }
+}
+
+/// A [`Stopwatch`] that also records lap times.
+///
+/// The first `N` laps are kept in a buffer; laps beyond that are still
+/// returned by [`lap`](LapStopwatch::lap) and counted but not stored.
+#[derive(Clone, Copy, Debug)]
+pub struct LapStopwatch<const N: usize> {
+    stopwatch: Stopwatch,
+    last_lap: Duration,
+    laps: [Duration; N],
+    count: usize,
+}
+
+impl<const N: usize> LapStopwatch<N> {
+    /// Creates a stopped stopwatch with no laps.
+    pub const fn new() -> Self {
+        LapStopwatch {
+            stopwatch: Stopwatch::new(),
+            last_lap: Duration::from_micros(0),
+            laps: [Duration::from_micros(0); N],
+            count: 0,
+        }
+    }
+
+    /// Clears the elapsed time and laps and starts the stopwatch.
+    pub fn start(&mut self) {
+        *self = LapStopwatch::new();
+        self.stopwatch.start();
+    }
+
+    /// Stops the stopwatch, keeping the elapsed time and laps.
+    pub fn stop(&mut self) {
+        self.stopwatch.stop();
+    }
+
+    /// Continues a stopped stopwatch.
+    pub fn resume(&mut self) {
+        self.stopwatch.resume();
+    }
+
+    /// Stops the stopwatch and clears the elapsed time and laps.
+    pub fn reset(&mut self) {
+        *self = LapStopwatch::new();
+    }
+
+    /// Returns the total time the stopwatch has been running.
+    pub fn elapsed(&self) -> Duration {
+        self.stopwatch.elapsed()
+    }
+
+    /// Ends the current lap and returns its duration, i.e. the running time
+    /// since the previous lap.
+    pub fn lap(&mut self) -> Duration {
+        let elapsed = self.stopwatch.elapsed();
+        let split = elapsed.saturating_sub(self.last_lap);
+        self.last_lap = elapsed;
+
+        if let Some(slot) = self.laps.get_mut(self.count) {
+            *slot = split;
+        }
+        self.count = self.count.saturating_add(1);
+        split
+    }
+
+    /// Returns the recorded laps, oldest first.
+    pub fn laps(&self) -> &[Duration] {
+        &self.laps[..self.count.min(N)]
+    }
+
+    /// Returns the total number of laps, including any that didn't fit into
+    /// the buffer.
+    pub fn lap_count(&self) -> usize {
+        self.count
+    }
+}
+
+impl<const N: usize> Default for LapStopwatch<N> {
+    fn default() -> Self {
+        LapStopwatch::new()
+    }
+}
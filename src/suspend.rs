@@ -0,0 +1,55 @@
+//! Stopping the time base, e.g. around a timing-sensitive section.
+//!
+//! [`suspend`] stops the hardware timer, so its interrupt can't disturb
+//! bit-banged protocols or wake the CPU, and [`Suspended::resume`] starts
+//! it again from the same count.  The timer can't measure the time it was
+//! stopped for, so the caller passes it in, e.g. from the cycles a section
+//! is known to take or from another clock, and the counters are advanced
+//! by it as if the timer had kept running.
+
+use crate::time::{Duration, Instant};
+use crate::{timer, CLOCK_MHZ};
+
+/// The time base while suspended.  Dropping it leaves the timer stopped.
+pub struct Suspended {
+    at: Instant,
+}
+
+/// Stops the time base.
+///
+/// A compare match already pending is kept, and taken by the ISR once the
+/// timer runs again.
+pub fn suspend() -> Suspended {
+    avr_device::interrupt::free(|cs| {
+        let at = crate::now_in(cs);
+        timer::stop();
+        Suspended { at }
+    })
+}
+
+impl Suspended {
+    /// Returns the time at which the time base was suspended.
+    pub fn at(&self) -> Instant {
+        self.at
+    }
+
+    /// Restarts the time base, advanced by `gap`, and returns how long it
+    /// now counts the suspension as.
+    ///
+    /// The result differs from `gap` only by the rounding to whole timer
+    /// counts.  A `gap` of zero leaves the suspension out of the counters
+    /// entirely, as if time had stood still.
+    pub fn resume(self, gap: Duration) -> Duration {
+        self.resume_after_cycles(gap.as_micros() as u64 * CLOCK_MHZ as u64)
+    }
+
+    /// Restarts the time base, advanced by `cycles` CPU cycles, and returns
+    /// how long it now counts the suspension as.
+    pub fn resume_after_cycles(self, cycles: u64) -> Duration {
+        avr_device::interrupt::free(|cs| {
+            crate::skip_cycles(cs, cycles);
+            timer::start(&crate::SETTINGS.borrow(cs).get());
+            crate::now_in(cs) - self.at
+        })
+    }
+}
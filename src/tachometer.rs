@@ -0,0 +1,96 @@
+//! Rotational speed from pulse timestamps.
+
+use crate::time::{Duration, Instant};
+
+/// Converts the intervals between pulses, e.g. from a hall sensor or a fan's
+/// tach output, into revolutions per minute.
+///
+/// Pulses are reported with [`pulse`](Tachometer::pulse), typically using
+/// timestamps from an edge interrupt or the input capture unit.  The
+/// intervals are smoothed by an exponential moving average, and the speed
+/// reads as zero once no pulse has arrived for the stall timeout.
+#[derive(Clone, Copy, Debug)]
+pub struct Tachometer {
+    pulses_per_rev: u8,
+    smoothing: u8,
+    stall_timeout: Duration,
+    last_pulse: Option<Instant>,
+    interval: Option<u32>,
+}
+
+impl Tachometer {
+    /// Creates a tachometer for a sensor giving `pulses_per_rev` pulses per
+    /// revolution, without smoothing.  A count of zero is taken as one.
+    pub const fn new(pulses_per_rev: u8, stall_timeout: Duration) -> Self {
+        Tachometer {
+            pulses_per_rev: if pulses_per_rev == 0 {
+                1
+            } else {
+                pulses_per_rev
+            },
+            smoothing: 0,
+            stall_timeout,
+            last_pulse: None,
+            interval: None,
+        }
+    }
+
+    /// Sets the smoothing of the intervals.  Each new interval moves the
+    /// average by 1/2^`shift` of the difference, so larger values respond
+    /// more slowly but are steadier.
+    pub const fn with_smoothing(self, shift: u8) -> Self {
+        Tachometer {
+            smoothing: shift,
+            ..self
+        }
+    }
+
+    /// Records a pulse at `at`.
+    pub fn pulse(&mut self, at: Instant) {
+        if let Some(last) = self.last_pulse {
+            let interval = (at - last).as_micros();
+            if interval > self.stall_timeout.as_micros() {
+                // The first pulse after a stall only restarts the timing.
+                self.interval = None;
+            } else {
+                self.interval = Some(match self.interval {
+                    Some(average) => {
+                        let delta = interval as i32 - average as i32;
+                        (average as i32 + (delta >> self.smoothing)) as u32
+                    }
+                    None => interval,
+                });
+            }
+        }
+        self.last_pulse = Some(at);
+    }
+
+    /// Returns the smoothed time between pulses, or `None` if the shaft is
+    /// stalled or not enough pulses have been seen.
+    pub fn interval(&self) -> Option<Duration> {
+        let last = self.last_pulse?;
+        if crate::now() - last > self.stall_timeout {
+            return None;
+        }
+        self.interval.map(Duration::from_micros)
+    }
+
+    /// Returns the speed in revolutions per minute, zero when stalled.
+    pub fn rpm(&self) -> u32 {
+        match self.interval() {
+            Some(interval) if interval.as_micros() > 0 => {
+                let per_rev = interval.as_micros() as u64 * self.pulses_per_rev as u64;
+                (60_000_000 / per_rev) as u32
+            }
+            _ => 0,
+        }
+    }
+
+    /// Returns whether no pulse has arrived within the stall timeout.
+    pub fn is_stalled(&self) -> bool {
+        match self.last_pulse {
+            Some(last) => crate::now() - last > self.stall_timeout,
+            None => true,
+        }
+    }
+}
@@ -0,0 +1,266 @@
+//! Streaming of timestamped values, as compact binary records or CSV.
+//!
+//! Formatting numbers as text takes far longer than sending them, so for
+//! dense event streams records are sent in binary instead.  Each record is
+//! a channel byte, the timestamp in microseconds as a little-endian `u32`,
+//! the payload and the little-endian CRC-16/CCITT-FALSE of everything before
+//! it.  It is encoded with Consistent Overhead Byte Stuffing so it contains
+//! no zero bytes, and followed by a zero byte.  A receiver can therefore
+//! resynchronize at the next zero after a lost byte, and drops the record
+//! the CRC rejects.
+//!
+//! When the data is meant for a spreadsheet rather than a custom receiver,
+//! [`send_value`] can emit CSV lines instead, with the format chosen at
+//! runtime, and [`plot`] prints labelled values for the Arduino IDE's
+//! Serial Plotter.
+
+use embedded_hal::serial::Write;
+
+use crate::time::Instant;
+
+/// The longest payload a record can carry.
+pub const MAX_PAYLOAD: usize = 32;
+
+// Channel, timestamp, payload and CRC.
+const MAX_RECORD: usize = 1 + 4 + MAX_PAYLOAD + 2;
+
+// COBS adds one byte per 254, and one at the start.
+const MAX_ENCODED: usize = MAX_RECORD + MAX_RECORD / 254 + 1;
+
+/// Computes the CRC-16/CCITT-FALSE of `bytes`: polynomial 0x1021, initial
+/// value 0xffff, not reflected.
+pub fn crc16(bytes: &[u8]) -> u16 {
+    bytes.iter().fold(0xffff, |crc, &byte| {
+        (0..8).fold(crc ^ ((byte as u16) << 8), |crc, _| {
+            if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            }
+        })
+    })
+}
+
+/// COBS-encodes `input` into `output` and returns the encoded length,
+/// without the trailing zero.
+///
+/// `output` must hold at least `input.len() + input.len() / 254 + 1` bytes.
+pub fn cobs_encode(input: &[u8], output: &mut [u8]) -> usize {
+    let mut code_index = 0;
+    let mut len = 1;
+    let mut code = 1u8;
+    for &byte in input {
+        if byte != 0 {
+            output[len] = byte;
+            len += 1;
+            code += 1;
+        }
+        if byte == 0 || code == 0xff {
+            output[code_index] = code;
+            code_index = len;
+            len += 1;
+            code = 1;
+        }
+    }
+    output[code_index] = code;
+    len
+}
+
+/// Sends a record with `payload` on `channel`, timestamped `at`.
+///
+/// Payloads longer than [`MAX_PAYLOAD`] are truncated.
+pub fn send<W: Write<u8>>(
+    writer: &mut W,
+    channel: u8,
+    at: Instant,
+    payload: &[u8],
+) -> Result<(), W::Error> {
+    let payload = &payload[..payload.len().min(MAX_PAYLOAD)];
+    let mut record = [0u8; MAX_RECORD];
+    record[0] = channel;
+    record[1..5].copy_from_slice(&at.as_micros().to_le_bytes());
+    record[5..5 + payload.len()].copy_from_slice(payload);
+    let len = 5 + payload.len();
+    let crc = crc16(&record[..len]);
+    record[len..len + 2].copy_from_slice(&crc.to_le_bytes());
+
+    let mut encoded = [0u8; MAX_ENCODED];
+    let encoded_len = cobs_encode(&record[..len + 2], &mut encoded);
+    for &byte in &encoded[..encoded_len] {
+        nb::block!(writer.write(byte))?;
+    }
+    nb::block!(writer.write(0))
+}
+
+/// Sends a record carrying a single little-endian `u32`.
+pub fn send_u32<W: Write<u8>>(
+    writer: &mut W,
+    channel: u8,
+    at: Instant,
+    value: u32,
+) -> Result<(), W::Error> {
+    send(writer, channel, at, &value.to_le_bytes())
+}
+
+/// How [`send_value`] formats values.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// COBS-framed binary records carrying the value as a little-endian
+    /// `i32`.
+    Binary,
+    /// `timestamp_us,channel,value` lines ending in CRLF, which
+    /// spreadsheets and CSV readers can take directly.
+    Csv,
+}
+
+impl Format {
+    /// Parses `binary` or `csv`, e.g. from a command line.
+    pub fn from_name(name: &str) -> Option<Format> {
+        match name {
+            "binary" => Some(Format::Binary),
+            "csv" => Some(Format::Csv),
+            _ => None,
+        }
+    }
+}
+
+/// Sends `value` on `channel`, timestamped `at`, in the given format.
+pub fn send_value<W: Write<u8>>(
+    writer: &mut W,
+    format: Format,
+    channel: u8,
+    at: Instant,
+    value: i32,
+) -> Result<(), W::Error> {
+    match format {
+        Format::Binary => send(writer, channel, at, &value.to_le_bytes()),
+        Format::Csv => {
+            write_decimal(writer, at.as_micros() as i64)?;
+            nb::block!(writer.write(b','))?;
+            write_decimal(writer, channel as i64)?;
+            nb::block!(writer.write(b','))?;
+            write_decimal(writer, value as i64)?;
+            nb::block!(writer.write(b'\r'))?;
+            nb::block!(writer.write(b'\n'))
+        }
+    }
+}
+
+/// Prints one line of `label:value` pairs in the format the Arduino IDE's
+/// Serial Plotter reads, e.g. `late_us:12 period_us:10004` and CRLF.
+///
+/// The plotter draws a trace per label, so every line should carry the same
+/// labels.  Labels must not contain spaces, tabs, commas or colons.
+pub fn plot<W: Write<u8>>(writer: &mut W, values: &[(&str, i32)]) -> Result<(), W::Error> {
+    for (i, &(label, value)) in values.iter().enumerate() {
+        if i > 0 {
+            nb::block!(writer.write(b' '))?;
+        }
+        for &byte in label.as_bytes() {
+            nb::block!(writer.write(byte))?;
+        }
+        nb::block!(writer.write(b':'))?;
+        write_decimal(writer, value as i64)?;
+    }
+    nb::block!(writer.write(b'\r'))?;
+    nb::block!(writer.write(b'\n'))
+}
+
+/// Writes `s` byte by byte.
+pub(crate) fn write_str<W: Write<u8>>(writer: &mut W, s: &str) -> Result<(), W::Error> {
+    for &byte in s.as_bytes() {
+        nb::block!(writer.write(byte))?;
+    }
+    Ok(())
+}
+
+/// Writes `value` in decimal.
+pub(crate) fn write_decimal<W: Write<u8>>(writer: &mut W, value: i64) -> Result<(), W::Error> {
+    if value < 0 {
+        nb::block!(writer.write(b'-'))?;
+    }
+    let mut magnitude = value.unsigned_abs();
+    let mut digits = [0u8; 20];
+    let mut len = 0;
+    loop {
+        digits[len] = b'0' + (magnitude % 10) as u8;
+        len += 1;
+        magnitude /= 10;
+        if magnitude == 0 {
+            break;
+        }
+    }
+    for &digit in digits[..len].iter().rev() {
+        nb::block!(writer.write(digit))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Decodes a COBS frame without its trailing zero into `output`.
+    fn cobs_decode(input: &[u8], output: &mut [u8]) -> usize {
+        let mut len = 0;
+        let mut index = 0;
+        while index < input.len() {
+            let code = input[index] as usize;
+            assert_ne!(code, 0);
+            for &byte in &input[index + 1..index + code] {
+                output[len] = byte;
+                len += 1;
+            }
+            index += code;
+            if code < 0xff && index < input.len() {
+                output[len] = 0;
+                len += 1;
+            }
+        }
+        len
+    }
+
+    fn round_trip(input: &[u8]) {
+        let mut encoded = [0u8; 600];
+        let encoded_len = cobs_encode(input, &mut encoded);
+        assert!(encoded_len <= input.len() + input.len() / 254 + 1);
+        assert!(!encoded[..encoded_len].contains(&0));
+        let mut decoded = [0u8; 600];
+        let decoded_len = cobs_decode(&encoded[..encoded_len], &mut decoded);
+        assert_eq!(&decoded[..decoded_len], input);
+    }
+
+    #[test]
+    fn crc16_check_value() {
+        assert_eq!(crc16(b"123456789"), 0x29b1);
+        assert_eq!(crc16(b""), 0xffff);
+    }
+
+    #[test]
+    fn cobs_encodes_zeros() {
+        let mut encoded = [0u8; 8];
+        let len = cobs_encode(&[0x11, 0x00, 0x22, 0x00], &mut encoded);
+        assert_eq!(&encoded[..len], &[0x02, 0x11, 0x02, 0x22, 0x01]);
+    }
+
+    #[test]
+    fn cobs_round_trips() {
+        round_trip(&[]);
+        round_trip(&[0]);
+        round_trip(&[0, 0]);
+        round_trip(&[1, 2, 0, 3]);
+    }
+
+    #[test]
+    fn cobs_round_trips_long_runs() {
+        let mut run = [0u8; 600];
+        for (i, byte) in run.iter_mut().enumerate() {
+            *byte = (i % 255 + 1) as u8;
+        }
+        for len in [253, 254, 255, 508, 509] {
+            round_trip(&run[..len]);
+        }
+        run[254] = 0;
+        round_trip(&run[..300]);
+    }
+}
@@ -0,0 +1,49 @@
+//! Rate limiting for serial prints, sensor polls and the like.
+
+use crate::micros64;
+use crate::time::Duration;
+
+/// Lets an action through at most once per interval.
+///
+/// Replaces hand-rolled `if micros() - last > X` guards.  The last time is
+/// kept as a 64-bit timestamp, so a throttle that goes unpolled for longer
+/// than the 71-minute wraparound of [`micros`](crate::micros) still lets
+/// the next call through.
+#[derive(Clone, Copy, Debug)]
+pub struct Throttle {
+    interval: u32,
+    last: Option<u64>,
+}
+
+impl Throttle {
+    /// Creates a throttle whose first [`ready`](Throttle::ready) succeeds
+    /// right away.
+    pub const fn new(min_interval: Duration) -> Self {
+        Throttle {
+            interval: min_interval.as_micros(),
+            last: None,
+        }
+    }
+
+    /// Returns `true` if at least the interval has passed since it last
+    /// returned `true`.
+    ///
+    /// The interval is measured from the call that succeeded, not from when
+    /// it was due, so a late call pushes the next one back rather than
+    /// letting two through in quick succession.
+    pub fn ready(&mut self) -> bool {
+        let now = micros64();
+        match self.last {
+            Some(last) if now - last < self.interval as u64 => false,
+            _ => {
+                self.last = Some(now);
+                true
+            }
+        }
+    }
+
+    /// Lets the next call to [`ready`](Throttle::ready) through.
+    pub fn reset(&mut self) {
+        self.last = None;
+    }
+}
@@ -0,0 +1,420 @@
+//! Units of time used throughout the crate.
+//!
+//! [`Instant`] and [`Duration`] wrap the raw microsecond values returned by
+//! [`micros`](crate::micros) so that points in time and spans of time can't
+//! be mixed up.  All arithmetic on instants wraps around like the counter.
+
+use core::convert::TryFrom;
+use core::ops::{Add, AddAssign, Div, Mul, Sub, SubAssign};
+
+use crate::micros;
+
+/// Returns `true` if the raw timestamp `a` is later than `b`.
+///
+/// Timestamps are compared by their wrapping difference, so the result is
+/// correct across the 71-minute rollover of [`micros`](crate::micros) as long
+/// as `a` and `b` are less than half of that apart.
+pub fn time_after(a: u32, b: u32) -> bool {
+    (b.wrapping_sub(a) as i32) < 0
+}
+
+/// Returns the microseconds elapsed since the raw timestamp `start`.
+///
+/// Unlike `micros() - start`, this doesn't overflow across the rollover.
+pub fn elapsed_since(start: u32) -> u32 {
+    micros().wrapping_sub(start)
+}
+
+/// Returns `true` once the current time is at or after the raw timestamp
+/// `deadline`.
+pub fn deadline_reached(deadline: u32) -> bool {
+    !time_after(deadline, micros())
+}
+
+/// A point in time, as returned by [`now`](crate::now).
+///
+/// Instants wrap around every `u32::MAX` microseconds (about 71 minutes), so
+/// they can only be meaningfully compared when less than half of that apart.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Instant(u32);
+
+impl Instant {
+    /// Creates an instant from a raw [`micros`](crate::micros) value.
+    pub const fn from_micros(micros: u32) -> Self {
+        Instant(micros)
+    }
+
+    /// Returns the raw [`micros`](crate::micros) value of the instant.
+    pub const fn as_micros(self) -> u32 {
+        self.0
+    }
+
+    /// Returns the time elapsed from `earlier` to `self`, or `None` if
+    /// `earlier` is actually later than `self`.
+    pub fn checked_duration_since(self, earlier: Instant) -> Option<Duration> {
+        let diff = self.0.wrapping_sub(earlier.0);
+        if diff <= u32::MAX / 2 {
+            Some(Duration(diff))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the time elapsed from `earlier` to `self`, or zero if
+    /// `earlier` is actually later than `self`.
+    pub fn saturating_duration_since(self, earlier: Instant) -> Duration {
+        self.checked_duration_since(earlier).unwrap_or_default()
+    }
+
+    /// Returns the time elapsed since the instant.
+    pub fn elapsed(self) -> Duration {
+        Duration(micros().wrapping_sub(self.0))
+    }
+}
+
+impl Add<Duration> for Instant {
+    type Output = Instant;
+
+    fn add(self, rhs: Duration) -> Instant {
+        Instant(self.0.wrapping_add(rhs.0))
+    }
+}
+
+impl AddAssign<Duration> for Instant {
+    fn add_assign(&mut self, rhs: Duration) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub<Duration> for Instant {
+    type Output = Instant;
+
+    fn sub(self, rhs: Duration) -> Instant {
+        Instant(self.0.wrapping_sub(rhs.0))
+    }
+}
+
+impl SubAssign<Duration> for Instant {
+    fn sub_assign(&mut self, rhs: Duration) {
+        *self = *self - rhs;
+    }
+}
+
+impl Sub<Instant> for Instant {
+    type Output = Duration;
+
+    /// Returns the wrapping difference between the instants.
+    fn sub(self, rhs: Instant) -> Duration {
+        Duration(self.0.wrapping_sub(rhs.0))
+    }
+}
+
+/// A span of time with microsecond resolution.
+///
+/// Durations up to `u32::MAX` microseconds (about 71 minutes) can be
+/// represented, matching the range of [`micros`](crate::micros).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Duration(u32);
+
+impl Duration {
+    /// Creates a duration from a number of microseconds.
+    pub const fn from_micros(micros: u32) -> Self {
+        Duration(micros)
+    }
+
+    /// Creates a duration from a number of milliseconds.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the duration exceeds `u32::MAX` microseconds, i.e. for more
+    /// than 4,294,967 ms, at compile time in a constant.  See
+    /// [`checked_from_millis`](Duration::checked_from_millis).
+    pub const fn from_millis(millis: u32) -> Self {
+        match Duration::checked_from_millis(millis) {
+            Some(duration) => duration,
+            None => panic!("duration overflow"),
+        }
+    }
+
+    /// Creates a duration from a number of seconds.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the duration exceeds `u32::MAX` microseconds, i.e. for more
+    /// than 4294 s, at compile time in a constant.  See
+    /// [`checked_from_secs`](Duration::checked_from_secs).
+    pub const fn from_secs(secs: u32) -> Self {
+        match Duration::checked_from_secs(secs) {
+            Some(duration) => duration,
+            None => panic!("duration overflow"),
+        }
+    }
+
+    /// Creates a duration from a number of milliseconds, returning `None` if
+    /// it exceeds `u32::MAX` microseconds.
+    pub const fn checked_from_millis(millis: u32) -> Option<Self> {
+        match millis.checked_mul(1000) {
+            Some(micros) => Some(Duration(micros)),
+            None => None,
+        }
+    }
+
+    /// Creates a duration from a number of seconds, returning `None` if it
+    /// exceeds `u32::MAX` microseconds.
+    pub const fn checked_from_secs(secs: u32) -> Option<Self> {
+        match secs.checked_mul(1_000_000) {
+            Some(micros) => Some(Duration(micros)),
+            None => None,
+        }
+    }
+
+    /// Returns the duration in whole microseconds.
+    pub const fn as_micros(self) -> u32 {
+        self.0
+    }
+
+    /// Returns the duration in whole milliseconds.
+    pub const fn as_millis(self) -> u32 {
+        self.0 / 1000
+    }
+
+    /// Adds two durations, returning `None` on overflow.
+    pub fn checked_add(self, rhs: Duration) -> Option<Duration> {
+        self.0.checked_add(rhs.0).map(Duration)
+    }
+
+    /// Subtracts two durations, returning `None` if `rhs` is longer.
+    pub fn checked_sub(self, rhs: Duration) -> Option<Duration> {
+        self.0.checked_sub(rhs.0).map(Duration)
+    }
+
+    /// Subtracts two durations, returning zero if `rhs` is longer.
+    pub fn saturating_sub(self, rhs: Duration) -> Duration {
+        Duration(self.0.saturating_sub(rhs.0))
+    }
+
+    /// Returns the duration in milliseconds, with the fraction.
+    #[cfg(feature = "float")]
+    pub fn as_millis_f32(self) -> f32 {
+        self.0 as f32 / 1000.0
+    }
+
+    /// Returns the duration in seconds, with the fraction.
+    #[cfg(feature = "float")]
+    pub fn as_secs_f32(self) -> f32 {
+        self.0 as f32 / 1_000_000.0
+    }
+}
+
+impl From<Duration> for core::time::Duration {
+    fn from(duration: Duration) -> Self {
+        core::time::Duration::from_micros(duration.0 as u64)
+    }
+}
+
+/// The error returned when a `core::time::Duration` is too long for a
+/// [`Duration`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TryFromDurationError;
+
+impl TryFrom<core::time::Duration> for Duration {
+    type Error = TryFromDurationError;
+
+    /// Converts to whole microseconds, dropping any fraction, or fails for
+    /// durations longer than `u32::MAX` microseconds, about 71 minutes.
+    fn try_from(duration: core::time::Duration) -> Result<Self, Self::Error> {
+        u32::try_from(duration.as_micros())
+            .map(Duration)
+            .map_err(|_| TryFromDurationError)
+    }
+}
+
+impl Add for Duration {
+    type Output = Duration;
+
+    fn add(self, rhs: Duration) -> Duration {
+        Duration(self.0 + rhs.0)
+    }
+}
+
+impl AddAssign for Duration {
+    fn add_assign(&mut self, rhs: Duration) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub for Duration {
+    type Output = Duration;
+
+    fn sub(self, rhs: Duration) -> Duration {
+        Duration(self.0 - rhs.0)
+    }
+}
+
+impl SubAssign for Duration {
+    fn sub_assign(&mut self, rhs: Duration) {
+        *self = *self - rhs;
+    }
+}
+
+impl Mul<u32> for Duration {
+    type Output = Duration;
+
+    fn mul(self, rhs: u32) -> Duration {
+        Duration(self.0 * rhs)
+    }
+}
+
+impl Div<u32> for Duration {
+    type Output = Duration;
+
+    fn div(self, rhs: u32) -> Duration {
+        Duration(self.0 / rhs)
+    }
+}
+
+/// Extension trait for creating durations from integers, e.g. `10.ms()`.
+pub trait U32Ext {
+    /// Interprets the value as microseconds.
+    fn us(self) -> Duration;
+
+    /// Interprets the value as milliseconds.
+    fn ms(self) -> Duration;
+
+    /// Interprets the value as seconds.
+    fn s(self) -> Duration;
+}
+
+impl U32Ext for u32 {
+    fn us(self) -> Duration {
+        Duration::from_micros(self)
+    }
+
+    fn ms(self) -> Duration {
+        Duration::from_millis(self)
+    }
+
+    fn s(self) -> Duration {
+        Duration::from_secs(self)
+    }
+}
+
+/// Formats as `1234 us` below a second, and as `3.456789s`, `2m03.456789s`
+/// or `1h02m03.456789s` from there on.
+#[cfg(feature = "ufmt")]
+impl ufmt::uDisplay for Duration {
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        let micros = self.0 % 1_000_000;
+        let secs = self.0 / 1_000_000;
+        if secs == 0 {
+            return ufmt::uwrite!(f, "{} us", micros);
+        }
+        let (hours, minutes, secs) = (secs / 3600, secs / 60 % 60, secs % 60);
+        if hours > 0 {
+            ufmt::uwrite!(f, "{}h", hours)?;
+            write_padded(f, minutes, 2)?;
+            f.write_str("m")?;
+            write_padded(f, secs, 2)?;
+        } else if minutes > 0 {
+            ufmt::uwrite!(f, "{}m", minutes)?;
+            write_padded(f, secs, 2)?;
+        } else {
+            ufmt::uwrite!(f, "{}", secs)?;
+        }
+        f.write_str(".")?;
+        write_padded(f, micros, 6)?;
+        f.write_str("s")
+    }
+}
+
+#[cfg(feature = "ufmt")]
+impl ufmt::uDebug for Duration {
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        ufmt::uDisplay::fmt(self, f)
+    }
+}
+
+/// Formats as the time since [`micros_init`](crate::micros_init), like a
+/// [`Duration`], e.g. `2m03.456789s`.
+#[cfg(feature = "ufmt")]
+impl ufmt::uDisplay for Instant {
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        ufmt::uDisplay::fmt(&Duration(self.0), f)
+    }
+}
+
+#[cfg(feature = "ufmt")]
+impl ufmt::uDebug for Instant {
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        ufmt::uwrite!(f, "Instant({})", Duration(self.0))
+    }
+}
+
+/// Writes `value` with leading zeros to `digits` digits.
+#[cfg(feature = "ufmt")]
+fn write_padded<W>(
+    f: &mut ufmt::Formatter<'_, W>,
+    value: u32,
+    digits: usize,
+) -> Result<(), W::Error>
+where
+    W: ufmt::uWrite + ?Sized,
+{
+    let mut buffer = [b'0'; 10];
+    let mut value = value;
+    for digit in buffer[..digits].iter_mut().rev() {
+        *digit = b'0' + (value % 10) as u8;
+        value /= 10;
+    }
+    // Only ASCII digits are written.
+    f.write_str(core::str::from_utf8(&buffer[..digits]).unwrap_or(""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn millis_up_to_the_limit() {
+        assert_eq!(Duration::from_millis(4_294_967).as_micros(), 4_294_967_000);
+        assert_eq!(
+            Duration::checked_from_millis(4_294_967),
+            Some(Duration(4_294_967_000))
+        );
+        assert_eq!(Duration::checked_from_millis(4_294_968), None);
+    }
+
+    #[test]
+    fn secs_up_to_the_limit() {
+        assert_eq!(Duration::from_secs(4294).as_micros(), 4_294_000_000);
+        assert_eq!(
+            Duration::checked_from_secs(4294),
+            Some(Duration(4_294_000_000))
+        );
+        assert_eq!(Duration::checked_from_secs(4295), None);
+    }
+
+    #[test]
+    #[should_panic]
+    fn millis_overflow_panics() {
+        Duration::from_millis(4_294_968);
+    }
+
+    #[test]
+    #[should_panic]
+    fn secs_overflow_panics() {
+        Duration::from_secs(u32::MAX);
+    }
+}
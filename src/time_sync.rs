@@ -0,0 +1,167 @@
+//! Setting the wall clock from a host over serial.
+//!
+//! The exchange works like a single NTP query.  The device sends a request
+//! carrying its own timestamp `t1`, and the host answers with the times it
+//! received the request (`t2`) and sent the reply (`t3`).  With the time the
+//! reply arrived (`t4`), the device can estimate both the round trip and the
+//! offset between the two clocks, assuming the link is equally fast both
+//! ways:
+//!
+//! ```text
+//! round trip = (t4 - t1) - (t3 - t2)
+//! offset     = ((t2 - t1) + (t3 - t4)) / 2
+//! ```
+//!
+//! All values are little-endian.  Device times are [`micros64`] timestamps
+//! and host times are Unix time in microseconds.  The device takes `t1` as
+//! it starts sending the request and `t4` when the first byte of the reply
+//! is fed, or from the timestamp passed to [`TimeSync::feed_at`], e.g. one
+//! taken in the receive interrupt, so the host should take `t2` once the
+//! whole request has arrived and `t3` just before it starts writing the
+//! reply.
+//!
+//! | Message | Bytes                                            |
+//! |---------|--------------------------------------------------|
+//! | Request | `b'T'`, sequence `u8`, `t1: u64`                 |
+//! | Reply   | `b'R'`, sequence `u8`, `t2: u64`, `t3: u64`      |
+
+use crate::micros64;
+use crate::time::Duration;
+use crate::wall_clock::WallClock;
+
+/// The length of a request.
+pub const REQUEST_LEN: usize = 10;
+
+const REPLY_LEN: usize = 18;
+
+const REQUEST: u8 = b'T';
+const REPLY: u8 = b'R';
+
+// Replies arriving later than this are ignored.
+const TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The outcome of an exchange.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Sync {
+    /// The host time minus the device time, in microseconds.
+    pub offset: i64,
+    /// The time taken by the exchange, less the host's processing time.
+    pub round_trip: Duration,
+    /// The device time the reply started arriving, as a [`micros64`]
+    /// timestamp.
+    pub received_at: u64,
+}
+
+impl Sync {
+    /// Returns the host's Unix time in microseconds at `received_at`.
+    pub fn host_micros(&self) -> u64 {
+        (self.received_at as i64 + self.offset) as u64
+    }
+
+    /// Sets `clock` to the host's time.
+    pub fn apply(&self, clock: &mut WallClock) {
+        let host = self.host_micros();
+        let second_start = self.received_at.saturating_sub(host % 1_000_000);
+        clock.set_unix_at((host / 1_000_000) as u32, second_start);
+    }
+}
+
+/// The device side of the exchange.
+pub struct TimeSync {
+    sequence: u8,
+    sent_at: Option<u64>,
+    reply_at: u64,
+    buf: [u8; REPLY_LEN],
+    len: usize,
+}
+
+impl TimeSync {
+    /// Creates an idle exchange.
+    pub const fn new() -> Self {
+        TimeSync {
+            sequence: 0,
+            sent_at: None,
+            reply_at: 0,
+            buf: [0; REPLY_LEN],
+            len: 0,
+        }
+    }
+
+    /// Starts an exchange, returning the request to send to the host.
+    ///
+    /// Any exchange still in progress is abandoned.
+    pub fn request(&mut self) -> [u8; REQUEST_LEN] {
+        self.sequence = self.sequence.wrapping_add(1);
+        self.len = 0;
+        let t1 = micros64();
+        self.sent_at = Some(t1);
+
+        let mut request = [0; REQUEST_LEN];
+        request[0] = REQUEST;
+        request[1] = self.sequence;
+        request[2..].copy_from_slice(&t1.to_le_bytes());
+        request
+    }
+
+    /// Returns `true` while waiting for a reply.
+    pub fn is_pending(&self) -> bool {
+        self.sent_at.is_some()
+    }
+
+    /// Feeds a byte received from the host, returning the result once the
+    /// reply to the current request is complete.
+    pub fn feed(&mut self, byte: u8) -> Option<Sync> {
+        self.feed_at(byte, micros64())
+    }
+
+    /// Feeds a byte received from the host at `received_at`, a [`micros64`]
+    /// timestamp taken e.g. in the receive interrupt, so that the time the
+    /// byte waited to be fed doesn't count towards the round trip.  Only the
+    /// timestamp of the reply's first byte is used, as `t4`.
+    pub fn feed_at(&mut self, byte: u8, received_at: u64) -> Option<Sync> {
+        let now = received_at;
+        let t1 = self.sent_at?;
+        if now.saturating_sub(t1) > TIMEOUT.as_micros() as u64 {
+            self.sent_at = None;
+            return None;
+        }
+        if self.len == 0 {
+            if byte != REPLY {
+                return None;
+            }
+            self.reply_at = now;
+        }
+        self.buf[self.len] = byte;
+        self.len += 1;
+        if self.len < REPLY_LEN {
+            return None;
+        }
+
+        self.len = 0;
+        if self.buf[1] != self.sequence {
+            // A late reply to an earlier request.
+            return None;
+        }
+        self.sent_at = None;
+        let t2 = read_u64(&self.buf[2..10]) as i64;
+        let t3 = read_u64(&self.buf[10..18]) as i64;
+        let (t1, t4) = (t1 as i64, self.reply_at as i64);
+        Some(Sync {
+            offset: ((t2 - t1) + (t3 - t4)) / 2,
+            round_trip: Duration::from_micros(((t4 - t1) - (t3 - t2)).max(0) as u32),
+            received_at: t4 as u64,
+        })
+    }
+}
+
+impl Default for TimeSync {
+    fn default() -> Self {
+        TimeSync::new()
+    }
+}
+
+fn read_u64(bytes: &[u8]) -> u64 {
+    let mut value = [0; 8];
+    value.copy_from_slice(bytes);
+    u64::from_le_bytes(value)
+}
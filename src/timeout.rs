@@ -0,0 +1,110 @@
+//! Software timeouts layered on the microsecond counter.
+
+use embedded_hal::timer::{CountDown, Periodic};
+use void::Void;
+
+use crate::micros64;
+use crate::time::Duration;
+
+/// A software timeout.
+///
+/// State machines can carry a timeout around and poll [`expired`] instead of
+/// stashing raw start timestamps.  The start is kept as a 64-bit timestamp,
+/// so an expired timeout stays expired past the wraparound of
+/// [`micros`](crate::micros).
+///
+/// `Timeout` also implements the `embedded-hal` [`CountDown`] trait without
+/// claiming a hardware timer of its own, so drivers that need one can share
+/// the time base.  After [`wait`] succeeds, the timer restarts with the same
+/// period.
+///
+/// [`expired`]: Timeout::expired
+/// [`wait`]: CountDown::wait
+#[derive(Clone, Copy, Debug)]
+pub struct Timeout {
+    start: u64,
+    duration: u32,
+}
+
+impl Timeout {
+    /// Creates a timeout that expires `duration` from now.
+    pub fn new(duration: Duration) -> Self {
+        Timeout {
+            start: micros64(),
+            duration: duration.as_micros(),
+        }
+    }
+
+    /// Returns `true` once the timeout has expired.
+    pub fn expired(&self) -> bool {
+        self.elapsed() >= self.duration as u64
+    }
+
+    /// Returns the time left until the timeout expires, or zero if it
+    /// already has.
+    pub fn remaining(&self) -> Duration {
+        let remaining = (self.duration as u64).saturating_sub(self.elapsed());
+        Duration::from_micros(remaining as u32)
+    }
+
+    /// Starts the timeout over with the same duration.
+    pub fn restart(&mut self) {
+        self.start = micros64();
+    }
+
+    fn elapsed(&self) -> u64 {
+        micros64() - self.start
+    }
+}
+
+impl CountDown for Timeout {
+    type Time = Duration;
+
+    fn start<T>(&mut self, count: T)
+    where
+        T: Into<Self::Time>,
+    {
+        *self = Timeout::new(count.into());
+    }
+
+    fn wait(&mut self) -> nb::Result<(), Void> {
+        if !self.expired() {
+            return Err(nb::Error::WouldBlock);
+        }
+
+        // Advance by exactly one period so that repeated waits don't drift.
+        self.start += self.duration as u64;
+        Ok(())
+    }
+}
+
+impl Periodic for Timeout {}
+
+/// Errors returned by [`with_timeout`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error<E> {
+    /// The operation didn't complete before the timeout expired.
+    TimedOut,
+    /// The operation itself failed.
+    Other(E),
+}
+
+/// Polls the `nb` operation `f` until it completes or `duration` has
+/// elapsed.
+///
+/// This gives a bounded-wait alternative to `nb::block!`, e.g.
+/// `with_timeout(100.ms(), || serial.read())`.
+pub fn with_timeout<T, E, F>(duration: Duration, mut f: F) -> Result<T, Error<E>>
+where
+    F: FnMut() -> nb::Result<T, E>,
+{
+    let timeout = Timeout::new(duration);
+    loop {
+        match f() {
+            Ok(value) => return Ok(value),
+            Err(nb::Error::Other(e)) => return Err(Error::Other(e)),
+            Err(nb::Error::WouldBlock) if timeout.expired() => return Err(Error::TimedOut),
+            Err(nb::Error::WouldBlock) => (),
+        }
+    }
+}
@@ -0,0 +1,100 @@
+//! Hardware timer backends driving the time base.
+//!
+//! The backend exposes the same interface for every timer: the peripheral
+//! type, `const fn`s describing which prescalers and periods it supports, a
+//! function that configures it from the validated settings, accessors for
+//! the current count and the pending interrupt flag, a function that clears
+//! the flag, and functions that stop and restart the timer and set its
+//! count.  Its ISR calls `crate::tick()`.
+//!
+//! The CTC and normal mode backends, except on the ATtiny85, also offer
+//! their second compare unit through `arm_compare_b` and
+//! `disarm_compare_b`, whose ISR calls `crate::on_compare_b()`, for the
+//! features that have to act between two periods.  With the `rtic`
+//! feature the application binds that ISR to the monotonic instead.
+
+#[cfg(all(feature = "timer1", feature = "timer2"))]
+compile_error!("the `timer1` and `timer2` features are mutually exclusive");
+
+#[cfg(all(feature = "timer2", feature = "atmega32u4"))]
+compile_error!("the ATmega32U4 has no Timer2");
+
+#[cfg(all(feature = "attiny85", any(feature = "timer1", feature = "timer2")))]
+compile_error!("only Timer0 is supported on the ATtiny85");
+
+#[cfg(all(
+    feature = "atmega4809",
+    any(feature = "timer1", feature = "timer2", feature = "arduino-core")
+))]
+compile_error!("the ATmega4809 time base always runs on TCB0");
+
+#[cfg(all(feature = "arduino-core", any(feature = "timer1", feature = "timer2")))]
+compile_error!("the `arduino-core` feature requires the time base to run on Timer0");
+
+#[cfg(all(
+    feature = "fast-isr",
+    any(
+        not(feature = "atmega328p"),
+        feature = "timer1",
+        feature = "timer2",
+        feature = "arduino-core"
+    )
+))]
+compile_error!("the `fast-isr` feature requires the ATmega328P with the time base on TC0");
+
+// The minimal ISR doesn't run the hooks of `crate::tick`.
+#[cfg(all(
+    feature = "fast-isr",
+    any(
+        feature = "rtic",
+        feature = "embassy",
+        feature = "executor",
+        feature = "isr-alarms",
+        feature = "isr-metrics",
+        feature = "latency-probe",
+        feature = "tone"
+    )
+))]
+compile_error!(
+    "the `fast-isr` feature can't be combined with features that run from the timer ISR"
+);
+
+#[cfg(not(any(
+    feature = "timer1",
+    feature = "timer2",
+    feature = "arduino-core",
+    feature = "atmega4809"
+)))]
+mod tc0;
+#[cfg(not(any(
+    feature = "timer1",
+    feature = "timer2",
+    feature = "arduino-core",
+    feature = "atmega4809"
+)))]
+pub use self::tc0::*;
+
+#[cfg(feature = "arduino-core")]
+mod tc0_pwm;
+#[cfg(feature = "arduino-core")]
+pub use self::tc0_pwm::*;
+
+#[cfg(all(feature = "timer1", not(feature = "timer1-overflow")))]
+mod tc1;
+#[cfg(all(feature = "timer1", not(feature = "timer1-overflow")))]
+pub use self::tc1::*;
+
+#[cfg(feature = "timer1-overflow")]
+mod tc1_ovf;
+#[cfg(feature = "timer1-overflow")]
+pub use self::tc1_ovf::*;
+
+#[cfg(all(feature = "timer2", not(feature = "timer1")))]
+mod tc2;
+#[cfg(all(feature = "timer2", not(feature = "timer1")))]
+pub use self::tc2::*;
+
+#[cfg(feature = "atmega4809")]
+mod tcb0;
+#[cfg(feature = "atmega4809")]
+pub use self::tcb0::*;
@@ -0,0 +1,91 @@
+//! Timer/Counter 0 backend for `arduino-core` mode.
+//!
+//! The timer runs freely in Fast PWM mode and the time base is advanced on
+//! every overflow, leaving both compare units available for PWM.
+
+use crate::config::Settings;
+
+/// The timer peripheral driving the time base.
+pub type Timer = crate::pac::TC0;
+
+/// Returns the clock select bits for `prescaler`, if the timer supports it.
+pub(crate) const fn clock_select(prescaler: u32) -> Option<u8> {
+    match prescaler {
+        1 => Some(0b001),
+        8 => Some(0b010),
+        64 => Some(0b011),
+        256 => Some(0b100),
+        1024 => Some(0b101),
+        _ => None,
+    }
+}
+
+/// Returns `true` if the timer can count `counts` per period.  In Fast PWM
+/// mode the timer always overflows after 256 counts.
+pub(crate) const fn valid_counts(counts: u32) -> bool {
+    counts == 256
+}
+
+pub(crate) fn configure(tc0: &Timer, settings: &Settings) {
+    // Only the waveform generation and clock select bits are touched so that
+    // any PWM outputs configured on OC0A/OC0B are left alone.
+    tc0.tccr0a.modify(|_, w| w.wgm0().pwm_fast());
+    tc0.tccr0b
+        .modify(|_, w| unsafe { w.cs0().bits(settings.clock_select) });
+    #[cfg(not(feature = "attiny85"))]
+    tc0.timsk0.modify(|_, w| w.toie0().set_bit());
+    #[cfg(feature = "attiny85")]
+    tc0.timsk.modify(|_, w| w.toie0().set_bit());
+}
+
+fn regs() -> &'static crate::pac::tc0::RegisterBlock {
+    unsafe { &*Timer::ptr() }
+}
+
+pub(crate) fn counts() -> u16 {
+    regs().tcnt0.read().bits() as u16
+}
+
+#[cfg(not(feature = "attiny85"))]
+pub(crate) fn compare_pending() -> bool {
+    regs().tifr0.read().tov0().bit_is_set()
+}
+
+#[cfg(feature = "attiny85")]
+pub(crate) fn compare_pending() -> bool {
+    regs().tifr.read().tov0().bit_is_set()
+}
+
+#[cfg(not(feature = "attiny85"))]
+pub(crate) fn clear_compare() {
+    regs().tifr0.write(|w| w.tov0().set_bit());
+}
+
+#[cfg(feature = "attiny85")]
+pub(crate) fn clear_compare() {
+    regs().tifr.write(|w| w.tov0().set_bit());
+}
+
+/// Stops the timer, keeping its count and flags.
+pub(crate) fn stop() {
+    regs().tccr0b.modify(|_, w| unsafe { w.cs0().bits(0) });
+}
+
+/// Starts the timer again after [`stop`].
+pub(crate) fn start(settings: &Settings) {
+    regs()
+        .tccr0b
+        .modify(|_, w| unsafe { w.cs0().bits(settings.clock_select) });
+}
+
+/// Sets the count, e.g. to carry over the part of a period that passed
+/// while the timer was stopped.
+pub(crate) fn set_counts(counts: u16) {
+    regs().tcnt0.write(|w| unsafe { w.bits(counts as u8) });
+}
+
+isr! {
+    fn TIMER0_OVF() {
+        crate::tick()
+    }
+}
@@ -0,0 +1,97 @@
+//! 16-bit Timer/Counter 1 backend.
+
+use crate::config::Settings;
+
+/// The timer peripheral driving the time base.
+pub type Timer = crate::pac::TC1;
+
+/// Returns the clock select bits for `prescaler`, if the timer supports it.
+pub(crate) const fn clock_select(prescaler: u32) -> Option<u8> {
+    match prescaler {
+        1 => Some(0b001),
+        8 => Some(0b010),
+        64 => Some(0b011),
+        256 => Some(0b100),
+        1024 => Some(0b101),
+        _ => None,
+    }
+}
+
+/// Returns `true` if the timer can count `counts` per period.
+pub(crate) const fn valid_counts(counts: u32) -> bool {
+    counts >= 1 && counts <= 65536
+}
+
+pub(crate) fn configure(tc1: &Timer, settings: &Settings) {
+    // Waveform generation mode 4 is CTC with OCR1A as the top.  WGM13:2
+    // live in TCCR1B and WGM11:0 in TCCR1A.
+    tc1.tccr1a.write(|w| unsafe { w.wgm1().bits(0b00) });
+    let top = (settings.counts - 1) as u16;
+    tc1.ocr1a.write(|w| unsafe { w.bits(top) });
+    tc1.tccr1b
+        .write(|w| unsafe { w.wgm1().bits(0b01).cs1().bits(settings.clock_select) });
+    tc1.timsk1.write(|w| w.ocie1a().set_bit());
+}
+
+fn regs() -> &'static crate::pac::tc1::RegisterBlock {
+    unsafe { &*Timer::ptr() }
+}
+
+pub(crate) fn counts() -> u16 {
+    regs().tcnt1.read().bits()
+}
+
+pub(crate) fn compare_pending() -> bool {
+    regs().tifr1.read().ocf1a().bit_is_set()
+}
+
+pub(crate) fn clear_compare() {
+    regs().tifr1.write(|w| w.ocf1a().set_bit());
+}
+
+/// Stops the timer, keeping its count and flags.
+pub(crate) fn stop() {
+    regs().tccr1b.modify(|_, w| unsafe { w.cs1().bits(0) });
+}
+
+/// Starts the timer again after [`stop`].
+pub(crate) fn start(settings: &Settings) {
+    regs()
+        .tccr1b
+        .modify(|_, w| unsafe { w.cs1().bits(settings.clock_select) });
+}
+
+/// Sets the count, e.g. to carry over the part of a period that passed
+/// while the timer was stopped.
+pub(crate) fn set_counts(counts: u16) {
+    regs().tcnt1.write(|w| unsafe { w.bits(counts) });
+}
+
+/// Makes compare unit B interrupt when the count next reaches `at`, which
+/// must be below the period.
+#[cfg(any(feature = "embassy", feature = "rtic", feature = "tone"))]
+pub(crate) fn arm_compare_b(at: u16) {
+    let tc1 = regs();
+    tc1.ocr1b.write(|w| unsafe { w.bits(at) });
+    tc1.tifr1.write(|w| w.ocf1b().set_bit());
+    tc1.timsk1.modify(|_, w| w.ocie1b().set_bit());
+}
+
+/// Stops the compare unit B interrupt.
+#[cfg(any(feature = "embassy", feature = "rtic", feature = "tone"))]
+pub(crate) fn disarm_compare_b() {
+    regs().timsk1.modify(|_, w| w.ocie1b().clear_bit());
+}
+
+#[cfg(all(any(feature = "embassy", feature = "tone"), not(feature = "rtic")))]
+isr! {
+    fn TIMER1_COMPB() {
+        crate::on_compare_b()
+    }
+}
+
+isr! {
+    fn TIMER1_COMPA() {
+        crate::tick()
+    }
+}
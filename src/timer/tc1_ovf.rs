@@ -0,0 +1,139 @@
+//! 16-bit Timer/Counter 1 backend in normal mode for `timer1-overflow`.
+//!
+//! The timer counts freely through all 65536 values at a prescaler of 8, so
+//! it interrupts only on overflow, every 32.768 ms at 16 MHz, while a count
+//! still lasts 0.5 us.  Both compare units stay free, except that the
+//! `tickless` feature uses OCR1A to interrupt exactly when the next alarm
+//! is due.
+
+use crate::config::Settings;
+
+/// The timer peripheral driving the time base.
+pub type Timer = crate::pac::TC1;
+
+/// Returns the clock select bits for `prescaler`, if the timer supports it.
+/// Only a prescaler of 8 is offered, which keeps the conversion of counts
+/// to microseconds a shift.
+pub(crate) const fn clock_select(prescaler: u32) -> Option<u8> {
+    match prescaler {
+        8 => Some(0b010),
+        _ => None,
+    }
+}
+
+/// Returns `true` if the timer can count `counts` per period.  In normal
+/// mode the timer always overflows after 65536 counts.
+pub(crate) const fn valid_counts(counts: u32) -> bool {
+    counts == 65536
+}
+
+pub(crate) fn configure(tc1: &Timer, settings: &Settings) {
+    // Waveform generation mode 0 is normal mode.
+    tc1.tccr1a.write(|w| unsafe { w.wgm1().bits(0b00) });
+    tc1.tcnt1.write(|w| unsafe { w.bits(0) });
+    tc1.tccr1b
+        .write(|w| unsafe { w.wgm1().bits(0b00).cs1().bits(settings.clock_select) });
+    tc1.timsk1.write(|w| w.toie1().set_bit());
+}
+
+fn regs() -> &'static crate::pac::tc1::RegisterBlock {
+    unsafe { &*Timer::ptr() }
+}
+
+pub(crate) fn counts() -> u16 {
+    regs().tcnt1.read().bits()
+}
+
+pub(crate) fn compare_pending() -> bool {
+    regs().tifr1.read().tov1().bit_is_set()
+}
+
+pub(crate) fn clear_compare() {
+    regs().tifr1.write(|w| w.tov1().set_bit());
+}
+
+/// Stops the timer, keeping its count and flags.
+pub(crate) fn stop() {
+    regs().tccr1b.modify(|_, w| unsafe { w.cs1().bits(0) });
+}
+
+/// Starts the timer again after [`stop`].
+pub(crate) fn start(settings: &Settings) {
+    regs()
+        .tccr1b
+        .modify(|_, w| unsafe { w.cs1().bits(settings.clock_select) });
+}
+
+/// Sets the count, e.g. to carry over the part of a period that passed
+/// while the timer was stopped.
+pub(crate) fn set_counts(counts: u16) {
+    regs().tcnt1.write(|w| unsafe { w.bits(counts) });
+}
+
+/// Makes compare unit B interrupt when the count next reaches `at`, which
+/// must be below the period.
+#[cfg(any(feature = "embassy", feature = "rtic", feature = "tone"))]
+pub(crate) fn arm_compare_b(at: u16) {
+    let tc1 = regs();
+    tc1.ocr1b.write(|w| unsafe { w.bits(at) });
+    tc1.tifr1.write(|w| w.ocf1b().set_bit());
+    tc1.timsk1.modify(|_, w| w.ocie1b().set_bit());
+}
+
+/// Stops the compare unit B interrupt.
+#[cfg(any(feature = "embassy", feature = "rtic", feature = "tone"))]
+pub(crate) fn disarm_compare_b() {
+    regs().timsk1.modify(|_, w| w.ocie1b().clear_bit());
+}
+
+#[cfg(all(any(feature = "embassy", feature = "tone"), not(feature = "rtic")))]
+isr! {
+    fn TIMER1_COMPB() {
+        crate::on_compare_b()
+    }
+}
+
+isr! {
+    fn TIMER1_OVF() {
+        crate::tick()
+    }
+}
+
+// Compare matches closer than this may already have passed by the time
+// OCR1A is written.
+#[cfg(feature = "tickless")]
+const MIN_COUNTS: u32 = 16;
+
+/// Schedules a compare match interrupt on OCR1A `micros` from now, for
+/// the `tickless` alarms, or cancels it if that is too far out to fit in
+/// the 16-bit count.  The overflow interrupt runs at least once per 65536
+/// counts and calls this again, by which time it fits.
+#[cfg(feature = "tickless")]
+pub(crate) fn arm_compare(micros: u32) {
+    let tc1 = regs();
+    let counts = micros as u64 * crate::CLOCK_MHZ as u64 / 8;
+    if counts >= 65536 - MIN_COUNTS as u64 {
+        tc1.timsk1.modify(|_, w| w.ocie1a().clear_bit());
+        return;
+    }
+    let counts = (counts as u32).max(MIN_COUNTS) as u16;
+    let target = tc1.tcnt1.read().bits().wrapping_add(counts);
+    tc1.ocr1a.write(|w| unsafe { w.bits(target) });
+    tc1.tifr1.write(|w| w.ocf1a().set_bit());
+    tc1.timsk1.modify(|_, w| w.ocie1a().set_bit());
+}
+
+/// Cancels the compare match interrupt.
+#[cfg(feature = "tickless")]
+pub(crate) fn disarm_compare() {
+    regs().timsk1.modify(|_, w| w.ocie1a().clear_bit());
+}
+
+#[cfg(feature = "tickless")]
+isr! {
+    fn TIMER1_COMPA() {
+        disarm_compare();
+        // Rearms the compare match for the next alarm.
+        crate::alarm::poll()
+    }
+}
@@ -0,0 +1,99 @@
+//! 8-bit Timer/Counter 2 backend.
+
+use crate::config::Settings;
+
+/// The timer peripheral driving the time base.
+pub type Timer = crate::pac::TC2;
+
+/// Returns the clock select bits for `prescaler`, if the timer supports it.
+/// Timer2 offers a few more prescalers than the other timers.
+pub(crate) const fn clock_select(prescaler: u32) -> Option<u8> {
+    match prescaler {
+        1 => Some(0b001),
+        8 => Some(0b010),
+        32 => Some(0b011),
+        64 => Some(0b100),
+        128 => Some(0b101),
+        256 => Some(0b110),
+        1024 => Some(0b111),
+        _ => None,
+    }
+}
+
+/// Returns `true` if the timer can count `counts` per period.
+pub(crate) const fn valid_counts(counts: u32) -> bool {
+    counts >= 1 && counts <= 256
+}
+
+pub(crate) fn configure(tc2: &Timer, settings: &Settings) {
+    // Same setup as Timer0: CTC mode with OCR2A as the top.
+    tc2.tccr2a.write(|w| w.wgm2().ctc());
+    let top = (settings.counts - 1) as u8;
+    tc2.ocr2a.write(|w| unsafe { w.bits(top) });
+    tc2.tccr2b
+        .write(|w| unsafe { w.cs2().bits(settings.clock_select) });
+    tc2.timsk2.write(|w| w.ocie2a().set_bit());
+}
+
+fn regs() -> &'static crate::pac::tc2::RegisterBlock {
+    unsafe { &*Timer::ptr() }
+}
+
+pub(crate) fn counts() -> u16 {
+    regs().tcnt2.read().bits() as u16
+}
+
+pub(crate) fn compare_pending() -> bool {
+    regs().tifr2.read().ocf2a().bit_is_set()
+}
+
+pub(crate) fn clear_compare() {
+    regs().tifr2.write(|w| w.ocf2a().set_bit());
+}
+
+/// Stops the timer, keeping its count and flags.
+pub(crate) fn stop() {
+    regs().tccr2b.modify(|_, w| unsafe { w.cs2().bits(0) });
+}
+
+/// Starts the timer again after [`stop`].
+pub(crate) fn start(settings: &Settings) {
+    regs()
+        .tccr2b
+        .modify(|_, w| unsafe { w.cs2().bits(settings.clock_select) });
+}
+
+/// Sets the count, e.g. to carry over the part of a period that passed
+/// while the timer was stopped.
+pub(crate) fn set_counts(counts: u16) {
+    regs().tcnt2.write(|w| unsafe { w.bits(counts as u8) });
+}
+
+/// Makes compare unit B interrupt when the count next reaches `at`, which
+/// must be below the period.
+#[cfg(any(feature = "embassy", feature = "rtic", feature = "tone"))]
+pub(crate) fn arm_compare_b(at: u16) {
+    let tc2 = regs();
+    tc2.ocr2b.write(|w| unsafe { w.bits(at as u8) });
+    tc2.tifr2.write(|w| w.ocf2b().set_bit());
+    tc2.timsk2.modify(|_, w| w.ocie2b().set_bit());
+}
+
+/// Stops the compare unit B interrupt.
+#[cfg(any(feature = "embassy", feature = "rtic", feature = "tone"))]
+pub(crate) fn disarm_compare_b() {
+    regs().timsk2.modify(|_, w| w.ocie2b().clear_bit());
+}
+
+#[cfg(all(any(feature = "embassy", feature = "tone"), not(feature = "rtic")))]
+isr! {
+    fn TIMER2_COMPB() {
+        crate::on_compare_b()
+    }
+}
+
+isr! {
+    fn TIMER2_COMPA() {
+        crate::tick()
+    }
+}
@@ -0,0 +1,75 @@
+//! ATmega4809 Timer/Counter B0 backend.
+//!
+//! The megaAVR-0 timers work differently from the classic ones: a TCB in
+//! periodic interrupt mode counts up to CCMP, then resets and raises its
+//! CAPT interrupt, which has to be acknowledged by software.
+
+use crate::config::Settings;
+
+/// The timer peripheral driving the time base.
+pub type Timer = crate::pac::TCB0;
+
+/// Returns the clock select bits for `prescaler`, if the timer supports it.
+/// A TCB can only divide the peripheral clock by 1 or 2.
+pub(crate) const fn clock_select(prescaler: u32) -> Option<u8> {
+    match prescaler {
+        1 => Some(0b00),
+        2 => Some(0b01),
+        _ => None,
+    }
+}
+
+/// Returns `true` if the timer can count `counts` per period.
+pub(crate) const fn valid_counts(counts: u32) -> bool {
+    counts >= 1 && counts <= 65536
+}
+
+pub(crate) fn configure(tcb0: &Timer, settings: &Settings) {
+    tcb0.ctrlb.write(|w| w.cntmode().int());
+    let top = (settings.counts - 1) as u16;
+    tcb0.ccmp.write(|w| unsafe { w.bits(top) });
+    tcb0.cnt.write(|w| unsafe { w.bits(0) });
+    tcb0.intctrl.write(|w| w.capt().set_bit());
+    tcb0.ctrla
+        .write(|w| unsafe { w.clksel().bits(settings.clock_select).enable().set_bit() });
+}
+
+fn regs() -> &'static crate::pac::tcb0::RegisterBlock {
+    unsafe { &*Timer::ptr() }
+}
+
+pub(crate) fn counts() -> u16 {
+    regs().cnt.read().bits()
+}
+
+pub(crate) fn compare_pending() -> bool {
+    regs().intflags.read().capt().bit_is_set()
+}
+
+pub(crate) fn clear_compare() {
+    regs().intflags.write(|w| w.capt().set_bit());
+}
+
+/// Stops the timer, keeping its count and flags.
+pub(crate) fn stop() {
+    regs().ctrla.modify(|_, w| w.enable().clear_bit());
+}
+
+/// Starts the timer again after [`stop`].
+pub(crate) fn start(_settings: &Settings) {
+    regs().ctrla.modify(|_, w| w.enable().set_bit());
+}
+
+/// Sets the count, e.g. to carry over the part of a period that passed
+/// while the timer was stopped.
+pub(crate) fn set_counts(counts: u16) {
+    regs().cnt.write(|w| unsafe { w.bits(counts) });
+}
+
+isr! {
+    fn TCB0_INT() {
+        // Unlike on the classic AVRs, the flag isn't cleared on ISR entry.
+        clear_compare();
+        crate::tick()
+    }
+}
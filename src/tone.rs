@@ -0,0 +1,267 @@
+//! Square waves for buzzers, like Arduino's `tone()`.
+//!
+//! Rather than claiming a timer of its own, the tone borrows the time base
+//! timer.  Where that timer has a free second compare unit, on the CTC and
+//! normal mode backends other than the ATtiny85's, the pin is toggled from
+//! its compare match interrupt, scheduled up to a timer period at a time
+//! until half of the tone's period has passed.  The edges are then placed
+//! to a timer count, 4 us with the default configuration, plus the
+//! interrupt latency, and frequencies up to
+//! `CLOCK_HZ / (2 * prescaler * (256 / prescaler + 2))`, about 20 kHz by
+//! default, can be produced.  Higher frequencies are clamped to that.
+//!
+//! Otherwise, with the `embassy` or `rtic` feature, which take that
+//! compare unit, or if the timer period is shorter than two compare steps,
+//! the pin is toggled from the time base ISR, at most once per timer period.
+//! The pitch is then only as precise as the period, and frequencies are
+//! limited to `500_000 / period_micros` Hz, 500 Hz with a 1 ms period.
+//! Higher frequencies are clamped to that as well.
+//!
+//! The pin is driven through a callback that toggles it, e.g. by writing
+//! its bit to the `PINx` register, since the ISR can't own a HAL pin.
+
+use core::cell;
+
+use avr_device::interrupt::{CriticalSection, Mutex};
+
+#[cfg(not(any(
+    feature = "attiny85",
+    feature = "atmega4809",
+    feature = "arduino-core",
+    feature = "embassy",
+    feature = "rtic"
+)))]
+use crate::config::Settings;
+use crate::time::{time_after, Duration, Instant};
+
+#[derive(Clone, Copy)]
+struct Tone {
+    toggle: fn(),
+    end: Option<Instant>,
+    high: bool,
+    schedule: Schedule,
+}
+
+#[derive(Clone, Copy)]
+enum Schedule {
+    // Toggled by the time base ISR once `next` has passed.
+    Tick {
+        half_period: Duration,
+        next: Instant,
+    },
+    // Toggled by compare unit B.  `half` and `wait`, the time from the last
+    // match at count `at` to the next toggle, are in 256ths of a timer count,
+    // so the fraction of a count carries over to the next half period.
+    #[cfg(not(any(
+        feature = "attiny85",
+        feature = "atmega4809",
+        feature = "arduino-core",
+        feature = "embassy",
+        feature = "rtic"
+    )))]
+    Compare { half: u32, wait: u32, at: u16 },
+}
+
+static TONE: Mutex<cell::Cell<Option<Tone>>> = Mutex::new(cell::Cell::new(None));
+
+/// Starts a square wave of `freq_hz` by calling `toggle` every half period,
+/// for `duration` or until [`no_tone`] if `None`.
+///
+/// The pin must be low when the tone starts, and is left low when it ends.
+/// A tone already playing is replaced.  Does nothing if `freq_hz` is zero,
+/// and clamps it to the highest frequency that can be produced, see the
+/// [module documentation](self).
+pub fn tone(toggle: fn(), freq_hz: u32, duration: Option<Duration>) {
+    if freq_hz == 0 {
+        return;
+    }
+    avr_device::interrupt::free(|cs| {
+        let now = crate::now_in(cs);
+        let cell = TONE.borrow(cs);
+        // Leave the pin low before switching to the new tone.
+        if let Some(previous) = cell.take() {
+            silence(&previous);
+        }
+        let mut tone = Tone {
+            toggle,
+            end: duration.map(|duration| now + duration),
+            high: false,
+            schedule: tick_schedule(cs, freq_hz, now),
+        };
+        #[cfg(not(any(
+            feature = "attiny85",
+            feature = "atmega4809",
+            feature = "arduino-core",
+            feature = "embassy",
+            feature = "rtic"
+        )))]
+        start_compare(cs, &mut tone, freq_hz);
+        cell.set(Some(tone));
+    });
+}
+
+/// Stops the tone, leaving the pin low.
+pub fn no_tone() {
+    avr_device::interrupt::free(|cs| {
+        if let Some(tone) = TONE.borrow(cs).take() {
+            silence(&tone);
+        }
+    })
+}
+
+/// Returns `true` while a tone is playing.
+pub fn is_playing() -> bool {
+    avr_device::interrupt::free(|cs| TONE.borrow(cs).get().is_some())
+}
+
+/// Ends the tone if its duration has passed, and otherwise toggles the pin
+/// if it is toggled from the tick and half a period has passed.  Called
+/// from the timer ISR.
+pub(crate) fn on_tick(cs: &CriticalSection) {
+    let now = crate::now_in(cs);
+    let cell = TONE.borrow(cs);
+    let mut tone = match cell.get() {
+        Some(tone) => tone,
+        None => return,
+    };
+    if ended(&tone, now) {
+        silence(&tone);
+        cell.set(None);
+        return;
+    }
+
+    if let Schedule::Tick { half_period, next } = tone.schedule {
+        if !time_after(next.as_micros(), now.as_micros()) {
+            (tone.toggle)();
+            tone.high = !tone.high;
+            tone.schedule = Schedule::Tick {
+                half_period,
+                next: next + half_period,
+            };
+            cell.set(Some(tone));
+        }
+    }
+}
+
+/// Toggles the pin if half a period has passed, and schedules the next
+/// match.  Called from the compare unit B ISR.
+#[cfg(not(any(
+    feature = "attiny85",
+    feature = "atmega4809",
+    feature = "arduino-core",
+    feature = "embassy",
+    feature = "rtic"
+)))]
+pub(crate) fn on_compare(cs: &CriticalSection) {
+    let cell = TONE.borrow(cs);
+    let mut tone = match cell.get() {
+        Some(tone) => tone,
+        None => return crate::timer::disarm_compare_b(),
+    };
+    if ended(&tone, crate::now_in(cs)) {
+        silence(&tone);
+        cell.set(None);
+        return;
+    }
+
+    if let Schedule::Compare {
+        half,
+        mut wait,
+        mut at,
+    } = tone.schedule
+    {
+        if wait < 256 {
+            (tone.toggle)();
+            tone.high = !tone.high;
+            wait += half;
+        }
+        schedule_match(&crate::SETTINGS.borrow(cs).get(), &mut wait, &mut at);
+        tone.schedule = Schedule::Compare { half, wait, at };
+        cell.set(Some(tone));
+    }
+}
+
+fn tick_schedule(cs: &CriticalSection, freq_hz: u32, now: Instant) -> Schedule {
+    // One toggle per period at most.
+    let period_micros = crate::SETTINGS.borrow(cs).get().micros_increment.max(1);
+    let freq_hz = freq_hz.min((500_000 / period_micros).max(1));
+    Schedule::Tick {
+        half_period: Duration::from_micros(500_000 / freq_hz),
+        next: now,
+    }
+}
+
+// Switches `tone` to compare unit B, if the period leaves room for two
+// compare steps, and raises the pin right away.
+#[cfg(not(any(
+    feature = "attiny85",
+    feature = "atmega4809",
+    feature = "arduino-core",
+    feature = "embassy",
+    feature = "rtic"
+)))]
+fn start_compare(cs: &CriticalSection, tone: &mut Tone, freq_hz: u32) {
+    let settings = crate::SETTINGS.borrow(cs).get();
+    let min = crate::min_compare_step(&settings);
+    if settings.counts < 2 * min {
+        return;
+    }
+    let half = crate::CLOCK_HZ as u64 * 256 / (2 * freq_hz as u64 * settings.prescaler as u64);
+    let half = (half as u32).max(min << 8);
+    let mut wait = half;
+    let mut at = crate::timer::counts();
+    (tone.toggle)();
+    tone.high = true;
+    schedule_match(&settings, &mut wait, &mut at);
+    tone.schedule = Schedule::Compare { half, wait, at };
+}
+
+// Arms compare unit B for the next step towards the toggle `wait` after the
+// match at `at`.  A step is at most a period, and never leaves less than
+// `min_compare_step` for the one after, so the last step may split the
+// remainder.
+#[cfg(not(any(
+    feature = "attiny85",
+    feature = "atmega4809",
+    feature = "arduino-core",
+    feature = "embassy",
+    feature = "rtic"
+)))]
+fn schedule_match(settings: &Settings, wait: &mut u32, at: &mut u16) {
+    let counts = settings.counts;
+    let whole = *wait >> 8;
+    let step = if whole < counts {
+        whole
+    } else if whole >= counts + crate::min_compare_step(settings) {
+        counts
+    } else {
+        whole / 2
+    };
+    *wait -= step << 8;
+    *at = ((*at as u32 + step) % counts) as u16;
+    crate::timer::arm_compare_b(*at);
+}
+
+fn ended(tone: &Tone, now: Instant) -> bool {
+    match tone.end {
+        Some(end) => !time_after(end.as_micros(), now.as_micros()),
+        None => false,
+    }
+}
+
+// Leaves the pin low and stops the compare interrupt.
+fn silence(tone: &Tone) {
+    if tone.high {
+        (tone.toggle)();
+    }
+    #[cfg(not(any(
+        feature = "attiny85",
+        feature = "atmega4809",
+        feature = "arduino-core",
+        feature = "embassy",
+        feature = "rtic"
+    )))]
+    if let Schedule::Compare { .. } = tone.schedule {
+        crate::timer::disarm_compare_b();
+    }
+}
@@ -0,0 +1,165 @@
+//! A circular buffer of timestamped trace records, for finding out what
+//! happened just before a timing bug.
+//!
+//! Unlike an [`EventQueue`](crate::event_queue::EventQueue), a full
+//! [`TraceBuffer`] overwrites its oldest record, so it always holds the
+//! last `N` events, like a flight recorder.  Records are a time, an event
+//! id and an argument, both chosen by the application, and can be written
+//! from any context, including ISRs.  [`TraceBuffer::dump`] prints them
+//! oldest first and empties the buffer, e.g. from a shell command.
+
+use core::cell;
+
+use avr_device::interrupt::{CriticalSection, Mutex};
+use embedded_hal::serial::Write;
+
+use crate::telemetry::{write_decimal, write_str};
+use crate::time::Instant;
+
+/// A trace record.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Record {
+    /// When it was recorded.
+    pub at: Instant,
+    /// The event id passed to [`TraceBuffer::record`].
+    pub event: u8,
+    /// The argument passed to [`TraceBuffer::record`].
+    pub arg: u16,
+}
+
+/// A circular buffer of the last `N` trace records.
+///
+/// It is meant to be placed in a `static`, e.g.
+/// `static TRACE: TraceBuffer<32> = TraceBuffer::new();`.  A record takes 8
+/// bytes of RAM.  `N` must be at least one, which is checked at compile
+/// time.
+pub struct TraceBuffer<const N: usize> {
+    records: Mutex<cell::Cell<[Option<Record>; N]>>,
+    head: Mutex<cell::Cell<usize>>,
+    len: Mutex<cell::Cell<usize>>,
+    overwritten: Mutex<cell::Cell<u16>>,
+}
+
+impl<const N: usize> TraceBuffer<N> {
+    // Compile-time assertion, as in `TimerConfig`: an empty buffer would
+    // divide by zero when wrapping its indices.
+    const NOT_EMPTY: () = [()][(N == 0) as usize];
+
+    /// Creates an empty buffer.
+    pub const fn new() -> Self {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::NOT_EMPTY;
+        TraceBuffer {
+            records: Mutex::new(cell::Cell::new([None; N])),
+            head: Mutex::new(cell::Cell::new(0)),
+            len: Mutex::new(cell::Cell::new(0)),
+            overwritten: Mutex::new(cell::Cell::new(0)),
+        }
+    }
+
+    /// Records `event` with `arg` at the current time.
+    pub fn record(&self, event: u8, arg: u16) {
+        avr_device::interrupt::free(|cs| self.record_in(cs, event, arg))
+    }
+
+    /// Like [`record`](TraceBuffer::record), but within a critical section
+    /// the caller already holds, e.g. in an ISR.
+    pub fn record_in(&self, cs: &CriticalSection, event: u8, arg: u16) {
+        let record = Record {
+            at: crate::now_in(cs),
+            event,
+            arg,
+        };
+        let head = self.head.borrow(cs);
+        let len = self.len.borrow(cs);
+        if len.get() == N {
+            // Replace the oldest record.
+            self.records(cs)[head.get()].set(Some(record));
+            head.set((head.get() + 1) % N);
+            let overwritten = self.overwritten.borrow(cs);
+            overwritten.set(overwritten.get().saturating_add(1));
+        } else {
+            self.records(cs)[(head.get() + len.get()) % N].set(Some(record));
+            len.set(len.get() + 1);
+        }
+    }
+
+    /// Takes the oldest record.
+    pub fn pop(&self) -> Option<Record> {
+        avr_device::interrupt::free(|cs| {
+            let len = self.len.borrow(cs);
+            if len.get() == 0 {
+                return None;
+            }
+            let head = self.head.borrow(cs);
+            let record = self.records(cs)[head.get()].take();
+            head.set((head.get() + 1) % N);
+            len.set(len.get() - 1);
+            record
+        })
+    }
+
+    /// Returns the number of records held.
+    pub fn len(&self) -> usize {
+        avr_device::interrupt::free(|cs| self.len.borrow(cs).get())
+    }
+
+    /// Returns `true` if no records are held.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of records overwritten since the buffer was last
+    /// emptied, saturating at `u16::MAX`.
+    pub fn overwritten(&self) -> u16 {
+        avr_device::interrupt::free(|cs| self.overwritten.borrow(cs).get())
+    }
+
+    /// Discards all records and resets the overwritten count.
+    pub fn clear(&self) {
+        avr_device::interrupt::free(|cs| {
+            for record in self.records(cs) {
+                record.set(None);
+            }
+            self.head.borrow(cs).set(0);
+            self.len.borrow(cs).set(0);
+            self.overwritten.borrow(cs).set(0);
+        })
+    }
+
+    /// Writes the records oldest first as lines of time in microseconds,
+    /// event id and argument, e.g. `1234567 3 42`, each ending in CRLF,
+    /// and removes them.
+    ///
+    /// A line with the number of records lost, e.g. `5 overwritten`, comes
+    /// first if the buffer had filled up.  Records are taken one at a time,
+    /// so interrupts aren't held off while writing, and records added
+    /// meanwhile are dumped too.
+    pub fn dump<W: Write<u8>>(&self, writer: &mut W) -> Result<(), W::Error> {
+        let overwritten = avr_device::interrupt::free(|cs| self.overwritten.borrow(cs).replace(0));
+        if overwritten > 0 {
+            write_decimal(writer, overwritten as i64)?;
+            write_str(writer, " overwritten\r\n")?;
+        }
+        while let Some(record) = self.pop() {
+            write_decimal(writer, record.at.as_micros() as i64)?;
+            nb::block!(writer.write(b' '))?;
+            write_decimal(writer, record.event as i64)?;
+            nb::block!(writer.write(b' '))?;
+            write_decimal(writer, record.arg as i64)?;
+            write_str(writer, "\r\n")?;
+        }
+        Ok(())
+    }
+
+    fn records<'cs>(&'cs self, cs: &'cs CriticalSection) -> &'cs [cell::Cell<Option<Record>>] {
+        let records: &cell::Cell<[Option<Record>]> = self.records.borrow(cs);
+        records.as_slice_of_cells()
+    }
+}
+
+impl<const N: usize> Default for TraceBuffer<N> {
+    fn default() -> Self {
+        TraceBuffer::new()
+    }
+}
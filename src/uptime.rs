@@ -0,0 +1,148 @@
+//! Lifetime uptime accounting in EEPROM.
+//!
+//! An [`UptimeLog`] keeps the total time the device has run across resets,
+//! for e.g. service intervals or lifetime hours.  It is checkpointed to a
+//! ring of records in EEPROM, each write going to the slot after the
+//! previous one, so each cell only wears at the checkpoint rate divided by
+//! the number of slots.  With 16 slots and a checkpoint every 10 minutes,
+//! the rated 100,000 erase cycles last about 30 years.
+//!
+//! Each record holds the total seconds, the seconds of the session that
+//! wrote it and a check byte written last, so a record torn by a reset
+//! during the write is ignored.  At most the time since the last checkpoint
+//! is lost at a reset.
+
+use embedded_hal::serial::Write;
+
+use crate::eeprom;
+use crate::millis64;
+use crate::telemetry::{write_decimal, write_str};
+use crate::time::Duration;
+
+pub use crate::eeprom::Eeprom;
+
+/// The EEPROM bytes used by each slot.
+pub const RECORD_LEN: u16 = 9;
+
+/// A monotonic count of seconds of operation, persisted in EEPROM.
+pub struct UptimeLog {
+    base: u16,
+    slots: u16,
+    next_slot: u16,
+    previous_total: u32,
+    previous_session: u32,
+    last_checkpoint: u64,
+}
+
+impl UptimeLog {
+    /// Restores the log kept in `slots` records starting at EEPROM address
+    /// `base`, i.e. in `slots * RECORD_LEN` bytes.  Erased EEPROM starts the
+    /// count at zero.
+    ///
+    /// The session is timed with [`millis64`], so the time base must be
+    /// running and not be restarted.  Panics if `slots` is zero.
+    pub fn restore(eeprom: &Eeprom, base: u16, slots: u16) -> Self {
+        assert!(slots > 0);
+        let mut log = UptimeLog {
+            base,
+            slots,
+            next_slot: 0,
+            previous_total: 0,
+            previous_session: 0,
+            last_checkpoint: 0,
+        };
+        let mut newest = None;
+        for slot in 0..slots {
+            if let Some((total, session)) = log.read_record(eeprom, slot) {
+                if newest.map_or(true, |(newest_total, _, _)| total > newest_total) {
+                    newest = Some((total, session, slot));
+                }
+            }
+        }
+        if let Some((total, session, slot)) = newest {
+            log.previous_total = total;
+            log.previous_session = session;
+            log.next_slot = (slot + 1) % slots;
+        }
+        log
+    }
+
+    /// Returns the total seconds of operation, including this session.
+    pub fn total_secs(&self) -> u32 {
+        self.previous_total.saturating_add(session_secs())
+    }
+
+    /// Returns the total seconds of operation before this session, as of
+    /// the last checkpoint.
+    pub fn previous_total_secs(&self) -> u32 {
+        self.previous_total
+    }
+
+    /// Returns how long the previous session ran, as of its last
+    /// checkpoint.
+    pub fn previous_session_secs(&self) -> u32 {
+        self.previous_session
+    }
+
+    /// Stores the current total in the next slot.
+    ///
+    /// This blocks for up to 30 ms while the EEPROM is written.
+    pub fn checkpoint(&mut self, eeprom: &Eeprom) {
+        self.last_checkpoint = millis64();
+        let total = self.total_secs();
+        let session = session_secs();
+        let mut record = [0u8; RECORD_LEN as usize];
+        record[..4].copy_from_slice(&total.to_le_bytes());
+        record[4..8].copy_from_slice(&session.to_le_bytes());
+        record[8] = check(&record[..8]);
+
+        let address = self.base + self.next_slot * RECORD_LEN;
+        for (offset, &byte) in record.iter().enumerate() {
+            eeprom::write(eeprom, address + offset as u16, byte);
+        }
+        self.next_slot = (self.next_slot + 1) % self.slots;
+    }
+
+    /// Calls [`checkpoint`](UptimeLog::checkpoint) if `interval` has passed
+    /// since the last one.  Call this from the main loop.
+    pub fn poll(&mut self, eeprom: &Eeprom, interval: Duration) {
+        if millis64() - self.last_checkpoint >= interval.as_millis() as u64 {
+            self.checkpoint(eeprom);
+        }
+    }
+
+    /// Writes the length of the previous session and the total before this
+    /// one as a line, e.g. `previous session: 3605 s, total: 86410 s`.
+    pub fn report<W: Write<u8>>(&self, writer: &mut W) -> Result<(), W::Error> {
+        write_str(writer, "previous session: ")?;
+        write_decimal(writer, self.previous_session as i64)?;
+        write_str(writer, " s, total: ")?;
+        write_decimal(writer, self.previous_total as i64)?;
+        write_str(writer, " s\r\n")
+    }
+
+    fn read_record(&self, eeprom: &Eeprom, slot: u16) -> Option<(u32, u32)> {
+        let address = self.base + slot * RECORD_LEN;
+        let mut record = [0u8; RECORD_LEN as usize];
+        for (offset, byte) in record.iter_mut().enumerate() {
+            *byte = eeprom::read(eeprom, address + offset as u16);
+        }
+        if record[8] != check(&record[..8]) {
+            return None;
+        }
+        let mut total = [0u8; 4];
+        let mut session = [0u8; 4];
+        total.copy_from_slice(&record[..4]);
+        session.copy_from_slice(&record[4..8]);
+        Some((u32::from_le_bytes(total), u32::from_le_bytes(session)))
+    }
+}
+
+fn session_secs() -> u32 {
+    (millis64() / 1000) as u32
+}
+
+// The complement of the byte sum, so that erased cells (all ones) fail it.
+fn check(bytes: &[u8]) -> u8 {
+    !bytes.iter().fold(0u8, |sum, &byte| sum.wrapping_add(byte))
+}
@@ -0,0 +1,43 @@
+//! USART0 registers shared by the buffered serial transmitter and receiver
+//! and the serial panic handler.
+
+#[cfg(not(any(feature = "atmega328p", feature = "atmega2560")))]
+compile_error!("the serial features are only supported on the ATmega328P and ATmega2560");
+
+use crate::CLOCK_HZ;
+
+/// The USART used for the serial port.
+pub type Usart = crate::pac::USART0;
+
+pub(crate) type Registers = crate::pac::usart0::RegisterBlock;
+
+// UCSR0A bits.
+pub(crate) const U2X0: u8 = 1 << 1;
+pub(crate) const DOR0: u8 = 1 << 3;
+pub(crate) const FE0: u8 = 1 << 4;
+pub(crate) const UDRE0: u8 = 1 << 5;
+pub(crate) const TXC0: u8 = 1 << 6;
+// UCSR0B bits.
+pub(crate) const TXEN0: u8 = 1 << 3;
+pub(crate) const RXEN0: u8 = 1 << 4;
+pub(crate) const UDRIE0: u8 = 1 << 5;
+pub(crate) const RXCIE0: u8 = 1 << 7;
+// UCSR0C: asynchronous, 8 data bits, no parity, one stop bit.
+const FORMAT_8N1: u8 = 0b110;
+
+/// Sets the baud rate and frame format, leaving the transmitter and
+/// receiver enables untouched.
+pub(crate) fn configure(usart: &Registers, baud: u32) {
+    // Double speed mode halves the baud rate error at common rates.
+    let ubrr = (CLOCK_HZ / 4 / baud - 1) / 2;
+    usart.ubrr0.write(|w| unsafe { w.bits(ubrr as u16) });
+    usart.ucsr0a.write(|w| unsafe { w.bits(U2X0 | TXC0) });
+    usart.ucsr0c.write(|w| unsafe { w.bits(FORMAT_8N1) });
+}
+
+/// Sets or clears bits of UCSR0B.
+pub(crate) fn modify_control(usart: &Registers, set: u8, clear: u8) {
+    usart
+        .ucsr0b
+        .modify(|r, w| unsafe { w.bits((r.bits() & !clear) | set) });
+}
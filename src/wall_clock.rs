@@ -0,0 +1,187 @@
+//! A software wall clock layered on the microsecond counter.
+
+use crate::micros64;
+
+const SECS_PER_DAY: u32 = 86_400;
+
+/// A calendar date and time of day in UTC.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DateTime {
+    pub year: u16,
+    /// The month, from 1 to 12.
+    pub month: u8,
+    /// The day of the month, from 1 to 31.
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+impl DateTime {
+    /// Converts a Unix timestamp into a date and time.
+    pub fn from_unix(timestamp: u32) -> Self {
+        let days = timestamp / SECS_PER_DAY;
+        let secs = timestamp % SECS_PER_DAY;
+
+        // Days since 0000-03-01, counting years from March so the leap day
+        // falls at the end.  See Howard Hinnant's `civil_from_days`.
+        let z = days + 719_468;
+        let era = z / 146_097;
+        let doe = z - era * 146_097;
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + (month <= 2) as u32;
+
+        DateTime {
+            year: year as u16,
+            month: month as u8,
+            day: day as u8,
+            hour: (secs / 3600) as u8,
+            minute: (secs / 60 % 60) as u8,
+            second: (secs % 60) as u8,
+        }
+    }
+
+    /// Converts the date and time into a Unix timestamp.  Dates before 1970
+    /// are not supported.
+    pub fn to_unix(&self) -> u32 {
+        let month = self.month as u32;
+        let year = self.year as u32 - (month <= 2) as u32;
+        let era = year / 400;
+        let yoe = year - era * 400;
+        let mp = if month > 2 { month - 3 } else { month + 9 };
+        let doy = (153 * mp + 2) / 5 + self.day as u32 - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+        let days = era * 146_097 + doe - 719_468;
+
+        days * SECS_PER_DAY + self.hour as u32 * 3600 + self.minute as u32 * 60 + self.second as u32
+    }
+
+    /// Returns the day of the week, from 0 for Sunday to 6 for Saturday.
+    pub fn weekday(&self) -> u8 {
+        // 1970-01-01 was a Thursday.
+        ((self.to_unix() / SECS_PER_DAY + 4) % 7) as u8
+    }
+}
+
+/// A wall clock that keeps the time of day once it has been set.
+///
+/// The clock records the Unix time it was set to along with a timestamp of
+/// the time base, and derives the current time from the elapsed
+/// microseconds, so it drifts only as much as the crystal.  Until it is set
+/// it counts from midnight on 1970-01-01.
+#[derive(Clone, Copy, Debug)]
+pub struct WallClock {
+    unix_at_set: u32,
+    set_at: u64,
+}
+
+impl WallClock {
+    /// Creates a clock reading 1970-01-01 00:00:00 now.
+    pub fn new() -> Self {
+        WallClock {
+            unix_at_set: 0,
+            set_at: micros64(),
+        }
+    }
+
+    /// Sets the clock to the given Unix timestamp.
+    pub fn set_unix(&mut self, timestamp: u32) {
+        self.set_unix_at(timestamp, micros64());
+    }
+
+    /// Sets the clock to the given Unix timestamp as of an earlier
+    /// [`micros64`] timestamp, e.g. when a time reference was received.
+    pub fn set_unix_at(&mut self, timestamp: u32, at: u64) {
+        self.unix_at_set = timestamp;
+        self.set_at = at;
+    }
+
+    /// Sets the clock to the given date and time.
+    pub fn set(&mut self, date_time: &DateTime) {
+        self.set_unix(date_time.to_unix());
+    }
+
+    /// Sets the time of day, keeping the current date.
+    pub fn set_time(&mut self, hour: u8, minute: u8, second: u8) {
+        let midnight = self.unix() / SECS_PER_DAY * SECS_PER_DAY;
+        self.set_unix(midnight + hour as u32 * 3600 + minute as u32 * 60 + second as u32);
+    }
+
+    /// Returns the current Unix timestamp.
+    pub fn unix(&self) -> u32 {
+        let elapsed = self.elapsed_micros() / 1_000_000;
+        self.unix_at_set.wrapping_add(elapsed as u32)
+    }
+
+    /// Returns the microseconds elapsed in the current second.
+    pub fn subsec_micros(&self) -> u32 {
+        (self.elapsed_micros() % 1_000_000) as u32
+    }
+
+    // The time since the clock was set, or zero if it was set as of a
+    // timestamp that hasn't been reached yet, or before the counters were
+    // last reset.
+    fn elapsed_micros(&self) -> u64 {
+        micros64().saturating_sub(self.set_at)
+    }
+
+    /// Returns the current date and time in UTC.
+    pub fn now_utc(&self) -> DateTime {
+        DateTime::from_unix(self.unix())
+    }
+}
+
+impl Default for WallClock {
+    fn default() -> Self {
+        WallClock::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date_time(year: u16, month: u8, day: u8, hour: u8, minute: u8, second: u8) -> DateTime {
+        DateTime {
+            year,
+            month,
+            day,
+            hour,
+            minute,
+            second,
+        }
+    }
+
+    #[test]
+    fn epoch() {
+        let epoch = date_time(1970, 1, 1, 0, 0, 0);
+        assert_eq!(epoch.to_unix(), 0);
+        assert_eq!(DateTime::from_unix(0), epoch);
+        assert_eq!(epoch.weekday(), 4);
+    }
+
+    #[test]
+    fn leap_days() {
+        assert_eq!(date_time(2000, 2, 29, 12, 0, 0).to_unix(), 951_825_600);
+        assert_eq!(date_time(2024, 2, 29, 0, 0, 0).to_unix(), 1_709_164_800);
+        assert_eq!(
+            date_time(2024, 3, 1, 0, 0, 0).to_unix() - date_time(2024, 2, 28, 0, 0, 0).to_unix(),
+            2 * SECS_PER_DAY
+        );
+        assert_eq!(
+            DateTime::from_unix(1_709_164_800),
+            date_time(2024, 2, 29, 0, 0, 0)
+        );
+    }
+
+    #[test]
+    fn last_representable_second() {
+        let last = date_time(2106, 2, 7, 6, 28, 15);
+        assert_eq!(last.to_unix(), u32::MAX);
+        assert_eq!(DateTime::from_unix(u32::MAX), last);
+    }
+}
@@ -0,0 +1,146 @@
+//! The hardware watchdog, fed only while the time base is running.
+//!
+//! A watchdog fed unconditionally from the main loop catches a stuck loop,
+//! but not a timer that has stopped interrupting, after which everything
+//! waiting on a deadline silently hangs.  [`Watchdog::feed`] only resets the
+//! watchdog if the counter has been advanced by the timer ISR since the
+//! previous feed, so either failure resets the device.
+//!
+//! After a watchdog reset [`reset::take_cause`](crate::reset::take_cause)
+//! returns [`Cause::Watchdog`](crate::reset::Cause::Watchdog).  The
+//! watchdog stays enabled through the reset with its shortest timeout, so
+//! start it again or [`disable`] it early at boot.  Turning it off means
+//! clearing the reset flags, so [`disable`] takes and returns the cause
+//! in place of `take_cause`, and [`Watchdog::stop`] should only be called
+//! once the cause has been read.
+
+use crate::reset::{self, Cause};
+use crate::time::Duration;
+
+/// The watchdog timer peripheral.
+pub type Wdt = crate::pac::WDT;
+
+// WDTCSR (WDTCR on the ATtiny85) bits.
+const WDE: u8 = 1 << 3;
+const WDCE: u8 = 1 << 4;
+const WDP3: u8 = 1 << 5;
+const WDIE: u8 = 1 << 6;
+
+/// The time without a feed after which the watchdog resets the device,
+/// nominally, from its 128 kHz oscillator.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Timeout {
+    Ms16,
+    Ms32,
+    Ms64,
+    Ms125,
+    Ms250,
+    Ms500,
+    S1,
+    S2,
+    S4,
+    S8,
+}
+
+impl Timeout {
+    /// Returns the exact nominal timeout: 2048 cycles of the 128 kHz
+    /// oscillator, 16 ms, doubled for each step, so `Ms125` is 128 ms.
+    pub const fn duration(self) -> Duration {
+        Duration::from_millis(16 << (self as u32))
+    }
+
+    // The WDP3:0 prescaler bits, with WDP3 out of line.
+    fn bits(self) -> u8 {
+        let prescaler = self as u8;
+        (prescaler & 0b111) | if prescaler & 0b1000 != 0 { WDP3 } else { 0 }
+    }
+}
+
+/// The running watchdog.
+pub struct Watchdog {
+    wdt: Wdt,
+    last_micros: u32,
+}
+
+impl Watchdog {
+    /// Starts the watchdog in reset mode with the given timeout.
+    ///
+    /// The timeout should span several timer periods, so that a feed can
+    /// always find the counter advanced.
+    pub fn start(wdt: Wdt, timeout: Timeout) -> Self {
+        avr_device::asm::wdr();
+        // The new configuration must be written within four cycles of
+        // setting WDCE.
+        avr_device::interrupt::free(|_| {
+            write(&wdt, WDCE | WDE);
+            write(&wdt, WDE | timeout.bits());
+        });
+        Watchdog {
+            wdt,
+            last_micros: crate::ticked_micros(),
+        }
+    }
+
+    /// Resets the watchdog if the timer ISR has advanced the counter since
+    /// the last feed, and returns whether it did.
+    ///
+    /// Call this from the main loop more often than the timeout.
+    pub fn feed(&mut self) -> bool {
+        let micros = crate::ticked_micros();
+        if micros == self.last_micros {
+            return false;
+        }
+        self.last_micros = micros;
+        avr_device::asm::wdr();
+        true
+    }
+
+    /// Disables the watchdog and releases the peripheral.  This clears the
+    /// reset flags like [`disable`].
+    pub fn stop(self) -> Wdt {
+        disable(&self.wdt);
+        self.wdt
+    }
+}
+
+/// Disables the watchdog, e.g. at boot after a watchdog reset, which
+/// leaves it running with the shortest timeout, and returns the cause of
+/// the last reset.
+///
+/// The watchdog can't be turned off while the WDRF reset flag is set, so
+/// this reads and clears the flags with [`reset::take_cause`], and a later
+/// `take_cause` returns [`Cause::Unknown`].
+pub fn disable(wdt: &Wdt) -> Cause {
+    avr_device::asm::wdr();
+    avr_device::interrupt::free(|_| {
+        let cause = reset::take_cause();
+        write(wdt, WDCE | WDE);
+        write(wdt, 0);
+        cause
+    })
+}
+
+/// Starts the watchdog in interrupt mode, in which it wakes the CPU after
+/// `timeout` instead of resetting it.
+pub(crate) fn start_interrupt(wdt: &Wdt, timeout: Timeout) {
+    avr_device::asm::wdr();
+    avr_device::interrupt::free(|_| {
+        write(wdt, WDCE | WDE);
+        write(wdt, WDIE | timeout.bits());
+    });
+}
+
+/// Stops the watchdog after [`start_interrupt`].
+pub(crate) fn stop_interrupt(wdt: &Wdt) {
+    avr_device::interrupt::free(|_| {
+        write(wdt, WDCE | WDE);
+        write(wdt, 0);
+    });
+}
+
+fn write(wdt: &Wdt, bits: u8) {
+    #[cfg(not(feature = "attiny85"))]
+    wdt.wdtcsr.write(|w| unsafe { w.bits(bits) });
+    #[cfg(feature = "attiny85")]
+    wdt.wdtcr.write(|w| unsafe { w.bits(bits) });
+}
@@ -0,0 +1,157 @@
+//! WS2812 ("NeoPixel") LED strips.
+//!
+//! The LEDs take a 800 kHz bit stream with no clock: each bit is a 1.25 us
+//! period starting high, staying high for about 0.4 us for a zero or 0.8 us
+//! for a one.  At 16 MHz that is 20 CPU cycles per bit, written out by a
+//! hand-counted assembly loop.
+//!
+//! Interrupts are masked while the 24 bits of each LED are sent, which takes
+//! 30 us, and enabled between LEDs.  The strip only latches the data once
+//! the line has been low for 50 us, so this is safe as long as no ISR runs
+//! for that long.  The time base ISR misses its compare matches while
+//! masked; the number of timer periods that passed is worked out from the
+//! known length of the loop and added to the counters afterwards, so
+//! [`micros`](crate::micros) doesn't fall behind however long the strip is.
+
+#[cfg(feature = "atmega4809")]
+compile_error!("the `ws2812` feature doesn't support the ATmega4809's instruction timing");
+
+#[cfg(any(
+    feature = "clock-8mhz",
+    feature = "clock-20mhz",
+    all(feature = "attiny85", not(feature = "clock-16mhz"))
+))]
+compile_error!("the `ws2812` feature requires a 16 MHz clock");
+
+#[cfg(target_arch = "avr")]
+use core::arch::asm;
+
+use crate::delay::delay_until;
+use crate::time::{Duration, Instant};
+use crate::timer;
+
+// The low time after which the LEDs latch their data, with a margin for
+// newer WS2812B parts that need 280 us.
+const LATCH: Duration = Duration::from_micros(300);
+
+// The CPU cycles taken by the loop per byte, see `write_led`.
+const CYCLES_PER_BYTE: u32 = 165;
+
+/// The color of an LED.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Rgb {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Rgb {
+    /// Creates a color from its red, green and blue components.
+    pub const fn new(r: u8, g: u8, b: u8) -> Self {
+        Rgb { r, g, b }
+    }
+}
+
+/// A strip of WS2812 LEDs on a port pin.
+pub struct Ws2812 {
+    port: *mut u8,
+    mask: u8,
+    last_write: Option<Instant>,
+}
+
+impl Ws2812 {
+    /// Creates a driver for the LEDs on bit `bit` of the port whose `PORTx`
+    /// data register is at `port`, e.g. `dp.PORTD.portd.as_ptr()` for pin 6
+    /// of the Uno with `bit` 6.
+    ///
+    /// # Safety
+    ///
+    /// `port` must point to a `PORTx` register, and the pin must already be
+    /// configured as an output.  Other pins on the same port may be changed
+    /// by ISRs, but not by code interrupted between two LEDs.
+    pub unsafe fn new(port: *mut u8, bit: u8) -> Self {
+        Ws2812 {
+            port,
+            mask: 1 << bit,
+            last_write: None,
+        }
+    }
+
+    /// Sends the colors of the LEDs, starting from the one nearest to the
+    /// pin.  Waits for the previous data to latch first if necessary.
+    pub fn write(&mut self, leds: &[Rgb]) {
+        if let Some(last) = self.last_write {
+            delay_until(last + LATCH);
+        }
+        for led in leds {
+            // The LEDs expect green first.
+            self.write_led(&[led.g, led.r, led.b]);
+        }
+        self.last_write = Some(crate::now());
+    }
+
+    fn write_led(&self, grb: &[u8; 3]) {
+        loop {
+            let sent = avr_device::interrupt::free(|cs| {
+                // Let the ISR take a pending compare match first rather than
+                // hold it off for the whole write.  The flag is checked after
+                // reading the timer in case it just wrapped.
+                let start_counts = timer::counts();
+                if timer::compare_pending() {
+                    return false;
+                }
+                let port = unsafe { self.port.read_volatile() };
+                let high = port | self.mask;
+                let low = port & !self.mask;
+
+                // Cycle counts from the start of each bit are on the right.
+                // A bit takes 20 cycles; the outer loop stretches the low
+                // time before the first bit of each byte by 5 cycles, which
+                // the LEDs tolerate.  A byte takes 165 cycles, the last one
+                // 164.
+                #[cfg(target_arch = "avr")]
+                unsafe {
+                    asm!(
+                        "2:",
+                        "ld {byte}, {data}+",    // -3
+                        "ldi {bit}, 8",          // -1
+                        "1:",
+                        "st {port}, {high}",     // 0: high
+                        "mov {tmp}, {low}",      // 2
+                        "sbrc {byte}, 7",        // 3
+                        "mov {tmp}, {high}",     // 4
+                        "nop",                   // 5
+                        "st {port}, {tmp}",      // 6: low for a zero
+                        "lsl {byte}",            // 8
+                        "nop",                   // 9
+                        "nop",                   // 10
+                        "nop",                   // 11
+                        "nop",                   // 12
+                        "st {port}, {low}",      // 13: low for a one
+                        "dec {bit}",             // 15
+                        "nop",                   // 16
+                        "nop",                   // 17
+                        "brne 1b",               // 18
+                        "dec {count}",           // 19
+                        "brne 2b",               // 20
+                        data = inout(reg_ptr) grb.as_ptr() as u16 => _,
+                        port = in(reg_ptr) self.port as u16,
+                        count = inout(reg) grb.len() as u8 => _,
+                        high = in(reg) high,
+                        low = in(reg) low,
+                        byte = out(reg) _,
+                        bit = out(reg_upper) _,
+                        tmp = out(reg) _,
+                        options(nostack),
+                    );
+                }
+
+                crate::catch_up(cs, start_counts, false, grb.len() as u32 * CYCLES_PER_BYTE);
+                true
+            });
+            if sent {
+                return;
+            }
+        }
+    }
+}